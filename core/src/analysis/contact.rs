@@ -0,0 +1,169 @@
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LeadOff {
+    // Reported by the DC lead-off input of the front-end
+    Electrode,
+    // Input stuck at one of the ADC rails
+    Saturation,
+    FlatLine,
+    Noise,
+}
+
+impl LeadOff {
+    pub fn name(self) -> &'static str {
+        match self {
+            LeadOff::Electrode => "ELECTRODE",
+            LeadOff::Saturation => "SATURATED",
+            LeadOff::FlatLine => "FLAT LINE",
+            LeadOff::Noise => "NOISE",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Contact {
+    Good,
+    LeadOff(LeadOff),
+}
+
+// Electrode contact monitor.
+//
+// The stream is judged in blocks of 500 ms. Rail saturation is taken from the raw ADC
+// code, flat line and noise from the filtered signal, where noise is measured as the
+// rate of turns, local extremes with a swing above 1/16 of the previous block range.
+// Saturation and the lead-off input take effect after one block, flat line and noise
+// after two, the contact is good again after 2 s without any problem.
+pub struct ContactMonitor {
+    block_len: u32,
+    position: u32,
+    // ADC code limits considered to be rails
+    rails: (u16, u16),
+    saturated: u32,
+    electrode: u32,
+    min: u16,
+    max: u16,
+    turns: u32,
+    rising: bool,
+    extreme: u16,
+    swing: u16,
+    bad_blocks: u8,
+    good_blocks: u8,
+    contact: Contact,
+}
+
+impl ContactMonitor {
+    const BLOCK_MS: u32 = 500;
+    // Distance of the rails from the ends of the ADC range in percent
+    const RAIL_PERCENT: u32 = 2;
+    // Peak to peak in mV below which the signal is flat
+    const FLAT_MV: u16 = 5;
+    const MIN_SWING_MV: u16 = 5;
+    const MAX_TURNS_PER_SECOND: u32 = 30;
+    const ONSET_BLOCKS: u8 = 2;
+    const RECOVERY_BLOCKS: u8 = 4;
+
+    pub fn new(sample_rate: u32, full_scale: u16) -> Self {
+        let rail = (full_scale as u32 * ContactMonitor::RAIL_PERCENT / 100) as u16;
+        ContactMonitor {
+            block_len: (sample_rate * ContactMonitor::BLOCK_MS / 1000).max(1),
+            position: 0,
+            rails: (rail, full_scale - rail),
+            saturated: 0,
+            electrode: 0,
+            min: u16::MAX,
+            max: 0,
+            turns: 0,
+            rising: true,
+            extreme: 0,
+            swing: ContactMonitor::MIN_SWING_MV,
+            bad_blocks: 0,
+            good_blocks: 0,
+            contact: Contact::Good,
+        }
+    }
+
+    // Takes the raw ADC code, the filtered sample in mV and the state of the lead-off
+    // input, `false` if there is none
+    pub fn process(&mut self, input: u16, sample: u16, electrode_off: bool) -> Contact {
+        if input <= self.rails.0 || input >= self.rails.1 {
+            self.saturated += 1;
+        }
+        if electrode_off {
+            self.electrode += 1;
+        }
+        self.min = self.min.min(sample);
+        self.max = self.max.max(sample);
+        self.turn(sample);
+
+        self.position += 1;
+        if self.position >= self.block_len {
+            self.evaluate();
+        }
+        self.contact
+    }
+
+    pub fn contact(&self) -> Contact {
+        self.contact
+    }
+
+    fn turn(&mut self, sample: u16) {
+        if self.rising {
+            if sample >= self.extreme {
+                self.extreme = sample;
+            } else if self.extreme - sample > self.swing {
+                self.turns += 1;
+                self.rising = false;
+                self.extreme = sample;
+            }
+        } else if sample <= self.extreme {
+            self.extreme = sample;
+        } else if sample - self.extreme > self.swing {
+            self.turns += 1;
+            self.rising = true;
+            self.extreme = sample;
+        }
+    }
+
+    fn evaluate(&mut self) {
+        let half = self.block_len / 2;
+        let range = self.max.saturating_sub(self.min);
+        let turns_per_second = self.turns * 1000 / ContactMonitor::BLOCK_MS;
+        let cause = if self.electrode > half {
+            Some(LeadOff::Electrode)
+        } else if self.saturated > half {
+            Some(LeadOff::Saturation)
+        } else if range < ContactMonitor::FLAT_MV {
+            Some(LeadOff::FlatLine)
+        } else if turns_per_second > ContactMonitor::MAX_TURNS_PER_SECOND {
+            Some(LeadOff::Noise)
+        } else {
+            None
+        };
+
+        match cause {
+            Some(cause) => {
+                self.good_blocks = 0;
+                self.bad_blocks = self.bad_blocks.saturating_add(1);
+                let immediate = cause == LeadOff::Electrode || cause == LeadOff::Saturation;
+                let lead_off = self.contact != Contact::Good;
+                if immediate || lead_off || self.bad_blocks >= ContactMonitor::ONSET_BLOCKS {
+                    self.contact = Contact::LeadOff(cause);
+                }
+            }
+            None => {
+                self.bad_blocks = 0;
+                self.good_blocks = self.good_blocks.saturating_add(1);
+                if self.good_blocks >= ContactMonitor::RECOVERY_BLOCKS {
+                    self.contact = Contact::Good;
+                }
+            }
+        }
+
+        self.swing = (range / 16).max(ContactMonitor::MIN_SWING_MV);
+        self.position = 0;
+        self.saturated = 0;
+        self.electrode = 0;
+        self.min = u16::MAX;
+        self.max = 0;
+        self.turns = 0;
+    }
+}
@@ -0,0 +1,69 @@
+use heapless::consts::U16;
+use heapless::spsc::{Queue, SingleCore};
+
+use crate::analysis::rhythm::RhythmEvent;
+
+// The last 16 rhythm events, an ongoing event is updated in place as it changes
+pub struct EventLog {
+    events: Queue<RhythmEvent, U16, u8, SingleCore>,
+    // Total number of events, including those that fell out of the log
+    count: u32,
+}
+
+impl EventLog {
+    pub fn new() -> Self {
+        EventLog {
+            events: unsafe { Queue::u8_sc() },
+            count: 0,
+        }
+    }
+
+    // Takes the changes reported by the rhythm analyzer, returns the number of new events
+    pub fn record(&mut self, changes: &[RhythmEvent]) -> u32 {
+        let mut new = 0;
+        for change in changes {
+            let logged = self
+                .events
+                .iter_mut()
+                .rev()
+                .find(|event| event.rhythm == change.rhythm && event.start == change.start);
+            match logged {
+                Some(event) => {
+                    if change.severity > event.severity {
+                        event.severity = change.severity;
+                    }
+                    event.end = change.end;
+                }
+                None => {
+                    if self.events.len() == self.events.capacity() {
+                        self.events.dequeue();
+                    }
+                    // Cannot fail, there is always room after the check above
+                    self.events.enqueue(*change).ok();
+                    self.count = self.count.wrapping_add(1);
+                    new += 1;
+                }
+            }
+        }
+        new
+    }
+
+    // Newest first
+    pub fn events(&self) -> impl Iterator<Item = &RhythmEvent> {
+        self.events.iter().rev()
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn clear(&mut self) {
+        while self.events.dequeue().is_some() {}
+    }
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        EventLog::new()
+    }
+}
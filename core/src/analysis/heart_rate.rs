@@ -0,0 +1,120 @@
+use heapless::consts::U8;
+use heapless::Vec;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Averaging {
+    Instantaneous,
+    Beats4,
+    Beats8,
+}
+
+impl Averaging {
+    fn beats(self) -> usize {
+        match self {
+            Averaging::Instantaneous => 1,
+            Averaging::Beats4 => 4,
+            Averaging::Beats8 => 8,
+        }
+    }
+}
+
+// Beat-to-beat heart rate estimated from R-R intervals between detected beats
+pub struct HeartRate {
+    sample_rate: u32,
+    averaging: Averaging,
+    last_beat: Option<u32>,
+    // Accepted R-R intervals in samples, the newest is the last one
+    intervals: Vec<u32, U8>,
+    rejected: u8,
+}
+
+impl HeartRate {
+    // 240 BPM
+    const MIN_INTERVAL_MS: u32 = 250;
+    // 20 BPM
+    const MAX_INTERVAL_MS: u32 = 3000;
+    // Maximal deviation from the 8-beat average in percent
+    const MAX_DEVIATION: u32 = 30;
+    // Number of consecutive outliers considered to be a change of the rhythm
+    const MAX_REJECTED: u8 = 4;
+
+    pub fn new(sample_rate: u32, averaging: Averaging) -> Self {
+        HeartRate {
+            sample_rate,
+            averaging,
+            last_beat: None,
+            intervals: Vec::new(),
+            rejected: 0,
+        }
+    }
+
+    pub fn set_averaging(&mut self, averaging: Averaging) {
+        self.averaging = averaging;
+    }
+
+    // Feeds sample index of the detected beat, returns the rate with the configured
+    // averaging if the R-R interval was accepted
+    pub fn beat(&mut self, index: u32) -> Option<u16> {
+        let last_beat = self.last_beat.replace(index)?;
+        let interval = index.wrapping_sub(last_beat);
+        if !self.is_physiological(interval) {
+            return None;
+        }
+        if self.is_outlier(interval) {
+            self.rejected += 1;
+            if self.rejected < HeartRate::MAX_REJECTED {
+                return None;
+            }
+            // The rhythm has changed, start over from this beat
+            self.intervals = Vec::new();
+        }
+        self.rejected = 0;
+
+        if self.intervals.len() == self.intervals.capacity() {
+            self.intervals.rotate_left(1);
+            self.intervals.pop();
+        }
+        self.intervals.push(interval).ok();
+        self.rate()
+    }
+
+    // Rate with the configured averaging
+    pub fn rate(&self) -> Option<u16> {
+        self.average(self.averaging.beats())
+    }
+
+    pub fn instantaneous(&self) -> Option<u16> {
+        self.average(1)
+    }
+
+    // Average over the last `beats` intervals, `None` until that many were collected
+    pub fn average(&self, beats: usize) -> Option<u16> {
+        let len = self.intervals.len();
+        if beats == 0 || len < beats {
+            return None;
+        }
+        let sum: u32 = self.intervals[len - beats..].iter().sum();
+        Some((60 * self.sample_rate * beats as u32 / sum) as u16)
+    }
+
+    pub fn reset(&mut self) {
+        self.last_beat = None;
+        self.intervals = Vec::new();
+        self.rejected = 0;
+    }
+
+    fn is_physiological(&self, interval: u32) -> bool {
+        let min = HeartRate::MIN_INTERVAL_MS * self.sample_rate / 1000;
+        let max = HeartRate::MAX_INTERVAL_MS * self.sample_rate / 1000;
+        (min..=max).contains(&interval)
+    }
+
+    fn is_outlier(&self, interval: u32) -> bool {
+        if self.intervals.is_empty() {
+            return false;
+        }
+        let average = self.intervals.iter().sum::<u32>() / self.intervals.len() as u32;
+        let deviation = average * HeartRate::MAX_DEVIATION / 100;
+        interval + deviation < average || interval > average + deviation
+    }
+}
@@ -0,0 +1,250 @@
+use heapless::consts::U1024;
+use heapless::spsc::{Queue, SingleCore};
+
+use crate::math::sqrt;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct HrvMetrics {
+    // Number of NN intervals in the window
+    pub count: u16,
+    // All in milliseconds
+    pub mean_nn: u16,
+    pub sdnn: u16,
+    pub rmssd: u16,
+    // Percentage of successive NN differences larger than 50 ms
+    pub pnn50: u8,
+}
+
+// Time-domain heart rate variability over rolling windows of R-R intervals.
+//
+// Every R-R interval is kept for the longest window, those that pass the ectopic
+// filter are marked as normal-to-normal (NN). Successive differences are only taken
+// between NN intervals that directly follow each other.
+pub struct Hrv {
+    sample_rate: u32,
+    last_beat: Option<u32>,
+    // The last NN interval is the reference for the ectopic filter
+    last_nn: Option<u16>,
+    previous_normal: bool,
+    rejected: u8,
+    intervals: Queue<Interval, U1024, u16, SingleCore>,
+    // Sum of all stored intervals in ms
+    duration: u32,
+}
+
+impl Hrv {
+    pub const SHORT_TERM_MS: u32 = 60_000;
+    pub const LONG_TERM_MS: u32 = 300_000;
+    // 200 - 30 BPM
+    const MIN_INTERVAL_MS: u32 = 300;
+    const MAX_INTERVAL_MS: u32 = 2000;
+    // Maximal deviation from the previous NN interval in percent
+    const MAX_DEVIATION: u32 = 20;
+    // Number of consecutive rejections considered to be a change of the rhythm
+    const MAX_REJECTED: u8 = 5;
+
+    pub fn new(sample_rate: u32) -> Self {
+        Hrv {
+            sample_rate,
+            last_beat: None,
+            last_nn: None,
+            previous_normal: false,
+            rejected: 0,
+            intervals: unsafe { Queue::u16_sc() },
+            duration: 0,
+        }
+    }
+
+    pub fn beat(&mut self, index: u32) {
+        let last_beat = match self.last_beat.replace(index) {
+            Some(last_beat) => last_beat,
+            None => return,
+        };
+        let interval = index.wrapping_sub(last_beat) as u64 * 1000 / self.sample_rate as u64;
+        let interval = interval.min(Interval::MAX_MS as u64) as u16;
+
+        let normal = self.is_normal(interval);
+        let successive = normal && self.previous_normal;
+        self.previous_normal = normal;
+        if normal {
+            self.last_nn = Some(interval);
+            self.rejected = 0;
+        } else {
+            self.rejected = self.rejected.saturating_add(1);
+        }
+        self.push(Interval::new(interval, normal, successive));
+    }
+
+    pub fn short_term(&self) -> Option<HrvMetrics> {
+        self.metrics(Hrv::SHORT_TERM_MS)
+    }
+
+    pub fn long_term(&self) -> Option<HrvMetrics> {
+        self.metrics(Hrv::LONG_TERM_MS)
+    }
+
+    // Metrics of the most recent `window` milliseconds, `None` until the window is covered
+    pub fn metrics(&self, window: u32) -> Option<HrvMetrics> {
+        if self.duration < window {
+            return None;
+        }
+
+        let mut count = 0u32;
+        let mut sum = 0u32;
+        let mut sum_squares = 0u64;
+        let mut differences = 0u32;
+        let mut sum_differences = 0u64;
+        let mut over_50 = 0u32;
+        let mut previous: Option<u16> = None;
+        for interval in self.window(window) {
+            if !interval.is_normal() {
+                previous = None;
+                continue;
+            }
+            let ms = interval.ms();
+            count += 1;
+            sum += ms as u32;
+            sum_squares += ms as u64 * ms as u64;
+            if let (Some(previous), true) = (previous, interval.is_successive()) {
+                let difference = (ms as i32 - previous as i32).unsigned_abs() as u64;
+                differences += 1;
+                sum_differences += difference * difference;
+                if difference > 50 {
+                    over_50 += 1;
+                }
+            }
+            previous = Some(ms);
+        }
+        if count < 2 || differences == 0 {
+            return None;
+        }
+
+        let mean = sum / count;
+        let variance =
+            (sum_squares - (sum as u64 * sum as u64) / count as u64) / (count as u64 - 1);
+        Some(HrvMetrics {
+            count: count as u16,
+            mean_nn: mean as u16,
+            sdnn: sqrt(variance) as u16,
+            rmssd: sqrt(sum_differences / differences as u64) as u16,
+            pnn50: (over_50 * 100 / differences) as u8,
+        })
+    }
+
+    // Ectopic filtered NN intervals in ms of the most recent `window`, oldest first
+    pub fn nn_intervals(&self, window: u32) -> impl Iterator<Item = u16> + '_ {
+        self.window(window)
+            .filter(|interval| interval.is_normal())
+            .map(|interval| interval.ms())
+    }
+
+    // NN intervals with time of their end in ms since the start of the `window`
+    pub fn nn_samples(&self, window: u32) -> impl Iterator<Item = (u32, u16)> + '_ {
+        let mut time = 0;
+        self.window(window).filter_map(move |interval| {
+            time += interval.ms() as u32;
+            if interval.is_normal() {
+                Some((time, interval.ms()))
+            } else {
+                None
+            }
+        })
+    }
+
+    // Gap in the beats, the next beat starts a new interval without affecting the data
+    pub fn interrupt(&mut self) {
+        self.last_beat = None;
+        self.previous_normal = false;
+    }
+
+    pub fn reset(&mut self) {
+        while self.intervals.dequeue().is_some() {}
+        self.duration = 0;
+        self.last_beat = None;
+        self.last_nn = None;
+        self.previous_normal = false;
+        self.rejected = 0;
+    }
+
+    fn window(&self, window: u32) -> impl Iterator<Item = Interval> + '_ {
+        let mut skip = self.duration.saturating_sub(window);
+        self.intervals.iter().copied().skip_while(move |interval| {
+            if skip == 0 {
+                return false;
+            }
+            skip = skip.saturating_sub(interval.ms() as u32);
+            true
+        })
+    }
+
+    fn is_normal(&self, interval: u16) -> bool {
+        let interval = interval as u32;
+        if !(Hrv::MIN_INTERVAL_MS..=Hrv::MAX_INTERVAL_MS).contains(&interval) {
+            return false;
+        }
+        match self.last_nn {
+            Some(last) if self.rejected < Hrv::MAX_REJECTED => {
+                let last = last as u32;
+                let deviation = last * Hrv::MAX_DEVIATION / 100;
+                interval + deviation >= last && interval <= last + deviation
+            }
+            _ => true,
+        }
+    }
+
+    fn push(&mut self, interval: Interval) {
+        if self.intervals.len() == self.intervals.capacity() {
+            self.drop_oldest();
+        }
+        self.duration += interval.ms() as u32;
+        // Cannot fail, there is always room after the check above
+        self.intervals.enqueue(interval).ok();
+        while self.duration - self.oldest() > Hrv::LONG_TERM_MS {
+            self.drop_oldest();
+        }
+    }
+
+    fn oldest(&self) -> u32 {
+        self.intervals.peek().map(|i| i.ms() as u32).unwrap_or(0)
+    }
+
+    fn drop_oldest(&mut self) {
+        if let Some(interval) = self.intervals.dequeue() {
+            self.duration -= interval.ms() as u32;
+        }
+    }
+}
+
+// R-R interval in ms packed with its NN flags
+#[derive(Copy, Clone)]
+struct Interval(u16);
+
+impl Interval {
+    const MAX_MS: u16 = 0x3fff;
+    const NORMAL: u16 = 1 << 14;
+    const SUCCESSIVE: u16 = 1 << 15;
+
+    fn new(ms: u16, normal: bool, successive: bool) -> Self {
+        let mut value = ms.min(Interval::MAX_MS);
+        if normal {
+            value |= Interval::NORMAL;
+        }
+        if successive {
+            value |= Interval::SUCCESSIVE;
+        }
+        Interval(value)
+    }
+
+    fn ms(self) -> u16 {
+        self.0 & Interval::MAX_MS
+    }
+
+    fn is_normal(self) -> bool {
+        self.0 & Interval::NORMAL != 0
+    }
+
+    // The previous interval was NN as well
+    fn is_successive(self) -> bool {
+        self.0 & Interval::SUCCESSIVE != 0
+    }
+}
@@ -0,0 +1,15 @@
+pub mod contact;
+pub mod event_log;
+pub mod heart_rate;
+pub mod hrv;
+pub mod pvc;
+pub mod qrs;
+pub mod quality;
+pub mod rhythm;
+pub mod session;
+pub mod spectrum;
+pub mod trend;
+
+mod window;
+
+pub(crate) use window::Window;
@@ -0,0 +1,368 @@
+use heapless::consts::{U128, U512, U64};
+use heapless::spsc::{Queue, SingleCore};
+use heapless::Vec;
+
+use crate::math::sqrt;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BeatClass {
+    Normal,
+    Pvc,
+    Unknown,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum EctopicPattern {
+    // Two PVCs in a row
+    Couplet,
+    // PVC after every normal beat for the last three cycles
+    Bigeminy,
+}
+
+impl EctopicPattern {
+    pub fn name(self) -> &'static str {
+        match self {
+            EctopicPattern::Couplet => "CPLT",
+            EctopicPattern::Bigeminy => "BIGEM",
+        }
+    }
+}
+
+// Beat morphology classifier.
+//
+// The signal is resampled to 250 Hz and kept for the last 2 s, so beats reported late
+// by the search-back of the detector can still be segmented. Every beat is cut from
+// 100 ms before to 150 ms after the R peak, aligned to the template within 8 ms and
+// compared by correlation, QRS width and prematurity. Normal beats update the template
+// by a step towards the beat, which approximates a running median.
+pub struct BeatClassifier {
+    sample_rate: u32,
+    accumulator: i32,
+    accumulated: u32,
+    // Resampling phase, a decimated sample is stored each time it reaches the rate
+    phase: u32,
+    // Decimated samples stored so far
+    index: u32,
+    history: Vec<i16, U512>,
+    template: Vec<i32, U64>,
+    template_width: u32,
+    // Normal beats matched to the template, the classification starts once learned
+    learned: u8,
+    mismatched: u8,
+    last_beat: Option<u32>,
+    // Average R-R interval of normal beats in decimated samples
+    rr_average: u32,
+}
+
+impl BeatClassifier {
+    pub const RATE: u32 = 250;
+    // Segment in decimated samples around the R peak
+    const BEFORE: usize = 25;
+    const AFTER: usize = 38;
+    const ALIGNMENT: usize = 2;
+    // Part of the segment the QRS width is measured in, +-100 ms
+    const QRS_SPAN: usize = 25;
+    // Points at the start of the segment taken as the isoelectric level
+    const BASELINE: usize = 5;
+    // Fraction bits of the template
+    const TEMPLATE_BITS: i32 = 4;
+    // QRS boundary at 25 % of the peak deviation
+    const WIDTH_LEVEL: i32 = 4;
+    const LEARNING_BEATS: u8 = 8;
+    const MAX_MISMATCHED: u8 = 4;
+    // Correlation in percent
+    const NORMAL_CORRELATION: i64 = 90;
+    const PVC_CORRELATION: i64 = 80;
+    // 40 ms wider than the template
+    const WIDE: u32 = 10;
+    // R-R interval shorter than 85 % of the average
+    const PREMATURE: u32 = 85;
+
+    pub fn new(sample_rate: u32) -> Self {
+        let mut history = Vec::new();
+        // Cannot fail, resized to the capacity
+        history.resize(history.capacity(), 0).ok();
+        BeatClassifier {
+            sample_rate: sample_rate.max(1),
+            accumulator: 0,
+            accumulated: 0,
+            phase: 0,
+            index: 0,
+            history,
+            template: Vec::new(),
+            template_width: 0,
+            learned: 0,
+            mismatched: 0,
+            last_beat: None,
+            rr_average: 0,
+        }
+    }
+
+    // Stores the filtered sample, has to be called for every sample the detector gets
+    pub fn push(&mut self, sample: u16) {
+        self.accumulator += sample as i32;
+        self.accumulated += 1;
+        self.phase += BeatClassifier::RATE;
+        if self.phase < self.sample_rate {
+            return;
+        }
+        let value = self.accumulator / self.accumulated as i32;
+        let len = self.history.len() as u32;
+        // Rates below 250 Hz repeat the sample
+        while self.phase >= self.sample_rate {
+            self.history[(self.index % len) as usize] = value as i16;
+            self.index = self.index.wrapping_add(1);
+            self.phase -= self.sample_rate;
+        }
+        self.accumulator = 0;
+        self.accumulated = 0;
+    }
+
+    // Classifies the beat with R peak at the sample `index` of the detector
+    pub fn classify(&mut self, index: u32) -> BeatClass {
+        let r = (index as u64 * BeatClassifier::RATE as u64 / self.sample_rate as u64) as u32;
+        let rr = self.last_beat.replace(r).map(|last| r.wrapping_sub(last));
+        let segment = match self.segment(r) {
+            Some(segment) => segment,
+            None => return BeatClass::Unknown,
+        };
+
+        if self.template.is_empty() || self.mismatched >= BeatClassifier::MAX_MISMATCHED {
+            let width = BeatClassifier::width(&segment);
+            self.start_learning(&segment, width);
+            return BeatClass::Unknown;
+        }
+
+        let (correlation, segment) = match self.align(r) {
+            Some((correlation, aligned)) => (correlation, self.segment(aligned).unwrap_or(segment)),
+            None => (0, segment),
+        };
+        let width = BeatClassifier::width(&segment);
+        let wide = width > self.template_width + BeatClassifier::WIDE;
+        let premature = match rr {
+            Some(rr) if self.rr_average > 0 => {
+                rr * 100 < self.rr_average * BeatClassifier::PREMATURE
+            }
+            _ => false,
+        };
+
+        if self.learned < BeatClassifier::LEARNING_BEATS {
+            if correlation >= BeatClassifier::NORMAL_CORRELATION && !wide {
+                self.learn(&segment, width, rr);
+                self.mismatched = 0;
+            } else {
+                self.mismatched += 1;
+            }
+            return BeatClass::Unknown;
+        }
+
+        if correlation >= BeatClassifier::NORMAL_CORRELATION && !wide {
+            self.learn(&segment, width, rr);
+            self.mismatched = 0;
+            BeatClass::Normal
+        } else if (correlation < BeatClassifier::PVC_CORRELATION && (wide || premature))
+            || (wide && premature)
+        {
+            BeatClass::Pvc
+        } else {
+            BeatClass::Unknown
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.template = Vec::new();
+        self.learned = 0;
+        self.mismatched = 0;
+        self.last_beat = None;
+        self.rr_average = 0;
+    }
+
+    fn start_learning(&mut self, segment: &Segment, width: u32) {
+        self.template = Vec::new();
+        for value in segment.iter() {
+            // Cannot fail, the segment fits the template
+            self.template
+                .push(*value << BeatClassifier::TEMPLATE_BITS)
+                .ok();
+        }
+        self.template_width = width;
+        self.learned = 1;
+        self.mismatched = 0;
+        self.rr_average = 0;
+    }
+
+    // Moves the template towards the beat by at most 1/64 of the R amplitude
+    fn learn(&mut self, segment: &Segment, width: u32, rr: Option<u32>) {
+        let amplitude = segment.iter().map(|value| value.abs()).max().unwrap_or(0);
+        let step = (amplitude << BeatClassifier::TEMPLATE_BITS) / 64;
+        for (template, value) in self.template.iter_mut().zip(segment.iter()) {
+            let difference = (*value << BeatClassifier::TEMPLATE_BITS) - *template;
+            if self.learned < BeatClassifier::LEARNING_BEATS {
+                *template += difference / 4;
+            } else {
+                *template += difference.clamp(-step, step);
+            }
+        }
+        self.template_width = if self.learned < BeatClassifier::LEARNING_BEATS {
+            (self.template_width * 3 + width) / 4
+        } else {
+            (self.template_width * 15 + width) / 16
+        };
+        if let Some(rr) = rr {
+            self.rr_average = if self.rr_average == 0 {
+                rr
+            } else {
+                (self.rr_average * 7 + rr) / 8
+            };
+        }
+        self.learned = self.learned.saturating_add(1);
+    }
+
+    // Best correlation in percent with the template around `r`
+    fn align(&self, r: u32) -> Option<(i64, u32)> {
+        (0..=2 * BeatClassifier::ALIGNMENT)
+            .filter_map(|shift| {
+                let candidate = (r + shift as u32).checked_sub(BeatClassifier::ALIGNMENT as u32)?;
+                let segment = self.segment(candidate)?;
+                Some((self.correlation(&segment), candidate))
+            })
+            .max_by_key(|(correlation, _)| *correlation)
+    }
+
+    fn correlation(&self, segment: &Segment) -> i64 {
+        let len = segment.len() as i64;
+        let template = self
+            .template
+            .iter()
+            .map(|value| (*value >> BeatClassifier::TEMPLATE_BITS) as i64);
+        let (mut sum_x, mut sum_y, mut sum_xx, mut sum_yy, mut sum_xy) = (0, 0, 0, 0, 0);
+        for (x, y) in segment.iter().map(|value| *value as i64).zip(template) {
+            sum_x += x;
+            sum_y += y;
+            sum_xx += x * x;
+            sum_yy += y * y;
+            sum_xy += x * y;
+        }
+        let covariance = len * sum_xy - sum_x * sum_y;
+        let variance_x = (len * sum_xx - sum_x * sum_x) as u64;
+        let variance_y = (len * sum_yy - sum_y * sum_y) as u64;
+        let deviations = sqrt(variance_x) as i64 * sqrt(variance_y) as i64;
+        if deviations == 0 {
+            return 0;
+        }
+        covariance * 100 / deviations
+    }
+
+    // Segment relative to its isoelectric level, `None` if it is not in the history
+    fn segment(&self, r: u32) -> Option<Segment> {
+        let start = r.checked_sub(BeatClassifier::BEFORE as u32)?;
+        let end = r + BeatClassifier::AFTER as u32;
+        let len = self.history.len() as u32;
+        if end >= self.index || self.index - start > len {
+            return None;
+        }
+        let mut segment = Segment::new();
+        for index in start..=end {
+            // Cannot fail, the segment fits its capacity
+            segment
+                .push(self.history[(index % len) as usize] as i32)
+                .ok();
+        }
+        let baseline = segment[..BeatClassifier::BASELINE].iter().sum::<i32>()
+            / BeatClassifier::BASELINE as i32;
+        for value in segment.iter_mut() {
+            *value -= baseline;
+        }
+        Some(segment)
+    }
+
+    // QRS width in decimated samples, between the outermost points deviating more than
+    // a quarter of the peak, biphasic complexes are measured whole
+    fn width(segment: &Segment) -> u32 {
+        let qrs = &segment[BeatClassifier::BEFORE - BeatClassifier::QRS_SPAN
+            ..=BeatClassifier::BEFORE + BeatClassifier::QRS_SPAN];
+        let peak = qrs.iter().map(|value| value.abs()).max().unwrap_or(0);
+        let level = peak / BeatClassifier::WIDTH_LEVEL;
+        let first = qrs.iter().position(|value| value.abs() > level);
+        let last = qrs.iter().rposition(|value| value.abs() > level);
+        match (first, last) {
+            (Some(first), Some(last)) => (last - first + 1) as u32,
+            _ => 0,
+        }
+    }
+}
+
+type Segment = Vec<i32, U64>;
+
+// PVC rate and patterns from the sequence of classified beats
+pub struct EctopyCounter {
+    sample_rate: u32,
+    // R peak indices of PVCs within the last minute
+    pvcs: Queue<u32, U128, u8, SingleCore>,
+    // Last beats as bits, the newest in bit 0
+    recent_pvc: u8,
+    recent_normal: u8,
+}
+
+impl EctopyCounter {
+    const MINUTE_MS: u64 = 60_000;
+    const BIGEMINY_BEATS: u8 = 0b11_1111;
+
+    pub fn new(sample_rate: u32) -> Self {
+        EctopyCounter {
+            sample_rate,
+            pvcs: unsafe { Queue::u8_sc() },
+            recent_pvc: 0,
+            recent_normal: 0,
+        }
+    }
+
+    pub fn beat(&mut self, index: u32, class: BeatClass) {
+        self.recent_pvc <<= 1;
+        self.recent_normal <<= 1;
+        match class {
+            BeatClass::Pvc => {
+                self.recent_pvc |= 1;
+                if self.pvcs.len() == self.pvcs.capacity() {
+                    self.pvcs.dequeue();
+                }
+                // Cannot fail, there is always room after the check above
+                self.pvcs.enqueue(index).ok();
+            }
+            BeatClass::Normal => self.recent_normal |= 1,
+            BeatClass::Unknown => {}
+        }
+        while let Some(oldest) = self.pvcs.peek() {
+            let age = index.wrapping_sub(*oldest) as u64 * 1000 / self.sample_rate as u64;
+            if age < EctopyCounter::MINUTE_MS {
+                break;
+            }
+            self.pvcs.dequeue();
+        }
+    }
+
+    // PVCs within the minute before the last beat
+    pub fn per_minute(&self) -> u16 {
+        self.pvcs.len() as u16
+    }
+
+    // Pattern formed by the last beats
+    pub fn pattern(&self) -> Option<EctopicPattern> {
+        let beats = EctopyCounter::BIGEMINY_BEATS;
+        if self.recent_pvc & 0b11 == 0b11 {
+            Some(EctopicPattern::Couplet)
+        } else if (self.recent_pvc | self.recent_normal) & beats == beats
+            && (self.recent_pvc & beats == 0b10_1010 || self.recent_pvc & beats == 0b01_0101)
+        {
+            Some(EctopicPattern::Bigeminy)
+        } else {
+            None
+        }
+    }
+
+    pub fn reset(&mut self) {
+        while self.pvcs.dequeue().is_some() {}
+        self.recent_pvc = 0;
+        self.recent_normal = 0;
+    }
+}
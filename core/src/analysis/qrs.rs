@@ -0,0 +1,411 @@
+use heapless::consts::{U160, U192, U24, U32, U320, U8};
+use heapless::Vec;
+
+use crate::analysis::Window;
+
+// Pan-Tompkins QRS detector working on the millivolt samples produced by the sampler.
+//
+// The stages are band-pass (low-pass + high-pass made of moving sums), derivative,
+// squaring and moving window integration. Peaks of the integrated signal are classified
+// by adaptive signal/noise thresholds with refractory period, T-wave discrimination and
+// search-back for missed beats. The R-peak itself is located as the maximum of the
+// band-passed signal under the integration window and reported as sample index of the
+// input stream.
+pub struct QrsDetector {
+    sample_rate: u32,
+    // Index of the next input sample
+    index: u32,
+    // Capacities are sized for the maximal sample rate
+    low_pass: (Window<U32>, Window<U32>),
+    high_pass: Window<U192>,
+    derivative: Window<U24>,
+    integrator: Window<U160>,
+    band_pass: Window<U320>,
+    delay: Delay,
+    peak: PeakFinder,
+    thresholds: Thresholds,
+    rr: RrIntervals,
+    learning: Learning,
+    last_qrs: Option<Peak>,
+    search_back: Option<Peak>,
+}
+
+impl QrsDetector {
+    pub const MAX_SAMPLE_RATE: u32 = 1000;
+    const DERIVATIVE_LIMIT: i32 = 2047;
+
+    pub fn new(sample_rate: u32) -> Self {
+        let sample_rate = sample_rate.clamp(1, QrsDetector::MAX_SAMPLE_RATE);
+        // First zero of the low-pass at ~33 Hz
+        let low_pass_len = QrsDetector::duration(sample_rate, 30).max(1);
+        // Averaging window of ~160 ms gives high-pass corner at ~5 Hz, odd to have a center
+        let high_pass_len = QrsDetector::duration(sample_rate, 160) | 1;
+        // Derivative step, 5 ms is the original 200 Hz spacing
+        let step = QrsDetector::duration(sample_rate, 5).max(1);
+        let integrator_len = QrsDetector::duration(sample_rate, 150).max(1);
+
+        let delay = Delay {
+            band_pass: (low_pass_len - 1) + (high_pass_len - 1) / 2,
+            derivative_step: step,
+        };
+        QrsDetector {
+            sample_rate,
+            index: 0,
+            low_pass: (Window::new(low_pass_len), Window::new(low_pass_len)),
+            high_pass: Window::new(high_pass_len),
+            derivative: Window::new(4 * step + 1),
+            integrator: Window::new(integrator_len),
+            band_pass: Window::new(2 * integrator_len + 4 * step),
+            delay,
+            peak: PeakFinder::new(),
+            thresholds: Thresholds::new(),
+            rr: RrIntervals::new(sample_rate),
+            learning: Learning::new(QrsDetector::duration(sample_rate, 2000)),
+            last_qrs: None,
+            search_back: None,
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    // Index that will be assigned to the next processed sample
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    // Feeds single sample, returns sample index of the R-peak if a beat was detected
+    pub fn process(&mut self, sample: u16) -> Option<u32> {
+        let integrated = self.filter(sample as i32);
+        let index = self.index;
+        self.index = self.index.wrapping_add(1);
+
+        if let Some(learning) = self.learning.update(integrated) {
+            self.thresholds.learn(learning);
+            return None;
+        }
+
+        let candidate = Peak {
+            value: integrated,
+            index,
+            r_index: 0,
+            slope: self.integrator.max(),
+        };
+        let timeout = self.integrator.len() as u32;
+        if let Some(mut peak) = self.peak.update(candidate, timeout) {
+            peak.r_index = self.locate_r_peak(index.wrapping_sub(peak.index));
+            if let Some(r_index) = self.classify(peak) {
+                return Some(r_index);
+            }
+        }
+        self.check_search_back(index)
+    }
+
+    fn filter(&mut self, sample: i32) -> i32 {
+        if self.index == 0 {
+            // Prime the windows to avoid huge step response from the DC component
+            self.low_pass.0.fill(sample);
+            self.low_pass.1.fill(sample * self.low_pass.0.len() as i32);
+            self.high_pass.fill(sample);
+        }
+
+        // Low-pass as two cascaded moving sums, normalized to unity gain
+        self.low_pass.0.push(sample);
+        self.low_pass.1.push(self.low_pass.0.sum());
+        let len = self.low_pass.0.len() as i32;
+        let low_passed = self.low_pass.1.sum() / (len * len);
+
+        // High-pass as delayed input minus its moving average
+        self.high_pass.push(low_passed);
+        let len = self.high_pass.len();
+        let band_passed = self.high_pass.get(len / 2) - self.high_pass.sum() / len as i32;
+        self.band_pass.push(band_passed);
+
+        // Five point derivative
+        self.derivative.push(band_passed);
+        let step = self.delay.derivative_step;
+        let derivative = (2 * self.derivative.get(0) + self.derivative.get(step)
+            - self.derivative.get(3 * step)
+            - 2 * self.derivative.get(4 * step))
+            / 8;
+
+        // Squaring, clamped so the integrator sum cannot overflow
+        let derivative = derivative.clamp(-Self::DERIVATIVE_LIMIT, Self::DERIVATIVE_LIMIT);
+        self.integrator.push(derivative * derivative);
+        self.integrator.sum() / self.integrator.len() as i32
+    }
+
+    // Finds the band-passed maximum under the integration window of peak `age` samples old
+    fn locate_r_peak(&self, age: u32) -> u32 {
+        let start = age as usize + 2 * self.delay.derivative_step;
+        let end = (start + self.integrator.len()).min(self.band_pass.len());
+        let mut best = (0, start);
+        for age in start..end {
+            let value = self.band_pass.get(age).abs();
+            if value > best.0 {
+                best = (value, age);
+            }
+        }
+        self.index
+            .wrapping_sub(1)
+            .wrapping_sub(best.1 as u32)
+            .wrapping_sub(self.delay.band_pass as u32)
+    }
+
+    fn classify(&mut self, peak: Peak) -> Option<u32> {
+        let threshold = self.thresholds.signal(self.rr.is_regular());
+        if peak.value <= threshold {
+            self.noise_peak(peak);
+            return None;
+        }
+
+        if let Some(last) = self.last_qrs {
+            let since_last = peak.index.wrapping_sub(last.index);
+            if since_last < QrsDetector::duration(self.sample_rate, 200) as u32 {
+                // Refractory period, physiologically impossible
+                return None;
+            }
+            if since_last < QrsDetector::duration(self.sample_rate, 360) as u32
+                && peak.slope < last.slope / 4
+            {
+                // T-wave, slope is less than half of the previous QRS (squared values)
+                self.noise_peak(peak);
+                return None;
+            }
+        }
+
+        self.thresholds.signal_peak(peak.value, false);
+        Some(self.accept(peak))
+    }
+
+    fn check_search_back(&mut self, index: u32) -> Option<u32> {
+        let last = self.last_qrs?;
+        if index.wrapping_sub(last.index) <= self.rr.missed_limit() {
+            return None;
+        }
+        let peak = self.search_back.take()?;
+        self.thresholds.signal_peak(peak.value, true);
+        Some(self.accept(peak))
+    }
+
+    fn noise_peak(&mut self, peak: Peak) {
+        self.thresholds.noise_peak(peak.value);
+        let refractory = QrsDetector::duration(self.sample_rate, 360) as u32;
+        let outside_refractory = self
+            .last_qrs
+            .map(|last| peak.index.wrapping_sub(last.index) > refractory)
+            .unwrap_or(true);
+        let threshold = self.thresholds.search_back(self.rr.is_regular());
+        let better = self
+            .search_back
+            .map(|candidate| peak.value > candidate.value)
+            .unwrap_or(true);
+        if outside_refractory && peak.value > threshold && better {
+            self.search_back = Some(peak);
+        }
+    }
+
+    fn accept(&mut self, peak: Peak) -> u32 {
+        if let Some(last) = self.last_qrs {
+            self.rr.update(peak.index.wrapping_sub(last.index));
+        }
+        self.last_qrs = Some(peak);
+        self.search_back = None;
+        peak.r_index
+    }
+
+    fn duration(sample_rate: u32, ms: u32) -> usize {
+        ((sample_rate * ms + 500) / 1000) as usize
+    }
+}
+
+struct Delay {
+    band_pass: usize,
+    derivative_step: usize,
+}
+
+#[derive(Copy, Clone)]
+struct Peak {
+    value: i32,
+    // Index in the integrated signal
+    index: u32,
+    // Index of the R-peak in the input signal
+    r_index: u32,
+    // Maximal squared slope under the integration window
+    slope: i32,
+}
+
+struct PeakFinder {
+    rising: bool,
+    previous: i32,
+    candidate: Option<Peak>,
+}
+
+impl PeakFinder {
+    fn new() -> Self {
+        PeakFinder {
+            rising: false,
+            previous: 0,
+            candidate: None,
+        }
+    }
+
+    // Returns the peak once the signal dropped to half of it, or once the peak
+    // is older than the `timeout`
+    fn update(&mut self, sample: Peak, timeout: u32) -> Option<Peak> {
+        let mut peak = None;
+        match self.candidate {
+            Some(candidate) if self.rising => {
+                if sample.value >= candidate.value {
+                    self.candidate = Some(sample);
+                } else if sample.value < candidate.value / 2
+                    || sample.index.wrapping_sub(candidate.index) > timeout
+                {
+                    peak = self.candidate.take();
+                    self.rising = false;
+                }
+            }
+            _ => {
+                if sample.value > self.previous {
+                    self.candidate = Some(sample);
+                    self.rising = true;
+                }
+            }
+        }
+        self.previous = sample.value;
+        peak
+    }
+}
+
+struct Thresholds {
+    signal: i32,
+    noise: i32,
+}
+
+impl Thresholds {
+    fn new() -> Self {
+        Thresholds {
+            signal: 0,
+            noise: 0,
+        }
+    }
+
+    fn learn(&mut self, learning: (i32, i32)) {
+        let (max, mean) = learning;
+        self.signal = max / 3;
+        self.noise = mean / 2;
+    }
+
+    fn signal(&self, regular: bool) -> i32 {
+        let threshold = self.noise + (self.signal - self.noise) / 4;
+        if regular {
+            threshold
+        } else {
+            threshold / 2
+        }
+    }
+
+    fn search_back(&self, regular: bool) -> i32 {
+        self.signal(regular) / 2
+    }
+
+    fn signal_peak(&mut self, value: i32, search_back: bool) {
+        if search_back {
+            self.signal = (value + 3 * self.signal) / 4;
+        } else {
+            self.signal = (value + 7 * self.signal) / 8;
+        }
+    }
+
+    fn noise_peak(&mut self, value: i32) {
+        self.noise = (value + 7 * self.noise) / 8;
+    }
+}
+
+struct Learning {
+    remaining: usize,
+    max: i32,
+    sum: i64,
+    count: i64,
+}
+
+impl Learning {
+    fn new(len: usize) -> Self {
+        Learning {
+            remaining: len,
+            max: 0,
+            sum: 0,
+            count: 0,
+        }
+    }
+
+    // Returns `None` once learning is over, otherwise collects statistics and
+    // returns the (max, mean) at the last learning sample
+    fn update(&mut self, value: i32) -> Option<(i32, i32)> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        self.max = self.max.max(value);
+        self.sum += value as i64;
+        self.count += 1;
+        Some((self.max, (self.sum / self.count) as i32))
+    }
+}
+
+struct RrIntervals {
+    recent: Vec<u32, U8>,
+    selected: Vec<u32, U8>,
+    regular: bool,
+}
+
+impl RrIntervals {
+    fn new(sample_rate: u32) -> Self {
+        let mut recent = Vec::new();
+        // Start with 60 BPM as the expected rhythm
+        recent.resize(8, sample_rate).ok();
+        RrIntervals {
+            selected: recent.clone(),
+            recent,
+            regular: true,
+        }
+    }
+
+    fn update(&mut self, rr: u32) {
+        RrIntervals::push(&mut self.recent, rr);
+        let average = RrIntervals::average(&self.selected);
+        let low = average * 92 / 100;
+        let high = average * 116 / 100;
+        self.regular = rr >= low && rr <= high;
+        if self.regular {
+            RrIntervals::push(&mut self.selected, rr);
+        }
+    }
+
+    fn is_regular(&self) -> bool {
+        self.regular
+    }
+
+    // Search-back is triggered when no beat was found for 166 % of the average RR
+    fn missed_limit(&self) -> u32 {
+        let average = if self.regular {
+            RrIntervals::average(&self.selected)
+        } else {
+            RrIntervals::average(&self.recent)
+        };
+        average * 166 / 100
+    }
+
+    fn push(buffer: &mut Vec<u32, U8>, rr: u32) {
+        if buffer.len() == buffer.capacity() {
+            buffer.rotate_left(1);
+            buffer.pop();
+        }
+        buffer.push(rr).ok();
+    }
+
+    fn average(buffer: &[u32]) -> u32 {
+        buffer.iter().sum::<u32>() / buffer.len().max(1) as u32
+    }
+}
@@ -0,0 +1,316 @@
+use heapless::consts::U64;
+use heapless::spsc::{Queue, SingleCore};
+
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+pub enum SignalQuality {
+    Unusable,
+    Acceptable,
+    Good,
+}
+
+impl SignalQuality {
+    pub fn name(self) -> &'static str {
+        match self {
+            SignalQuality::Unusable => "POOR",
+            SignalQuality::Acceptable => "FAIR",
+            SignalQuality::Good => "GOOD",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct QualityMetrics {
+    // Kurtosis multiplied by 10
+    pub kurtosis: u16,
+    // Signal power above 1 Hz in percent of the total
+    pub baseline: u8,
+    // Beats found by both detectors in percent of all found, `None` without any beat
+    pub agreement: Option<u8>,
+    // Clipped samples in per mille
+    pub clipping: u16,
+    pub quality: SignalQuality,
+}
+
+// Signal quality index over 10 s windows.
+//
+// Clean ECG is strongly peaked, so the kurtosis of the filtered samples drops towards 3
+// with Gaussian noise. Power left below 1 Hz is the baseline wander, the clipping is
+// counted from the raw ADC code. Beats of the main detector are matched within 150 ms
+// with a simple slope detector, that tends to fail differently on noise. The beats
+// arrive late, so the agreement is judged for the window delayed by 1 s.
+pub struct QualityIndex {
+    sample_rate: u32,
+    window_len: u32,
+    position: u32,
+    index: u32,
+    rails: (u16, u16),
+    // Deviations from the mean of the previous window
+    center: i32,
+    sum: i64,
+    sum_squares: u64,
+    sum_cubes: i64,
+    sum_fourth: u64,
+    // One pole low-pass below 1 Hz, Q8
+    baseline: i32,
+    baseline_alpha: i32,
+    baseline_power: u64,
+    clipped: u32,
+    detector: SlopeDetector,
+    beats: Queue<u32, U64, u8, SingleCore>,
+    slope_beats: Queue<u32, U64, u8, SingleCore>,
+    metrics: Option<QualityMetrics>,
+}
+
+impl QualityIndex {
+    const WINDOW_MS: u32 = 10_000;
+    const LAG_MS: u32 = 1000;
+    const MATCH_MS: u32 = 150;
+    const BASELINE_CORNER_HZ: u32 = 1;
+    const RAIL_PERCENT: u32 = 2;
+    // Deviations are limited so the fourth powers of a window fit u64
+    const MAX_DEVIATION: i32 = 4095;
+    const GOOD: QualityMetrics = QualityMetrics {
+        kurtosis: 50,
+        baseline: 95,
+        agreement: Some(90),
+        clipping: 10,
+        quality: SignalQuality::Good,
+    };
+    const ACCEPTABLE: QualityMetrics = QualityMetrics {
+        kurtosis: 30,
+        baseline: 80,
+        agreement: Some(60),
+        clipping: 100,
+        quality: SignalQuality::Acceptable,
+    };
+
+    pub fn new(sample_rate: u32, full_scale: u16) -> Self {
+        let rail = (full_scale as u32 * QualityIndex::RAIL_PERCENT / 100) as u16;
+        // alpha = 2 * PI * fc / fs in Q8, accurate enough for fc << fs
+        let alpha = (2 * 314 * QualityIndex::BASELINE_CORNER_HZ * 256 / (100 * sample_rate)).max(1);
+        QualityIndex {
+            sample_rate,
+            window_len: sample_rate * QualityIndex::WINDOW_MS / 1000,
+            position: 0,
+            index: 0,
+            rails: (rail, full_scale - rail),
+            center: 0,
+            sum: 0,
+            sum_squares: 0,
+            sum_cubes: 0,
+            sum_fourth: 0,
+            baseline: 0,
+            baseline_alpha: alpha as i32,
+            baseline_power: 0,
+            clipped: 0,
+            detector: SlopeDetector::new(sample_rate),
+            beats: unsafe { Queue::u8_sc() },
+            slope_beats: unsafe { Queue::u8_sc() },
+            metrics: None,
+        }
+    }
+
+    // Takes the raw ADC code and the filtered sample in mV, returns the metrics at the
+    // end of every window
+    pub fn process(&mut self, input: u16, sample: u16) -> Option<QualityMetrics> {
+        if self.index == 0 {
+            self.center = sample as i32;
+            self.baseline = (sample as i32) << 8;
+        }
+        if input <= self.rails.0 || input >= self.rails.1 {
+            self.clipped += 1;
+        }
+
+        let deviation = (sample as i32 - self.center)
+            .clamp(-QualityIndex::MAX_DEVIATION, QualityIndex::MAX_DEVIATION)
+            as i64;
+        let square = deviation * deviation;
+        self.sum += deviation;
+        self.sum_squares += square as u64;
+        self.sum_cubes += square * deviation;
+        self.sum_fourth += (square * square) as u64;
+
+        self.baseline += ((((sample as i32) << 8) - self.baseline) * self.baseline_alpha) >> 8;
+        let wander = ((self.baseline >> 8) - self.center) as i64;
+        self.baseline_power += (wander * wander) as u64;
+
+        if let Some(beat) = self.detector.process(self.index, sample) {
+            QualityIndex::push(&mut self.slope_beats, beat);
+        }
+        self.index = self.index.wrapping_add(1);
+        self.position += 1;
+        if self.position < self.window_len {
+            return None;
+        }
+        let metrics = self.evaluate();
+        self.metrics = Some(metrics);
+        Some(metrics)
+    }
+
+    // Sample index of a beat from the main detector
+    pub fn beat(&mut self, index: u32) {
+        QualityIndex::push(&mut self.beats, index);
+    }
+
+    // Metrics of the last complete window
+    pub fn metrics(&self) -> Option<QualityMetrics> {
+        self.metrics
+    }
+
+    pub fn quality(&self) -> Option<SignalQuality> {
+        self.metrics.map(|metrics| metrics.quality)
+    }
+
+    fn evaluate(&mut self) -> QualityMetrics {
+        let n = self.position as i64;
+        let mean = self.sum / n;
+        // Central moments from the raw ones, all in i64 as the mean is small
+        let m2 = self.sum_squares as i64 / n - mean * mean;
+        let m4 = self.sum_fourth as i64 / n - 4 * mean * (self.sum_cubes / n)
+            + 6 * mean * mean * (self.sum_squares as i64 / n)
+            - 3 * mean * mean * mean * mean;
+        let kurtosis = if m2 > 0 {
+            (m4 * 10 / (m2 * m2)).clamp(0, u16::MAX as i64) as u16
+        } else {
+            0
+        };
+        let total = self.sum_squares.max(1);
+        let wander = self.baseline_power.min(total);
+        let baseline = (100 - wander * 100 / total) as u8;
+        let clipping = (self.clipped * 1000 / self.position) as u16;
+        let agreement = self.agreement();
+
+        let mut metrics = QualityMetrics {
+            kurtosis,
+            baseline,
+            agreement,
+            clipping,
+            quality: SignalQuality::Unusable,
+        };
+        for limits in [QualityIndex::GOOD, QualityIndex::ACCEPTABLE].iter() {
+            if metrics.kurtosis >= limits.kurtosis
+                && metrics.baseline >= limits.baseline
+                && metrics
+                    .agreement
+                    .is_none_or(|agreement| Some(agreement) >= limits.agreement)
+                && metrics.clipping <= limits.clipping
+            {
+                metrics.quality = limits.quality;
+                break;
+            }
+        }
+
+        self.center += mean as i32;
+        self.position = 0;
+        self.sum = 0;
+        self.sum_squares = 0;
+        self.sum_cubes = 0;
+        self.sum_fourth = 0;
+        self.baseline_power = 0;
+        self.clipped = 0;
+        metrics
+    }
+
+    // Matched beats in percent of beats found by any of the detectors, a window without
+    // beats cannot be judged, e.g. in asystole
+    fn agreement(&mut self) -> Option<u8> {
+        let lag = self.sample_rate * QualityIndex::LAG_MS / 1000;
+        let end = self.index.wrapping_sub(lag);
+        let start = end.wrapping_sub(self.window_len);
+        let tolerance = self.sample_rate * QualityIndex::MATCH_MS / 1000;
+        let within = |beat: u32| beat.wrapping_sub(start) < end.wrapping_sub(start);
+
+        let mut matched = 0;
+        let mut beats = 0;
+        for beat in self.beats.iter().copied().filter(|beat| within(*beat)) {
+            beats += 1;
+            let found = self
+                .slope_beats
+                .iter()
+                .any(|slope| beat.max(*slope) - beat.min(*slope) <= tolerance);
+            if found {
+                matched += 1;
+            }
+        }
+        let slope_beats = self
+            .slope_beats
+            .iter()
+            .filter(|beat| within(**beat))
+            .count();
+        let all = beats + slope_beats - matched;
+
+        // Drop beats older than the next window
+        for queue in [&mut self.beats, &mut self.slope_beats].iter_mut() {
+            while let Some(beat) = queue.peek() {
+                if end.wrapping_sub(*beat) as i32 <= tolerance as i32 {
+                    break;
+                }
+                queue.dequeue();
+            }
+        }
+
+        if all == 0 {
+            return None;
+        }
+        Some((matched * 100 / all) as u8)
+    }
+
+    fn push(queue: &mut Queue<u32, U64, u8, SingleCore>, beat: u32) {
+        if queue.len() == queue.capacity() {
+            queue.dequeue();
+        }
+        // Cannot fail, there is always room after the check above
+        queue.enqueue(beat).ok();
+    }
+}
+
+// Beats from the absolute slope crossing half of its recent maximum
+struct SlopeDetector {
+    refractory: u32,
+    decay: u32,
+    elapsed: u32,
+    last: i32,
+    threshold: u32,
+    max: u32,
+    last_beat: Option<u32>,
+}
+
+impl SlopeDetector {
+    const REFRACTORY_MS: u32 = 250;
+    // The maximum is halved every 2 s
+    const DECAY_MS: u32 = 2000;
+
+    fn new(sample_rate: u32) -> Self {
+        SlopeDetector {
+            refractory: sample_rate * SlopeDetector::REFRACTORY_MS / 1000,
+            decay: sample_rate * SlopeDetector::DECAY_MS / 1000,
+            elapsed: 0,
+            last: 0,
+            threshold: u32::MAX,
+            max: 0,
+            last_beat: None,
+        }
+    }
+
+    fn process(&mut self, index: u32, sample: u16) -> Option<u32> {
+        let slope = (sample as i32 - self.last).unsigned_abs();
+        self.last = sample as i32;
+        self.elapsed += 1;
+        if self.elapsed >= self.decay {
+            self.elapsed = 0;
+            self.threshold = self.max / 2;
+            self.max /= 2;
+        }
+        self.max = self.max.max(slope);
+
+        let refractory = match self.last_beat {
+            Some(last_beat) => index.wrapping_sub(last_beat) < self.refractory,
+            None => false,
+        };
+        if slope > self.threshold && !refractory {
+            self.last_beat = Some(index);
+            return Some(index);
+        }
+        None
+    }
+}
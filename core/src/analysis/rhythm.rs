@@ -0,0 +1,328 @@
+use heapless::consts::{U32, U33, U4};
+use heapless::Vec;
+
+use crate::analysis::Window;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Rhythm {
+    Bradycardia,
+    Tachycardia,
+    Pause,
+    AtrialFibrillation,
+}
+
+impl Rhythm {
+    pub fn name(self) -> &'static str {
+        match self {
+            Rhythm::Bradycardia => "BRADY",
+            Rhythm::Tachycardia => "TACHY",
+            Rhythm::Pause => "PAUSE",
+            Rhythm::AtrialFibrillation => "AFIB",
+        }
+    }
+
+    fn slot(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+pub enum Severity {
+    Advisory,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Advisory => "advisory",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RhythmEvent {
+    pub rhythm: Rhythm,
+    pub severity: Severity,
+    // Sample indices, the end is `None` while the event lasts
+    pub start: u32,
+    pub end: Option<u32>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RhythmConfig {
+    // Rates in BPM
+    pub bradycardia: u16,
+    pub severe_bradycardia: u16,
+    pub tachycardia: u16,
+    pub severe_tachycardia: u16,
+    // Longest gap between beats that is not a pause, twice as long is critical
+    pub pause_ms: u32,
+}
+
+impl Default for RhythmConfig {
+    fn default() -> Self {
+        RhythmConfig {
+            bradycardia: 50,
+            severe_bradycardia: 40,
+            tachycardia: 120,
+            severe_tachycardia: 150,
+            pause_ms: 3000,
+        }
+    }
+}
+
+// Rhythm analysis of the detected beats.
+//
+// Bradycardia and tachycardia are judged from the mean rate of the last 8 beats with
+// a hysteresis of 5 BPM. Irregularly irregular rhythm suggestive of atrial fibrillation
+// is flagged when the last 32 R-R intervals have normalized RMSSD above 10 % and the
+// count of turning points is within the range expected for a random series, which
+// rules out sinus arrhythmia as well as bigeminy.
+pub struct RhythmAnalyzer {
+    config: RhythmConfig,
+    sample_rate: u32,
+    last_beat: Option<u32>,
+    // R-R intervals in samples
+    intervals: Window<U32>,
+    // Number of intervals pushed, saturates at the window length
+    count: usize,
+    // Beat indices at the start of the rate and irregularity windows
+    beats: Window<U33>,
+    active: [Option<RhythmEvent>; 4],
+    changes: Vec<RhythmEvent, U4>,
+}
+
+impl RhythmAnalyzer {
+    const RATE_BEATS: usize = 8;
+    const IRREGULAR_BEATS: usize = 32;
+    const HYSTERESIS_BPM: u32 = 5;
+    // Normalized RMSSD threshold in percent
+    const IRREGULAR_RMSSD: u32 = 10;
+    // Turning points expected in a random series of 32 intervals are 20 +- 2 sigma
+    const TURNING_POINTS: (usize, usize) = (16, 24);
+    const RHYTHMS: [Rhythm; 4] = [
+        Rhythm::Bradycardia,
+        Rhythm::Tachycardia,
+        Rhythm::Pause,
+        Rhythm::AtrialFibrillation,
+    ];
+
+    pub fn new(sample_rate: u32, config: RhythmConfig) -> Self {
+        RhythmAnalyzer {
+            config,
+            sample_rate,
+            last_beat: None,
+            intervals: Window::new(RhythmAnalyzer::IRREGULAR_BEATS),
+            count: 0,
+            beats: Window::new(RhythmAnalyzer::IRREGULAR_BEATS + 1),
+            active: [None; 4],
+            changes: Vec::new(),
+        }
+    }
+
+    pub fn set_config(&mut self, config: RhythmConfig) {
+        self.config = config;
+    }
+
+    // Feeds sample index of the detected beat, returns events that started, changed
+    // severity or ended with this beat
+    pub fn beat(&mut self, index: u32) -> &[RhythmEvent] {
+        self.changes = Vec::new();
+        self.beats.push(index as i32);
+        let last_beat = match self.last_beat.replace(index) {
+            Some(last_beat) => last_beat,
+            None => return &self.changes,
+        };
+
+        // Any beat terminates the pause
+        self.set(Rhythm::Pause, None, last_beat, index);
+
+        self.intervals.push(index.wrapping_sub(last_beat) as i32);
+        self.count = (self.count + 1).min(RhythmAnalyzer::IRREGULAR_BEATS);
+
+        if self.count >= RhythmAnalyzer::RATE_BEATS {
+            let start = self.beat_before(RhythmAnalyzer::RATE_BEATS);
+            let rate = self.rate();
+            let bradycardia = self.bradycardia(rate);
+            self.set(Rhythm::Bradycardia, bradycardia, start, index);
+            let tachycardia = self.tachycardia(rate);
+            self.set(Rhythm::Tachycardia, tachycardia, start, index);
+        }
+        if self.count >= RhythmAnalyzer::IRREGULAR_BEATS {
+            let start = self.beat_before(RhythmAnalyzer::IRREGULAR_BEATS);
+            let irregular = if self.is_irregular() {
+                Some(Severity::Warning)
+            } else {
+                None
+            };
+            self.set(Rhythm::AtrialFibrillation, irregular, start, index);
+        }
+        &self.changes
+    }
+
+    // Checks for a pause at the current sample index, to be called periodically
+    pub fn update(&mut self, now: u32) -> &[RhythmEvent] {
+        self.changes = Vec::new();
+        if let Some(last_beat) = self.last_beat {
+            let gap = now.wrapping_sub(last_beat) as u64 * 1000 / self.sample_rate as u64;
+            let pause = self.config.pause_ms as u64;
+            let severity = if gap > 2 * pause {
+                Some(Severity::Critical)
+            } else if gap > pause {
+                Some(Severity::Warning)
+            } else {
+                None
+            };
+            if severity.is_some() {
+                self.set(Rhythm::Pause, severity, last_beat, now);
+            }
+        }
+        &self.changes
+    }
+
+    // Ongoing events
+    pub fn active(&self) -> impl Iterator<Item = &RhythmEvent> + '_ {
+        self.active.iter().filter_map(|event| event.as_ref())
+    }
+
+    // The most severe ongoing event
+    pub fn alarm(&self) -> Option<RhythmEvent> {
+        self.active()
+            .fold(None, |alarm: Option<RhythmEvent>, event| match alarm {
+                Some(alarm) if alarm.severity >= event.severity => Some(alarm),
+                _ => Some(*event),
+            })
+    }
+
+    // Feeds a beat from a signal too poor to judge the rate and irregularity, it still
+    // ends a pause. Returns the events ended with it.
+    pub fn interrupt(&mut self, index: u32) -> &[RhythmEvent] {
+        self.changes = Vec::new();
+        for rhythm in RhythmAnalyzer::RHYTHMS.iter() {
+            self.set(*rhythm, None, index, index);
+        }
+        self.last_beat = Some(index);
+        self.beats.push(index as i32);
+        self.count = 0;
+        &self.changes
+    }
+
+    // Starts over when the rhythm cannot be followed, returns the ongoing events ended
+    // at the sample index
+    pub fn reset(&mut self, now: u32) -> &[RhythmEvent] {
+        self.changes = Vec::new();
+        for rhythm in RhythmAnalyzer::RHYTHMS.iter() {
+            self.set(*rhythm, None, now, now);
+        }
+        self.last_beat = None;
+        self.count = 0;
+        &self.changes
+    }
+
+    fn set(&mut self, rhythm: Rhythm, severity: Option<Severity>, start: u32, index: u32) {
+        let slot = &mut self.active[rhythm.slot()];
+        let change = match (slot.as_mut(), severity) {
+            (None, None) => None,
+            (None, Some(severity)) => {
+                let event = RhythmEvent {
+                    rhythm,
+                    severity,
+                    start,
+                    end: None,
+                };
+                *slot = Some(event);
+                Some(event)
+            }
+            (Some(event), Some(severity)) if event.severity != severity => {
+                event.severity = severity;
+                Some(*event)
+            }
+            (Some(_), Some(_)) => None,
+            (Some(event), None) => {
+                let mut event = *event;
+                event.end = Some(index);
+                *slot = None;
+                Some(event)
+            }
+        };
+        if let Some(change) = change {
+            // Cannot fail, every rhythm changes at most once per call
+            self.changes.push(change).ok();
+        }
+    }
+
+    // Index of the beat `beats` intervals before the last one
+    fn beat_before(&self, beats: usize) -> u32 {
+        self.beats.get(beats) as u32
+    }
+
+    // Mean rate of the last `RATE_BEATS` intervals in BPM
+    fn rate(&self) -> u32 {
+        let sum: u32 = (0..RhythmAnalyzer::RATE_BEATS)
+            .map(|age| self.intervals.get(age) as u32)
+            .sum();
+        60 * self.sample_rate * RhythmAnalyzer::RATE_BEATS as u32 / sum.max(1)
+    }
+
+    fn bradycardia(&self, rate: u32) -> Option<Severity> {
+        let active = self.active[Rhythm::Bradycardia.slot()].is_some();
+        let hysteresis = if active {
+            RhythmAnalyzer::HYSTERESIS_BPM
+        } else {
+            0
+        };
+        if rate < self.config.severe_bradycardia as u32 {
+            Some(Severity::Critical)
+        } else if rate < self.config.bradycardia as u32 + hysteresis {
+            Some(Severity::Warning)
+        } else {
+            None
+        }
+    }
+
+    fn tachycardia(&self, rate: u32) -> Option<Severity> {
+        let active = self.active[Rhythm::Tachycardia.slot()].is_some();
+        let hysteresis = if active {
+            RhythmAnalyzer::HYSTERESIS_BPM
+        } else {
+            0
+        };
+        if rate > self.config.severe_tachycardia as u32 {
+            Some(Severity::Critical)
+        } else if rate + hysteresis > self.config.tachycardia as u32 {
+            Some(Severity::Warning)
+        } else {
+            None
+        }
+    }
+
+    fn is_irregular(&self) -> bool {
+        let len = RhythmAnalyzer::IRREGULAR_BEATS;
+        let mean = self.intervals.sum() as u64 / len as u64;
+        let mut sum_differences = 0u64;
+        let mut turning_points = 0;
+        for age in 1..len {
+            let difference = (self.intervals.get(age) - self.intervals.get(age - 1)).unsigned_abs();
+            sum_differences += difference as u64 * difference as u64;
+            if age + 1 < len {
+                let (newer, middle, older) = (
+                    self.intervals.get(age - 1),
+                    self.intervals.get(age),
+                    self.intervals.get(age + 1),
+                );
+                if (middle > newer && middle > older) || (middle < newer && middle < older) {
+                    turning_points += 1;
+                }
+            }
+        }
+        // RMSSD / mean > threshold compared squared to stay in integers
+        let mean_square = sum_differences / (len as u64 - 1);
+        let threshold = mean * RhythmAnalyzer::IRREGULAR_RMSSD as u64 / 100;
+        let (min, max) = RhythmAnalyzer::TURNING_POINTS;
+        mean_square > threshold * threshold && (min..=max).contains(&turning_points)
+    }
+}
@@ -0,0 +1,97 @@
+// Summary of a recording session started and stopped by the user
+pub struct Session {
+    sample_rate: u32,
+    start: Option<u32>,
+    end: Option<u32>,
+    beats: u32,
+    min: u16,
+    max: u16,
+    sum: u32,
+    rates: u32,
+    pvcs: u32,
+    events: u32,
+}
+
+impl Session {
+    pub fn new(sample_rate: u32) -> Self {
+        Session {
+            sample_rate,
+            start: None,
+            end: None,
+            beats: 0,
+            min: u16::MAX,
+            max: 0,
+            sum: 0,
+            rates: 0,
+            pvcs: 0,
+            events: 0,
+        }
+    }
+
+    // Starts a new session, the previous one is discarded
+    pub fn start(&mut self, now: u32) {
+        *self = Session::new(self.sample_rate);
+        self.start = Some(now);
+    }
+
+    pub fn stop(&mut self, now: u32) {
+        if self.is_running() {
+            self.end = Some(now);
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.start.is_some() && self.end.is_none()
+    }
+
+    // Takes the rate if the beat was accepted
+    pub fn beat(&mut self, bpm: Option<u16>, pvc: bool) {
+        if !self.is_running() {
+            return;
+        }
+        self.beats += 1;
+        if pvc {
+            self.pvcs += 1;
+        }
+        if let Some(bpm) = bpm {
+            self.min = self.min.min(bpm);
+            self.max = self.max.max(bpm);
+            self.sum += bpm as u32;
+            self.rates += 1;
+        }
+    }
+
+    // Rhythm events that started
+    pub fn add_events(&mut self, count: u32) {
+        if self.is_running() {
+            self.events += count;
+        }
+    }
+
+    // Seconds since the start, until the end once stopped
+    pub fn duration(&self, now: u32) -> Option<u32> {
+        let start = self.start?;
+        let end = self.end.unwrap_or(now);
+        Some(end.wrapping_sub(start) / self.sample_rate)
+    }
+
+    pub fn beats(&self) -> u32 {
+        self.beats
+    }
+
+    pub fn pvcs(&self) -> u32 {
+        self.pvcs
+    }
+
+    pub fn events(&self) -> u32 {
+        self.events
+    }
+
+    // Minimum, mean and maximum rate
+    pub fn rates(&self) -> Option<(u16, u16, u16)> {
+        if self.rates == 0 {
+            return None;
+        }
+        Some((self.min, (self.sum / self.rates) as u16, self.max))
+    }
+}
@@ -0,0 +1,210 @@
+use crate::analysis::hrv::Hrv;
+use crate::math::{cos, round, PI};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct FrequencyMetrics {
+    // Band powers in ms^2
+    pub lf: u32,
+    pub hf: u32,
+    // LF/HF multiplied by 100
+    pub ratio: u16,
+}
+
+// Frequency-domain heart rate variability.
+//
+// The NN series is linearly interpolated to evenly spaced 2 Hz samples, the linear
+// trend is removed and the Hann windowed series is transformed by fixed-point radix-2
+// FFT. 512 samples cover the last 256 s of the HRV window with 1/256 Hz resolution,
+// the buffers take 4 KiB.
+pub struct HrvSpectrum {
+    cosine: [i16; HrvSpectrum::QUARTER + 1],
+    real: [i32; HrvSpectrum::LEN],
+    imaginary: [i32; HrvSpectrum::LEN],
+}
+
+impl HrvSpectrum {
+    pub const LEN: usize = 512;
+    const BITS: u32 = 9;
+    const QUARTER: usize = HrvSpectrum::LEN / 4;
+    // Resampling period, 2 Hz
+    pub const PERIOD_MS: u32 = 500;
+    // Fraction bits of the resampled NN series in ms
+    const SCALE_BITS: u32 = 8;
+    // Band edges in mHz
+    const LF: (u32, u32) = (40, 150);
+    const HF: (u32, u32) = (150, 400);
+
+    pub fn new() -> Self {
+        let mut cosine = [0; HrvSpectrum::QUARTER + 1];
+        for (n, value) in cosine.iter_mut().enumerate() {
+            let angle = 2.0 * PI * n as f64 / HrvSpectrum::LEN as f64;
+            *value = round(cos(angle) * i16::MAX as f64) as i16;
+        }
+        HrvSpectrum {
+            cosine,
+            real: [0; HrvSpectrum::LEN],
+            imaginary: [0; HrvSpectrum::LEN],
+        }
+    }
+
+    // `None` until the HRV window covers the whole analysed span
+    pub fn analyze(&mut self, hrv: &Hrv) -> Option<FrequencyMetrics> {
+        self.resample(hrv)?;
+        self.detrend();
+        self.window();
+        self.fft();
+
+        let lf = self.band_power(HrvSpectrum::LF);
+        let hf = self.band_power(HrvSpectrum::HF);
+        let ratio = if hf == 0 {
+            u16::MAX
+        } else {
+            (lf as u64 * 100 / hf as u64).min(u16::MAX as u64) as u16
+        };
+        Some(FrequencyMetrics { lf, hf, ratio })
+    }
+
+    fn resample(&mut self, hrv: &Hrv) -> Option<()> {
+        let window = Hrv::LONG_TERM_MS;
+        let (end, _) = hrv.nn_samples(window).last()?;
+        let span = (HrvSpectrum::LEN as u32 - 1) * HrvSpectrum::PERIOD_MS;
+        let start = end.checked_sub(span)?;
+
+        let mut index = 0;
+        let mut previous: Option<(u32, u16)> = None;
+        for (time, value) in hrv.nn_samples(window) {
+            let (last_time, last_value) = match previous.replace((time, value)) {
+                Some(previous) => previous,
+                None if time > start => return None,
+                None => continue,
+            };
+            while index < HrvSpectrum::LEN {
+                let grid = start + index as u32 * HrvSpectrum::PERIOD_MS;
+                if grid > time {
+                    break;
+                }
+                let value = if grid <= last_time || time == last_time {
+                    last_value as i64
+                } else {
+                    let slope = value as i64 - last_value as i64;
+                    last_value as i64
+                        + slope * (grid - last_time) as i64 / (time - last_time) as i64
+                };
+                self.real[index] = (value << HrvSpectrum::SCALE_BITS) as i32;
+                self.imaginary[index] = 0;
+                index += 1;
+            }
+        }
+        if index == HrvSpectrum::LEN {
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    // Least squares line fit subtracted from the series
+    fn detrend(&mut self) {
+        let len = HrvSpectrum::LEN as i64;
+        let (mut sum_x, mut sum_nx) = (0i64, 0i64);
+        for (n, value) in self.real.iter().enumerate() {
+            sum_x += *value as i64;
+            sum_nx += n as i64 * *value as i64;
+        }
+        let sum_n = len * (len - 1) / 2;
+        let sum_nn = (len - 1) * len * (2 * len - 1) / 6;
+        let denominator = len * sum_nn - sum_n * sum_n;
+        let slope = len * sum_nx - sum_n * sum_x;
+        let intercept = sum_x * sum_nn - sum_n * sum_nx;
+        for (n, value) in self.real.iter_mut().enumerate() {
+            let trend = (intercept + slope * n as i64) / denominator;
+            *value -= trend as i32;
+        }
+    }
+
+    // Hann window
+    fn window(&mut self) {
+        for n in 0..HrvSpectrum::LEN {
+            let cosine = self.cos(n) as i64;
+            let weight = ((1 << 15) - cosine) / 2;
+            self.real[n] = ((self.real[n] as i64 * weight) >> 15) as i32;
+        }
+    }
+
+    // In place radix-2 decimation in time, every stage is scaled by 1/2 so the result
+    // is the DFT divided by `LEN`
+    fn fft(&mut self) {
+        let len = HrvSpectrum::LEN;
+        for n in 0..len {
+            let reversed =
+                (0..HrvSpectrum::BITS).fold(0, |reversed, bit| (reversed << 1) | ((n >> bit) & 1));
+            if reversed > n {
+                self.real.swap(n, reversed);
+                self.imaginary.swap(n, reversed);
+            }
+        }
+
+        let mut size = 2;
+        while size <= len {
+            let step = len / size;
+            for start in (0..len).step_by(size) {
+                for k in 0..size / 2 {
+                    let (c, s) = (self.cos(k * step) as i64, self.sin(k * step) as i64);
+                    let a = start + k;
+                    let b = a + size / 2;
+                    let (br, bi) = (self.real[b] as i64, self.imaginary[b] as i64);
+                    let tr = ((br * c + bi * s) >> 15) as i32;
+                    let ti = ((bi * c - br * s) >> 15) as i32;
+                    let (ar, ai) = (self.real[a], self.imaginary[a]);
+                    self.real[a] = (ar + tr) >> 1;
+                    self.imaginary[a] = (ai + ti) >> 1;
+                    self.real[b] = (ar - tr) >> 1;
+                    self.imaginary[b] = (ai - ti) >> 1;
+                }
+            }
+            size <<= 1;
+        }
+    }
+
+    // One-sided power in ms^2 of bins within `band` given in mHz
+    fn band_power(&self, band: (u32, u32)) -> u32 {
+        // Bin width is 1000 / (LEN * PERIOD_MS) Hz
+        let span = HrvSpectrum::LEN as u32 * HrvSpectrum::PERIOD_MS;
+        let first = (band.0 * span / 1_000_000) as usize;
+        let last = (band.1 * span / 1_000_000) as usize;
+        let sum: u64 = (first..last)
+            .map(|k| {
+                let (re, im) = (self.real[k] as i64, self.imaginary[k] as i64);
+                (re * re + im * im) as u64
+            })
+            .sum();
+        // Two sided spectrum folded, Hann window power 3/8 compensated
+        let power = sum * 16 / 3;
+        (power >> (2 * HrvSpectrum::SCALE_BITS)) as u32
+    }
+
+    // cos(2 * PI * n / LEN) in Q15
+    fn cos(&self, n: usize) -> i16 {
+        let len = HrvSpectrum::LEN;
+        let mut n = n % len;
+        if n > len / 2 {
+            n = len - n;
+        }
+        if n <= HrvSpectrum::QUARTER {
+            self.cosine[n]
+        } else {
+            -self.cosine[len / 2 - n]
+        }
+    }
+
+    // sin(2 * PI * n / LEN) in Q15
+    fn sin(&self, n: usize) -> i16 {
+        let len = HrvSpectrum::LEN;
+        self.cos(n + len - HrvSpectrum::QUARTER)
+    }
+}
+
+impl Default for HrvSpectrum {
+    fn default() -> Self {
+        HrvSpectrum::new()
+    }
+}
@@ -0,0 +1,96 @@
+use heapless::consts::U512;
+use heapless::spsc::{Queue, SingleCore};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TrendPoint {
+    // All in BPM
+    pub min: u8,
+    pub mean: u8,
+    pub max: u8,
+}
+
+// Heart rate trend of the last 8.5 hours with one point per minute.
+//
+// Minutes are counted in samples from the first update, a minute without any accepted
+// beat is stored as a gap.
+pub struct HrTrend {
+    minute_len: u32,
+    minute_start: Option<u32>,
+    min: u16,
+    max: u16,
+    sum: u32,
+    beats: u16,
+    points: Queue<Option<TrendPoint>, U512, u16, SingleCore>,
+}
+
+impl HrTrend {
+    const MINUTE_MS: u32 = 60_000;
+
+    pub fn new(sample_rate: u32) -> Self {
+        HrTrend {
+            minute_len: sample_rate * HrTrend::MINUTE_MS / 1000,
+            minute_start: None,
+            min: u16::MAX,
+            max: 0,
+            sum: 0,
+            beats: 0,
+            points: unsafe { Queue::u16_sc() },
+        }
+    }
+
+    // Rate after an accepted beat
+    pub fn beat(&mut self, bpm: u16) {
+        self.min = self.min.min(bpm);
+        self.max = self.max.max(bpm);
+        self.sum += bpm as u32;
+        self.beats += 1;
+    }
+
+    // Takes the current sample index, returns true if a minute was closed
+    pub fn update(&mut self, now: u32) -> bool {
+        let mut minute_start = *self.minute_start.get_or_insert(now);
+        let mut closed = false;
+        while now.wrapping_sub(minute_start) >= self.minute_len {
+            minute_start = minute_start.wrapping_add(self.minute_len);
+            self.close();
+            closed = true;
+        }
+        self.minute_start = Some(minute_start);
+        closed
+    }
+
+    // Oldest first, `None` for minutes without beats
+    pub fn points(&self) -> impl Iterator<Item = &Option<TrendPoint>> {
+        self.points.iter()
+    }
+
+    // Number of stored minutes
+    pub fn len(&self) -> usize {
+        self.points.len() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    fn close(&mut self) {
+        let point = if self.beats > 0 {
+            Some(TrendPoint {
+                min: self.min.min(u8::MAX as u16) as u8,
+                mean: (self.sum / self.beats as u32).min(u8::MAX as u32) as u8,
+                max: self.max.min(u8::MAX as u16) as u8,
+            })
+        } else {
+            None
+        };
+        if self.points.len() == self.points.capacity() {
+            self.points.dequeue();
+        }
+        // Cannot fail, there is always room after the check above
+        self.points.enqueue(point).ok();
+        self.min = u16::MAX;
+        self.max = 0;
+        self.sum = 0;
+        self.beats = 0;
+    }
+}
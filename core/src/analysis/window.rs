@@ -0,0 +1,65 @@
+use heapless::{ArrayLength, Vec};
+
+// Fixed capacity ring of the last `len` values with a running sum
+pub struct Window<N>
+where
+    N: ArrayLength<i32>,
+{
+    data: Vec<i32, N>,
+    position: usize,
+    sum: i32,
+}
+
+impl<N> Window<N>
+where
+    N: ArrayLength<i32>,
+{
+    pub fn new(len: usize) -> Self {
+        let mut data = Vec::new();
+        let len = len.clamp(1, data.capacity());
+        // Cannot fail, the length is clamped to the capacity
+        data.resize(len, 0).ok();
+        Window {
+            data,
+            position: 0,
+            sum: 0,
+        }
+    }
+
+    pub fn fill(&mut self, value: i32) {
+        for item in self.data.iter_mut() {
+            *item = value;
+        }
+        self.sum = value * self.data.len() as i32;
+    }
+
+    // Returns the value that fell out of the window
+    pub fn push(&mut self, value: i32) -> i32 {
+        let old = core::mem::replace(&mut self.data[self.position], value);
+        self.position += 1;
+        if self.position >= self.data.len() {
+            self.position = 0;
+        }
+        self.sum += value - old;
+        old
+    }
+
+    // Age 0 is the most recently pushed value
+    pub fn get(&self, age: usize) -> i32 {
+        let len = self.data.len();
+        let age = age.min(len - 1);
+        self.data[(self.position + len - 1 - age) % len]
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn sum(&self) -> i32 {
+        self.sum
+    }
+
+    pub fn max(&self) -> i32 {
+        self.data.iter().copied().max().unwrap_or(0)
+    }
+}
@@ -0,0 +1,74 @@
+use crate::analysis::pvc::BeatClass;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Mark {
+    // Tick above the trace at the R peak
+    Beat,
+    // Tick with a label above the trace
+    Pvc,
+    // Bar below the trace
+    LeadOff,
+    // Tick below the trace
+    User,
+    // Line across the trace where samples were lost
+    Gap,
+}
+
+// Mark of the samples from `start` to `end`, counted from power-on like the sample
+// indices of the analyses
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Annotation {
+    pub mark: Mark,
+    pub start: u32,
+    pub end: u32,
+}
+
+impl Annotation {
+    pub fn beat(index: u32, class: BeatClass) -> Self {
+        let mark = match class {
+            BeatClass::Pvc => Mark::Pvc,
+            BeatClass::Normal | BeatClass::Unknown => Mark::Beat,
+        };
+        Annotation {
+            mark,
+            start: index,
+            end: index,
+        }
+    }
+
+    pub fn lead_off(start: u32, end: u32) -> Self {
+        Annotation {
+            mark: Mark::LeadOff,
+            start,
+            end,
+        }
+    }
+
+    pub fn user(index: u32) -> Self {
+        Annotation {
+            mark: Mark::User,
+            start: index,
+            end: index,
+        }
+    }
+
+    pub fn gap(index: u32) -> Self {
+        Annotation {
+            mark: Mark::Gap,
+            start: index,
+            end: index,
+        }
+    }
+}
+
+pub(crate) struct Marker;
+
+impl Marker {
+    pub(crate) const TICK_HEIGHT: i32 = 6;
+    pub(crate) const BAR_HEIGHT: i32 = 3;
+    pub(crate) const USER_HEIGHT: i32 = 10;
+    // The label is right of the tick, in the columns drawn after it
+    pub(crate) const LABEL: &'static str = "V";
+    pub(crate) const LABEL_WIDTH: u16 = 6;
+    pub(crate) const LABEL_HEIGHT: u16 = 8;
+}
@@ -0,0 +1,110 @@
+use super::grid::Scale;
+
+// Automatic centering and gain of the trace.
+//
+// The minimum and maximum are tracked in blocks of 2 s, the trace is fitted to the last
+// two blocks. The center moves only once the baseline is off by more than a tenth of
+// the frame and the gain only once the peak to peak leaves 75..125 % of the target
+// height, so the trace does not jitter from beat to beat. While locked the scale stays
+// as it is.
+pub(crate) struct AutoScale {
+    block_len: u32,
+    position: u32,
+    min: u16,
+    max: u16,
+    previous: Option<(u16, u16)>,
+    height: i32,
+    // Target peak to peak in percent of the frame height
+    fraction: u8,
+    locked: bool,
+}
+
+impl AutoScale {
+    const BLOCK_MS: u32 = 2000;
+    // 0.1 mV at the electrodes, a flat line is not blown up to the full height
+    const MIN_SPAN_MV: i32 = 110;
+    // Gain limits in tenths of mm/mV
+    const MIN_GAIN: i32 = 25;
+    const MAX_GAIN: i32 = 400;
+
+    pub(crate) fn new(sample_rate: u32, height: i32, fraction: u8) -> Self {
+        AutoScale {
+            block_len: (sample_rate * AutoScale::BLOCK_MS / 1000).max(1),
+            position: 0,
+            min: u16::MAX,
+            max: 0,
+            previous: None,
+            height,
+            fraction: fraction.clamp(10, 100),
+            locked: false,
+        }
+    }
+
+    pub(crate) fn set_fraction(&mut self, fraction: u8) {
+        self.fraction = fraction.clamp(10, 100);
+    }
+
+    pub(crate) fn set_locked(&mut self, locked: bool) {
+        self.locked = locked;
+    }
+
+    pub(crate) fn locked(&self) -> bool {
+        self.locked
+    }
+
+    // Restarts tracking, e.g. after the leads were off
+    pub(crate) fn reset(&mut self) {
+        self.position = 0;
+        self.min = u16::MAX;
+        self.max = 0;
+        self.previous = None;
+    }
+
+    // Returns true if the scale was changed
+    pub(crate) fn process(&mut self, sample: u16, scale: &mut Scale) -> bool {
+        self.min = self.min.min(sample);
+        self.max = self.max.max(sample);
+        self.position += 1;
+        if self.position < self.block_len {
+            return false;
+        }
+        let block = (self.min, self.max);
+        let (min, max) = match self.previous {
+            Some((min, max)) => (min.min(block.0), max.max(block.1)),
+            None => block,
+        };
+        self.previous = Some(block);
+        self.position = 0;
+        self.min = u16::MAX;
+        self.max = 0;
+        if self.locked {
+            return false;
+        }
+        self.fit(min as i32, max as i32, scale)
+    }
+
+    fn fit(&self, min: i32, max: i32, scale: &mut Scale) -> bool {
+        let target = self.height * self.fraction as i32 / 100;
+        let span = (max - min).max(AutoScale::MIN_SPAN_MV);
+        let height = scale.pixels(span);
+        let mut gain = scale.gain_x10();
+        if height < target * 3 / 4 || height > target * 5 / 4 {
+            gain = scale
+                .gain_for(span, target)
+                .clamp(AutoScale::MIN_GAIN, AutoScale::MAX_GAIN);
+        }
+
+        let mut center = scale.center();
+        let middle = (min + max) / 2;
+        let shift = scale.pixels(middle - center).abs();
+        if gain != scale.gain_x10() || shift > self.height / 10 {
+            center = middle;
+        }
+
+        let changed = gain != scale.gain_x10() || center != scale.center();
+        if changed {
+            scale.set_auto(center, gain);
+        }
+        changed
+    }
+}
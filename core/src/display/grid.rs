@@ -0,0 +1,257 @@
+use heapless::consts::U128;
+use heapless::Vec;
+
+use crate::sampler::BASELINE_MV;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SweepSpeed {
+    Mm12_5,
+    Mm25,
+    Mm50,
+}
+
+impl SweepSpeed {
+    // Speed in tenths of mm/s
+    fn speed(self) -> u32 {
+        match self {
+            SweepSpeed::Mm12_5 => 125,
+            SweepSpeed::Mm25 => 250,
+            SweepSpeed::Mm50 => 500,
+        }
+    }
+
+    pub fn next(self) -> SweepSpeed {
+        match self {
+            SweepSpeed::Mm12_5 => SweepSpeed::Mm25,
+            SweepSpeed::Mm25 => SweepSpeed::Mm50,
+            SweepSpeed::Mm50 => SweepSpeed::Mm12_5,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SweepSpeed::Mm12_5 => "12.5mm/s",
+            SweepSpeed::Mm25 => "25mm/s",
+            SweepSpeed::Mm50 => "50mm/s",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Gain {
+    Mm5,
+    Mm10,
+    Mm20,
+}
+
+impl Gain {
+    // Gain in tenths of mm/mV
+    fn gain(self) -> i32 {
+        match self {
+            Gain::Mm5 => 50,
+            Gain::Mm10 => 100,
+            Gain::Mm20 => 200,
+        }
+    }
+
+    pub fn next(self) -> Gain {
+        match self {
+            Gain::Mm5 => Gain::Mm10,
+            Gain::Mm10 => Gain::Mm20,
+            Gain::Mm20 => Gain::Mm5,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Gain::Mm5 => "5mm/mV",
+            Gain::Mm10 => "10mm/mV",
+            Gain::Mm20 => "20mm/mV",
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, PartialOrd)]
+pub enum GridLine {
+    None,
+    Minor,
+    Major,
+}
+
+// ECG paper of 1 mm boxes with every fifth line major, drawn in physical units.
+//
+// Rows are fixed for the whole frame, vertical lines belong to columns as the paper
+// moves with the trace. Every column is told its line when it is drawn, so the grid
+// stays continuous across the hardware scroll and its wrap around.
+pub struct Grid {
+    pitch_um: u32,
+    // Row offsets from the bottom of the frame
+    rows: Vec<(u16, GridLine), U128>,
+    // Paper position of the last column within a major box in um
+    paper_um: u32,
+}
+
+impl Grid {
+    const MINOR_UM: u32 = 1000;
+    const MAJOR_UM: u32 = 5000;
+
+    // Rows are centered around `center`, the baseline of the trace
+    pub fn new(pitch_um: u32, height: u16, center: u16) -> Self {
+        let mut rows = Vec::new();
+        for millimeter in 0.. {
+            let offset = ((millimeter * Grid::MINOR_UM + pitch_um / 2) / pitch_um) as u16;
+            if offset > center && center + offset >= height {
+                break;
+            }
+            let line = if millimeter % 5 == 0 {
+                GridLine::Major
+            } else {
+                GridLine::Minor
+            };
+            let below = center.checked_sub(offset);
+            let above = Some(center + offset).filter(|above| offset > 0 && *above < height);
+            for row in below.iter().chain(above.iter()) {
+                // Cannot fail, 128 rows cover more than the tallest frame
+                rows.push((*row, line)).ok();
+            }
+        }
+        Grid {
+            pitch_um,
+            rows,
+            paper_um: 0,
+        }
+    }
+
+    // Vertical line of the next column
+    pub fn next_column(&mut self) -> GridLine {
+        let previous = self.paper_um;
+        self.paper_um += self.pitch_um;
+        if self.paper_um >= Grid::MAJOR_UM {
+            self.paper_um -= Grid::MAJOR_UM;
+            GridLine::Major
+        } else if self.paper_um / Grid::MINOR_UM != previous / Grid::MINOR_UM {
+            GridLine::Minor
+        } else {
+            GridLine::None
+        }
+    }
+
+    // Rows between `bottom` and `top` inclusive
+    pub fn rows(&self, bottom: u16, top: u16) -> impl Iterator<Item = &(u16, GridLine)> {
+        self.rows
+            .iter()
+            .filter(move |(row, _)| (bottom..=top).contains(row))
+    }
+}
+
+// Column advance and sample scaling of the trace
+pub struct Scale {
+    sample_rate: u32,
+    pitch_um: u32,
+    speed: SweepSpeed,
+    gain: Gain,
+    // Gain in use in tenths of mm/mV, differs from `gain` with auto-scale
+    gain_x10: i32,
+    // Sample shown at the baseline in mV at the ADC
+    center: i32,
+    // Columns per sample in 1/65536
+    step: u32,
+    phase: u32,
+}
+
+impl Scale {
+    // AD8232 module, instrumentation amplifier 100 and output stage 11
+    pub const FRONT_END_GAIN: i32 = 1100;
+    // Filtered samples are centered at the baseline of the front-end
+    pub const CENTER: i32 = BASELINE_MV;
+
+    pub fn new(sample_rate: u32, pitch_um: u32, speed: SweepSpeed, gain: Gain) -> Self {
+        let mut scale = Scale {
+            sample_rate,
+            pitch_um,
+            speed,
+            gain,
+            gain_x10: gain.gain(),
+            center: Scale::CENTER,
+            step: 0,
+            phase: 0,
+        };
+        scale.set_speed(speed);
+        scale
+    }
+
+    pub fn set_speed(&mut self, speed: SweepSpeed) {
+        // px / sample = speed / (pitch * sample rate)
+        let step =
+            speed.speed() as u64 * 100 * 65536 / (self.pitch_um as u64 * self.sample_rate as u64);
+        self.speed = speed;
+        self.step = step as u32;
+    }
+
+    // Back to the calibrated gain and center
+    pub fn set_gain(&mut self, gain: Gain) {
+        self.gain = gain;
+        self.gain_x10 = gain.gain();
+        self.center = Scale::CENTER;
+    }
+
+    pub fn set_auto(&mut self, center: i32, gain_x10: i32) {
+        self.center = center;
+        self.gain_x10 = gain_x10;
+    }
+
+    pub fn speed(&self) -> SweepSpeed {
+        self.speed
+    }
+
+    pub fn gain(&self) -> Gain {
+        self.gain
+    }
+
+    pub fn gain_x10(&self) -> i32 {
+        self.gain_x10
+    }
+
+    pub fn center(&self) -> i32 {
+        self.center
+    }
+
+    // Number of columns the sample advances the trace
+    pub fn advance(&mut self) -> u32 {
+        self.phase += self.step;
+        let columns = self.phase >> 16;
+        self.phase &= 0xffff;
+        columns
+    }
+
+    // Number of samples shown in `columns`
+    pub fn samples(&self, columns: i32) -> i32 {
+        (columns as i64 * 65536 / self.step.max(1) as i64) as i32
+    }
+
+    // Number of columns that show `samples`
+    pub fn columns(&self, samples: u32) -> u32 {
+        ((samples as u64 * self.step as u64) >> 16) as u32
+    }
+
+    // Columns from the sample `from` to `to`, negative if `to` is before
+    pub fn columns_between(&self, from: u32, to: u32) -> i32 {
+        let samples = to.wrapping_sub(from) as i32;
+        ((samples as i64 * self.step as i64) >> 16) as i32
+    }
+
+    // Pixel offset of the sample from the baseline, `deviation` is in mV at the ADC
+    pub fn pixels(&self, deviation: i32) -> i32 {
+        deviation * self.gain_x10 * 100 / (Scale::FRONT_END_GAIN * self.pitch_um as i32)
+    }
+
+    // Gain in tenths of mm/mV that shows `span` mV at the ADC as `height` pixels
+    pub fn gain_for(&self, span: i32, height: i32) -> i32 {
+        height * Scale::FRONT_END_GAIN * self.pitch_um as i32 / (span.max(1) * 100)
+    }
+
+    // Height of the 1 mV calibration pulse
+    pub fn calibration(&self) -> i32 {
+        self.pixels(Scale::FRONT_END_GAIN)
+    }
+}
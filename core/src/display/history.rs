@@ -0,0 +1,146 @@
+use heapless::consts::{U32, U64};
+use heapless::spsc::{Queue, SingleCore};
+
+use super::annotation::Annotation;
+use super::grid::Scale;
+
+// Filtered samples of the last seconds for the review of the frozen trace.
+//
+// Samples are decimated to `History::RATE`, keeping the one of each group that is
+// farthest from the center so the QRS peaks survive, and stored as a byte in steps of
+// `History::STEP` mV at the ADC, about a pixel at 10 mm/mV. All indices are in
+// samples counted from the first one pushed.
+//
+// The last annotations are kept as well, they are drawn on the review.
+//
+// While held nothing is overwritten, the samples that arrive meanwhile are counted
+// and stored as a flat line once released.
+pub struct History {
+    samples: [u8; History::LEN],
+    decimation: u32,
+    // Samples pushed into the current group
+    phase: u32,
+    peak: i32,
+    // Index after the newest stored group
+    end: u32,
+    // End when the history was held
+    held: Option<u32>,
+    // Beats with the rate after them, oldest first
+    beats: Queue<(u32, u16), U64, u8, SingleCore>,
+    annotations: Queue<Annotation, U32, u8, SingleCore>,
+    max_gap: u32,
+}
+
+impl History {
+    // 16 s at the stored rate
+    const LEN: usize = 4096;
+    const RATE: u32 = 250;
+    const STEP: i32 = 16;
+    const FLAT: u8 = 128;
+    // No rate is shown for a cursor this far after the last beat
+    const MAX_GAP_MS: u32 = 3000;
+
+    pub fn new(sample_rate: u32) -> Self {
+        History {
+            samples: [History::FLAT; History::LEN],
+            decimation: (sample_rate / History::RATE).max(1),
+            phase: 0,
+            peak: 0,
+            end: 0,
+            held: None,
+            beats: unsafe { Queue::u8_sc() },
+            annotations: unsafe { Queue::u8_sc() },
+            max_gap: sample_rate * History::MAX_GAP_MS / 1000,
+        }
+    }
+
+    pub fn push(&mut self, sample: u16) {
+        let deviation = sample as i32 - Scale::CENTER;
+        if self.phase == 0 || deviation.abs() > self.peak.abs() {
+            self.peak = deviation;
+        }
+        self.phase += 1;
+        if self.phase < self.decimation {
+            return;
+        }
+        self.phase = 0;
+        if self.held.is_none() {
+            let value = (self.peak / History::STEP + History::FLAT as i32).clamp(0, 255);
+            self.samples[self.end as usize % History::LEN] = value as u8;
+        }
+        self.end += 1;
+    }
+
+    // Accepted beat with the rate after it, `index` counts samples like the history
+    pub fn beat(&mut self, index: u32, bpm: u16) {
+        if self.beats.len() as usize == self.beats.capacity() as usize {
+            self.beats.dequeue();
+        }
+        self.beats.enqueue((index, bpm)).ok();
+    }
+
+    // Oldest annotations are dropped first
+    pub fn annotate(&mut self, annotation: Annotation) {
+        if self.annotations.len() as usize == self.annotations.capacity() as usize {
+            self.annotations.dequeue();
+        }
+        self.annotations.enqueue(annotation).ok();
+    }
+
+    // Oldest first
+    pub fn annotations(&self) -> impl Iterator<Item = &Annotation> {
+        self.annotations.iter()
+    }
+
+    // Keeps the stored samples until released
+    pub fn hold(&mut self) {
+        self.held.get_or_insert(self.end);
+    }
+
+    pub fn release(&mut self) {
+        if let Some(held) = self.held.take() {
+            let start = held.max(self.end.saturating_sub(History::LEN as u32));
+            for group in start..self.end {
+                self.samples[group as usize % History::LEN] = History::FLAT;
+            }
+        }
+    }
+
+    // First and after the last index of the stored samples
+    pub fn range(&self) -> (u32, u32) {
+        let end = self.held.unwrap_or(self.end);
+        let start = end.saturating_sub(History::LEN as u32);
+        (start * self.decimation, end * self.decimation)
+    }
+
+    // Sample in mV at the ADC, `index` has to be within `range`
+    pub fn sample(&self, index: u32) -> u16 {
+        let group = index / self.decimation;
+        let value = self.samples[group as usize % History::LEN] as i32 - History::FLAT as i32;
+        (value * History::STEP + Scale::CENTER) as u16
+    }
+
+    // Lowest and highest sample from `from` to `to`, both within `range`
+    pub fn extent(&self, from: u32, to: u32) -> (u16, u16) {
+        let mut extent = (u16::MAX, 0);
+        let mut index = from;
+        loop {
+            let sample = self.sample(index);
+            extent = (extent.0.min(sample), extent.1.max(sample));
+            if index >= to {
+                return extent;
+            }
+            index = (index + self.decimation).min(to);
+        }
+    }
+
+    // Rate after the last beat up to `index`
+    pub fn bpm_at(&self, index: u32) -> Option<u16> {
+        self.beats
+            .iter()
+            .rev()
+            .find(|(beat, _)| *beat <= index)
+            .filter(|(beat, _)| index - beat <= self.max_gap)
+            .map(|(_, bpm)| *bpm)
+    }
+}
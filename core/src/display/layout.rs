@@ -0,0 +1,289 @@
+use embedded_graphics::prelude::Point;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Panel {
+    // 2.8", 57.6 mm active width over 320 pixels
+    Size320x240,
+    // 3.5", 73.44 mm active width over 480 pixels
+    Size480x320,
+}
+
+impl Panel {
+    fn pixel_pitch_um(self) -> u32 {
+        match self {
+            Panel::Size320x240 => 180,
+            Panel::Size480x320 => 153,
+        }
+    }
+
+    // Long and short side in pixels
+    fn size(self) -> (i32, i32) {
+        match self {
+            Panel::Size320x240 => (320, 240),
+            Panel::Size480x320 => (480, 320),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Orientation {
+    Landscape,
+    Portrait,
+}
+
+// Values and labels shown next to the trace
+#[derive(Copy, Clone, PartialEq)]
+pub(crate) enum Field {
+    Bpm,
+    BpmValue,
+    Sdnn,
+    SdnnValue,
+    Rmssd,
+    RmssdValue,
+    Pnn50,
+    Pnn50Value,
+    LfHf,
+    LfHfValue,
+    Pvc,
+    PvcValue,
+    Pattern,
+    Quality,
+    Sweep,
+    Alarm,
+    Warning,
+}
+
+impl Field {
+    const COUNT: usize = 17;
+}
+
+#[derive(Copy, Clone)]
+pub(crate) struct Frame {
+    pub(crate) top_left: Point,
+    pub(crate) bottom_right: Point,
+    pub(crate) width: i32,
+    pub(crate) height: i32,
+    // Row of the isoelectric line
+    pub(crate) baseline: u16,
+}
+
+impl Frame {
+    pub(crate) const BORDER_WIDTH: i32 = 2;
+    // Blank columns ahead of the newest one in sweep mode
+    pub(crate) const SWEEP_GAP: u16 = 12;
+
+    pub(crate) fn center(&self) -> Point {
+        Point::new(
+            (self.top_left.x + self.bottom_right.x) / 2,
+            (self.top_left.y + self.bottom_right.y) / 2,
+        )
+    }
+}
+
+pub(crate) struct DataColumn;
+
+impl DataColumn {
+    pub(crate) const TEXT_WIDTH: i32 = 12 * 3;
+    pub(crate) const TEXT_HEIGHT: i32 = 16;
+    pub(crate) const SMALL_TEXT_HEIGHT: i32 = 8;
+    pub(crate) const TEXT_SPACING: i32 = 5;
+    const WIDTH: i32 = 50;
+    const MARGIN: i32 = 10;
+
+    // Fields kept together in one column, in the order they are stacked
+    const GROUPS: [&'static [(Field, i32)]; 7] = [
+        &[
+            (Field::Bpm, DataColumn::TEXT_HEIGHT),
+            (Field::BpmValue, DataColumn::TEXT_HEIGHT),
+        ],
+        &[
+            (Field::Sdnn, DataColumn::SMALL_TEXT_HEIGHT),
+            (Field::SdnnValue, DataColumn::TEXT_HEIGHT),
+        ],
+        &[
+            (Field::Rmssd, DataColumn::SMALL_TEXT_HEIGHT),
+            (Field::RmssdValue, DataColumn::TEXT_HEIGHT),
+        ],
+        &[
+            (Field::Pnn50, DataColumn::SMALL_TEXT_HEIGHT),
+            (Field::Pnn50Value, DataColumn::TEXT_HEIGHT),
+        ],
+        &[
+            (Field::LfHf, DataColumn::SMALL_TEXT_HEIGHT),
+            (Field::LfHfValue, DataColumn::TEXT_HEIGHT),
+        ],
+        &[
+            (Field::Pvc, DataColumn::SMALL_TEXT_HEIGHT),
+            (Field::PvcValue, DataColumn::TEXT_HEIGHT),
+            (Field::Pattern, DataColumn::SMALL_TEXT_HEIGHT),
+        ],
+        &[
+            (Field::Quality, DataColumn::SMALL_TEXT_HEIGHT),
+            (Field::Sweep, DataColumn::SMALL_TEXT_HEIGHT),
+            (Field::Alarm, DataColumn::SMALL_TEXT_HEIGHT),
+            (Field::Warning, DataColumn::TEXT_HEIGHT),
+        ],
+    ];
+
+    // Stacks the groups into columns of `height`, starts a new column when a group
+    // does not fit. Returns the number of columns, the fields are relative to the top
+    // left corner of the first column.
+    fn flow(height: i32, fields: &mut [Point; Field::COUNT]) -> i32 {
+        let mut column = 0;
+        let mut y = 0;
+        for group in DataColumn::GROUPS.iter() {
+            let group_height = group.iter().map(|(_, height)| height).sum::<i32>()
+                + (group.len() as i32 - 1) * DataColumn::TEXT_SPACING;
+            if y > 0 && y + group_height > height {
+                column += 1;
+                y = 0;
+            }
+            for (field, field_height) in group.iter() {
+                // The sweep speed label is wider than the values
+                let x = match field {
+                    Field::Sweep => 1,
+                    _ => (DataColumn::WIDTH - Frame::BORDER_WIDTH - DataColumn::TEXT_WIDTH) / 2,
+                };
+                fields[*field as usize] = Point::new(column * DataColumn::WIDTH + x, y);
+                y += field_height + DataColumn::TEXT_SPACING;
+            }
+        }
+        column + 1
+    }
+
+    fn tallest_group() -> i32 {
+        DataColumn::GROUPS
+            .iter()
+            .map(|group| {
+                group.iter().map(|(_, height)| height).sum::<i32>()
+                    + (group.len() as i32 - 1) * DataColumn::TEXT_SPACING
+            })
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+// Geometry of the screen, computed at startup from the panel and its orientation.
+//
+// In landscape the data column is right of the frame and grows by whole columns
+// until all fields fit next to the frame. In portrait the fields are stacked below
+// the frame in as few rows as the width allows.
+//
+// The controller scrolls along the long side of the panel, so only landscape moves
+// the trace by the hardware scroll. The fixed areas of the scroll are the margins
+// left and right of the frame, portrait has to use `RenderMode::Sweep`.
+#[derive(Copy, Clone)]
+pub struct Layout {
+    panel: Panel,
+    orientation: Orientation,
+    pub(crate) frame: Frame,
+    // Margins around the frame, left, top, right and bottom
+    margins: (i32, i32, i32, i32),
+    fields: [Point; Field::COUNT],
+}
+
+impl Layout {
+    pub fn new(panel: Panel, orientation: Orientation) -> Self {
+        let (long, short) = panel.size();
+        let mut fields = [Point::zero(); Field::COUNT];
+        let (width, height, margins, origin) = match orientation {
+            Orientation::Landscape => {
+                let (width, height) = (long, short);
+                let available = height - 2 * DataColumn::MARGIN - 2 * DataColumn::TEXT_SPACING;
+                let columns = DataColumn::flow(available, &mut fields);
+                let right = columns * DataColumn::WIDTH;
+                let margins = (
+                    DataColumn::MARGIN,
+                    DataColumn::MARGIN,
+                    right,
+                    DataColumn::MARGIN,
+                );
+                let origin = Point::new(
+                    width - right + Frame::BORDER_WIDTH - 1,
+                    DataColumn::MARGIN + DataColumn::TEXT_SPACING,
+                );
+                (width, height, margins, origin)
+            }
+            Orientation::Portrait => {
+                let (width, height) = (short, long);
+                let columns = (width - DataColumn::MARGIN) / DataColumn::WIDTH;
+                // Lowest stack of the fields that fits the width
+                let mut available = DataColumn::tallest_group();
+                while DataColumn::flow(available, &mut fields) > columns {
+                    available += DataColumn::TEXT_SPACING;
+                }
+                let bottom = available + 2 * DataColumn::TEXT_SPACING + DataColumn::MARGIN;
+                let margins = (
+                    DataColumn::MARGIN,
+                    DataColumn::MARGIN,
+                    DataColumn::MARGIN,
+                    bottom,
+                );
+                let origin = Point::new(
+                    DataColumn::MARGIN,
+                    height - bottom + Frame::BORDER_WIDTH + DataColumn::TEXT_SPACING,
+                );
+                (width, height, margins, origin)
+            }
+        };
+        for field in fields.iter_mut() {
+            *field += origin;
+        }
+
+        let (left, top, right, bottom) = margins;
+        let frame_height = height - top - bottom;
+        let frame = Frame {
+            top_left: Point::new(left, top),
+            bottom_right: Point::new(width - right - 1, height - bottom - 1),
+            width: width - left - right,
+            height: frame_height,
+            baseline: (frame_height / 2) as u16,
+        };
+        Layout {
+            panel,
+            orientation,
+            frame,
+            margins,
+            fields,
+        }
+    }
+
+    pub fn panel(&self) -> Panel {
+        self.panel
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    // Width and height of the screen in pixels
+    pub fn size(&self) -> (i32, i32) {
+        let (long, short) = self.panel.size();
+        match self.orientation {
+            Orientation::Landscape => (long, short),
+            Orientation::Portrait => (short, long),
+        }
+    }
+
+    // Fixed areas at the start and the end of the scroll axis, i.e. the long side
+    pub fn scroll_offsets(&self) -> (u16, u16) {
+        let (left, top, right, bottom) = self.margins;
+        match self.orientation {
+            Orientation::Landscape => (left as u16, right as u16),
+            Orientation::Portrait => (top as u16, bottom as u16),
+        }
+    }
+
+    // The trace can move by the hardware scroll
+    pub fn can_scroll(&self) -> bool {
+        self.orientation == Orientation::Landscape
+    }
+
+    pub(crate) fn pixel_pitch_um(&self) -> u32 {
+        self.panel.pixel_pitch_um()
+    }
+
+    pub(crate) fn position(&self, field: Field) -> Point {
+        self.fields[field as usize]
+    }
+}
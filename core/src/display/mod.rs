@@ -0,0 +1,1280 @@
+use core::fmt::Write;
+use embedded_graphics::fonts::{Font12x16, Font6x8, Text};
+use embedded_graphics::pixelcolor::{Rgb565, RgbColor};
+use embedded_graphics::prelude::{Pixel, Point, Primitive};
+use embedded_graphics::primitives::{Line, Rectangle};
+use embedded_graphics::style::{
+    PrimitiveStyle, PrimitiveStyleBuilder, TextStyle, TextStyleBuilder,
+};
+use heapless::consts::{U16, U32, U512, U8};
+use heapless::spsc::Queue;
+use heapless::spsc::{Consumer, SingleCore};
+use heapless::{ArrayLength, String};
+
+use crate::analysis::contact::{Contact, LeadOff};
+use crate::analysis::hrv::HrvMetrics;
+use crate::analysis::pvc::EctopicPattern;
+use crate::analysis::quality::SignalQuality;
+use crate::analysis::rhythm::{Rhythm, RhythmEvent, Severity};
+use crate::error::{Error, Result};
+use crate::lcd::Lcd;
+use crate::settings::{AutoScale as AutoScaleSetting, Settings};
+use crate::ui::Screen;
+
+use annotation::Marker;
+pub use annotation::{Annotation, Mark};
+use auto_scale::AutoScale;
+pub use grid::{Gain, Grid, GridLine, Scale, SweepSpeed};
+pub use history::History;
+use layout::{DataColumn, Field, Frame};
+pub use layout::{Layout, Orientation, Panel};
+pub use trend::TrendSpan;
+
+mod annotation;
+mod auto_scale;
+mod grid;
+mod history;
+mod layout;
+mod page;
+mod trend;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RenderMode {
+    // The trace moves with the hardware scroll of the controller
+    Scroll,
+    // The trace stays, an erase bar moves ahead of the newest column
+    Sweep,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Theme {
+    // Bright trace on black
+    Dark,
+    // Dark trace on the pink grid of ECG paper
+    Paper,
+    // Dim warm colors that do not dazzle in a dark room
+    Night,
+}
+
+impl Theme {
+    pub fn next(self) -> Theme {
+        match self {
+            Theme::Dark => Theme::Paper,
+            Theme::Paper => Theme::Night,
+            Theme::Night => Theme::Dark,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Dark => "DARK",
+            Theme::Paper => "PAPER",
+            Theme::Night => "NIGHT",
+        }
+    }
+}
+
+// Height of the trace in percent of the frame when the auto-scale is turned on
+const AUTO_SCALE_FRACTION: u8 = 60;
+
+pub struct Display<'a, LEN, LCD, LCDER>
+where
+    LEN: ArrayLength<u16>,
+    LCD: Lcd<Error = LCDER>,
+{
+    current_data: Queue<Data, U512, u16, SingleCore>,
+    history: History,
+    // Sample index at the cursor while the trace is frozen
+    review: Option<u32>,
+    // Annotations not drawn yet, the newest column shows the sample `index`
+    annotations: Queue<Annotation, U16, u8, SingleCore>,
+    index: u32,
+    // Sample of the first column since the trace was restored
+    trace_start: u32,
+    buffer: Consumer<'a, u16, LEN, u8, SingleCore>,
+    horizontal_position: u16,
+    last_sample: u16,
+    // Lowest and highest mapped sample since the newest column
+    envelope: Option<(u16, u16)>,
+    last_bpm: Option<u16>,
+    last_hrv: Option<HrvMetrics>,
+    last_lf_hf: Option<u16>,
+    last_pvc: u16,
+    last_pattern: Option<EctopicPattern>,
+    interference_warning: bool,
+    alarm: Option<(Rhythm, Severity)>,
+    contact: Contact,
+    quality: Option<SignalQuality>,
+    grid: Grid,
+    scale: Scale,
+    auto_scale: Option<AutoScale>,
+    // Height of the calibration pulse on the screen
+    calibration: i32,
+    sample_rate: u32,
+    mode: RenderMode,
+    theme: Theme,
+    color: Color,
+    screen: Screen,
+    // Focused item of the screen
+    focus: Option<u8>,
+    layout: Layout,
+    lcd: LCD,
+}
+
+impl<'a, LEN, LCD, LCDER> Display<'a, LEN, LCD, LCDER>
+where
+    LEN: ArrayLength<u16>,
+    LCD: Lcd<Error = LCDER>,
+{
+    pub fn new(
+        lcd: LCD,
+        buffer: Consumer<'a, u16, LEN, u8, SingleCore>,
+        layout: Layout,
+        sample_rate: u32,
+        settings: &Settings,
+    ) -> Result<Self, LCDER> {
+        let frame = layout.frame;
+        let mut display = Display {
+            current_data: unsafe { Queue::u16_sc() },
+            history: History::new(sample_rate),
+            review: None,
+            annotations: unsafe { Queue::u8_sc() },
+            index: 0,
+            trace_start: 0,
+            buffer,
+            horizontal_position: (frame.width - 1) as u16,
+            last_sample: frame.baseline,
+            envelope: None,
+            last_bpm: None,
+            last_hrv: None,
+            last_lf_hf: None,
+            last_pvc: 0,
+            last_pattern: None,
+            interference_warning: false,
+            alarm: None,
+            contact: Contact::Good,
+            quality: None,
+            grid: Grid::new(layout.pixel_pitch_um(), frame.height as u16, frame.baseline),
+            scale: Scale::new(
+                sample_rate,
+                layout.pixel_pitch_um(),
+                settings.sweep_speed,
+                settings.gain,
+            ),
+            auto_scale: None,
+            calibration: 0,
+            sample_rate,
+            mode: if layout.can_scroll() {
+                RenderMode::Scroll
+            } else {
+                RenderMode::Sweep
+            },
+            theme: settings.theme,
+            color: Color::new(settings.theme),
+            screen: Screen::Waveform,
+            focus: None,
+            layout,
+            lcd,
+        };
+        display.init()?;
+        display.apply(settings)?;
+        Ok(display)
+    }
+
+    pub fn frame(&mut self) -> Result<(), LCDER> {
+        let len = self.buffer.len();
+        for _ in 0..len {
+            let sample = self.buffer.dequeue().ok_or(Error::Queue)?;
+            self.history.push(sample);
+            self.index = self.index.wrapping_add(1);
+            let mapped_sample = self.map_sample(sample);
+            if !self.is_trace_visible() {
+                // The banner or another screen is shown instead of the trace
+                self.last_sample = mapped_sample;
+                self.envelope = None;
+                continue;
+            }
+            if let Some(auto_scale) = self.auto_scale.as_mut() {
+                if auto_scale.process(sample, &mut self.scale) {
+                    self.update_calibration()?;
+                }
+            }
+            let (low, high) = self
+                .envelope
+                .map_or((mapped_sample, mapped_sample), |(low, high)| {
+                    (low.min(mapped_sample), high.max(mapped_sample))
+                });
+            self.envelope = Some((low, high));
+            // The column spans all samples since the last one, so narrow spikes are
+            // kept at any sample rate. Extra columns continue flat.
+            let columns = self.scale.advance();
+            for column in 0..columns {
+                if column == 0 {
+                    self.envelope = None;
+                    self.draw_column(low.min(self.last_sample), high.max(self.last_sample))?;
+                } else {
+                    self.draw_column(mapped_sample, mapped_sample)?;
+                }
+            }
+            if columns > 0 {
+                self.last_sample = mapped_sample;
+            }
+        }
+        self.draw_annotations()
+    }
+
+    // Follows changed settings, only the affected parts are redrawn
+    pub fn apply(&mut self, settings: &Settings) -> Result<(), LCDER> {
+        if settings.theme != self.theme {
+            self.theme = settings.theme;
+            self.color = Color::new(settings.theme);
+            self.redraw()?;
+        }
+        self.set_sweep_speed(settings.sweep_speed)?;
+        match settings.auto_scale {
+            AutoScaleSetting::Off => {
+                if self.auto_scale.is_some() || settings.gain != self.scale.gain() {
+                    self.set_gain(settings.gain)?;
+                }
+            }
+            AutoScaleSetting::On | AutoScaleSetting::Locked => {
+                self.set_auto_scale(Some(AUTO_SCALE_FRACTION))?;
+                self.set_scale_lock(settings.auto_scale == AutoScaleSetting::Locked);
+            }
+        }
+        self.set_render_mode(settings.render_mode)
+    }
+
+    pub fn set_sweep_speed(&mut self, speed: SweepSpeed) -> Result<(), LCDER> {
+        if speed != self.scale.speed() {
+            self.draw_sweep_speed(self.color.background)?;
+            self.scale.set_speed(speed);
+            self.draw_sweep_speed(self.color.frame_border)?;
+        }
+        Ok(())
+    }
+
+    // Sweep mode only needs `Lcd::draw`, layouts that cannot scroll stay in it
+    pub fn set_render_mode(&mut self, mode: RenderMode) -> Result<(), LCDER> {
+        if mode == self.mode || !self.layout.can_scroll() {
+            return Ok(());
+        }
+        match mode {
+            RenderMode::Sweep => {
+                // Columns are drawn at their screen position from now on
+                self.align_scroll()?;
+                self.mode = mode;
+                if self.is_trace_visible() {
+                    for distance in 1..=Frame::SWEEP_GAP {
+                        self.erase_ahead(distance)?;
+                    }
+                }
+            }
+            RenderMode::Scroll if self.is_trace_visible() => {
+                // The newest column goes to the right edge
+                let offset = (self.horizontal_position + 1) % self.layout.frame.width as u16;
+                if offset != 0 {
+                    self.lcd.scroll(offset).map_err(Error::Lcd)?;
+                }
+                self.mode = mode;
+            }
+            RenderMode::Scroll => {
+                // Other screens stay in screen coordinates, the columns are moved
+                // to match when the trace is shown again
+                self.mode = mode;
+                self.horizontal_position = (self.layout.frame.width - 1) as u16;
+            }
+        }
+        Ok(())
+    }
+
+    // The data column stays, the frame shows the selected screen. Screens other than
+    // the waveform only draw their static parts, their content is drawn by `show_*`.
+    pub fn set_screen(&mut self, screen: Screen, focus: Option<u8>) -> Result<(), LCDER> {
+        if screen == self.screen {
+            return self.set_focus(focus);
+        }
+        self.screen = screen;
+        self.focus = focus;
+        if self.review.take().is_some() {
+            self.history.release();
+        }
+        self.repaint()
+    }
+
+    pub fn screen(&self) -> Screen {
+        self.screen
+    }
+
+    pub fn lcd(&self) -> &LCD {
+        &self.lcd
+    }
+
+    // The content of the screen is drawn with the focus by `show_*`, on the waveform
+    // the focus freezes the trace for review
+    pub fn set_focus(&mut self, focus: Option<u8>) -> Result<(), LCDER> {
+        self.focus = focus;
+        if self.screen != Screen::Waveform {
+            return Ok(());
+        }
+        match (focus, self.review) {
+            (Some(_), None) => {
+                // The newest samples fill the frame up to the right edge
+                self.history.hold();
+                let (_, end) = self.history.range();
+                let half = self.scale.samples(self.layout.frame.width / 2);
+                self.review = Some(self.clamp_cursor(end as i64 - half as i64));
+                self.repaint()
+            }
+            (None, Some(_)) => {
+                self.review = None;
+                self.history.release();
+                self.repaint()
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Moves the cursor of the frozen trace by quarters of the frame, negative back
+    // in time
+    pub fn pan(&mut self, quarters: i8) -> Result<(), LCDER> {
+        if let Some(cursor) = self.review {
+            let step = self.scale.samples(self.layout.frame.width / 4) as i64;
+            let cursor = self.clamp_cursor(cursor as i64 + step * quarters as i64);
+            if Some(cursor) != self.review {
+                self.review = Some(cursor);
+                self.draw_review(cursor)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Accepted beat with the rate after it, `index` counts samples from power-on
+    pub fn add_beat(&mut self, index: u32, bpm: u16) {
+        self.history.beat(index, bpm);
+    }
+
+    // Drawn on the trace once its last sample is shown, the oldest is dropped if
+    // too many are waiting
+    pub fn annotate(&mut self, annotation: Annotation) {
+        self.history.annotate(annotation);
+        if self.annotations.len() as usize == self.annotations.capacity() as usize {
+            self.annotations.dequeue();
+        }
+        self.annotations.enqueue(annotation).ok();
+    }
+
+    // Resets the scroll and repaints the frame of the current screen
+    pub fn repaint(&mut self) -> Result<(), LCDER> {
+        self.align_scroll()?;
+        if let (Screen::Waveform, Some(cursor)) = (self.screen, self.review) {
+            return self.draw_review(cursor);
+        }
+        match (self.screen, self.contact) {
+            (Screen::Waveform, Contact::Good) => self.restore_grid(),
+            (Screen::Waveform, Contact::LeadOff(cause)) => self.draw_lead_off(cause),
+            (Screen::Trend(_), _) => self.clear_frame(),
+            _ => {
+                self.clear_frame()?;
+                self.draw_title()
+            }
+        }
+    }
+
+    // Calibrated gain, turns the auto-scale off
+    pub fn set_gain(&mut self, gain: Gain) -> Result<(), LCDER> {
+        self.auto_scale = None;
+        self.scale.set_gain(gain);
+        self.update_calibration()
+    }
+
+    // Fits the trace to `fraction` percent of the frame height, `None` returns to the
+    // calibrated gain
+    pub fn set_auto_scale(&mut self, fraction: Option<u8>) -> Result<(), LCDER> {
+        match (fraction, self.auto_scale.as_mut()) {
+            (Some(fraction), Some(auto_scale)) => auto_scale.set_fraction(fraction),
+            (Some(fraction), None) => {
+                self.auto_scale = Some(AutoScale::new(
+                    self.sample_rate,
+                    self.layout.frame.height,
+                    fraction,
+                ))
+            }
+            (None, _) => self.set_gain(self.scale.gain())?,
+        }
+        Ok(())
+    }
+
+    // Keeps the current auto-scale until unlocked
+    pub fn set_scale_lock(&mut self, locked: bool) {
+        if let Some(auto_scale) = self.auto_scale.as_mut() {
+            auto_scale.set_locked(locked);
+        }
+    }
+
+    pub fn scale_locked(&self) -> bool {
+        self.auto_scale
+            .as_ref()
+            .is_some_and(|auto_scale| auto_scale.locked())
+    }
+
+    // Dashes while the rate is not known, e.g. when the beats stopped
+    pub fn update_bpm(&mut self, bpm: Option<u16>) -> Result<(), LCDER> {
+        if bpm != self.last_bpm {
+            let position = self.layout.position(Field::BpmValue);
+            self.draw_value(self.last_bpm, position, self.color.background)?;
+            self.draw_value(bpm, position, self.value_color(self.color.bpm_text))?;
+            self.last_bpm = bpm;
+        }
+        Ok(())
+    }
+
+    pub fn update_hrv(&mut self, hrv: Option<HrvMetrics>) -> Result<(), LCDER> {
+        if hrv != self.last_hrv {
+            self.draw_hrv_values(self.last_hrv, self.color.background)?;
+            self.draw_hrv_values(hrv, self.value_color(self.color.hrv_text))?;
+            self.last_hrv = hrv;
+        }
+        Ok(())
+    }
+
+    // LF/HF ratio multiplied by 100
+    pub fn update_lf_hf(&mut self, ratio: Option<u16>) -> Result<(), LCDER> {
+        if ratio != self.last_lf_hf {
+            self.draw_ratio(self.last_lf_hf, self.color.background)?;
+            self.draw_ratio(ratio, self.value_color(self.color.hrv_text))?;
+            self.last_lf_hf = ratio;
+        }
+        Ok(())
+    }
+
+    // PVCs per minute and the pattern of the last beats
+    pub fn update_pvc(
+        &mut self,
+        per_minute: u16,
+        pattern: Option<EctopicPattern>,
+    ) -> Result<(), LCDER> {
+        if per_minute != self.last_pvc {
+            let position = self.layout.position(Field::PvcValue);
+            self.draw_value(Some(self.last_pvc), position, self.color.background)?;
+            self.draw_value(
+                Some(per_minute),
+                position,
+                self.value_color(self.color.pvc_text),
+            )?;
+            self.last_pvc = per_minute;
+        }
+        if pattern != self.last_pattern {
+            if let Some(last_pattern) = self.last_pattern {
+                self.draw_pattern(last_pattern, self.color.background)?;
+            }
+            if let Some(pattern) = pattern {
+                self.draw_pattern(pattern, self.color.pvc_text)?;
+            }
+            self.last_pattern = pattern;
+        }
+        Ok(())
+    }
+
+    // Replaces the trace by a banner while the electrodes are off
+    pub fn update_contact(&mut self, contact: Contact) -> Result<(), LCDER> {
+        if contact == self.contact {
+            return Ok(());
+        }
+        let last = core::mem::replace(&mut self.contact, contact);
+        if let (Contact::Good, Some(auto_scale)) = (last, self.auto_scale.as_mut()) {
+            auto_scale.reset();
+        }
+        // The frozen trace stays, the contact is shown once it is released
+        if self.screen != Screen::Waveform || self.review.is_some() {
+            return Ok(());
+        }
+        match (last, contact) {
+            (Contact::Good, Contact::LeadOff(cause)) => {
+                self.align_scroll()?;
+                self.draw_lead_off(cause)?;
+            }
+            (Contact::LeadOff(last), Contact::LeadOff(cause)) => {
+                self.draw_lead_off_cause(last, self.color.background)?;
+                self.draw_lead_off_cause(cause, self.color.critical_text)?;
+            }
+            (Contact::LeadOff(_), Contact::Good) => self.restore_grid()?,
+            (Contact::Good, Contact::Good) => {}
+        }
+        Ok(())
+    }
+
+    // Values derived from an unusable signal are greyed out
+    pub fn update_quality(&mut self, quality: Option<SignalQuality>) -> Result<(), LCDER> {
+        if quality == self.quality {
+            return Ok(());
+        }
+        let was_unusable = self.is_unusable();
+        if let Some(last) = self.quality {
+            self.draw_quality(last, self.color.background)?;
+        }
+        self.quality = quality;
+        if let Some(quality) = quality {
+            self.draw_quality(quality, self.quality_color(quality))?;
+        }
+        if self.is_unusable() != was_unusable {
+            let position = self.layout.position(Field::BpmValue);
+            self.draw_value(
+                self.last_bpm,
+                position,
+                self.value_color(self.color.bpm_text),
+            )?;
+            self.draw_hrv_values(self.last_hrv, self.value_color(self.color.hrv_text))?;
+            self.draw_ratio(self.last_lf_hf, self.value_color(self.color.hrv_text))?;
+            let position = self.layout.position(Field::PvcValue);
+            self.draw_value(
+                Some(self.last_pvc),
+                position,
+                self.value_color(self.color.pvc_text),
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn update_interference_warning(&mut self, warning: bool) -> Result<(), LCDER> {
+        if warning != self.interference_warning {
+            let color = if warning {
+                self.color.warning_text
+            } else {
+                self.color.background
+            };
+            let text = Text::new("HUM", self.layout.position(Field::Warning))
+                .into_styled(TextStyle::new(Font12x16, color));
+            self.lcd.draw(&text).map_err(Error::Lcd)?;
+            self.interference_warning = warning;
+        }
+        Ok(())
+    }
+
+    // Name of the most severe ongoing rhythm event
+    pub fn update_alarm(&mut self, event: Option<RhythmEvent>) -> Result<(), LCDER> {
+        let alarm = event.map(|event| (event.rhythm, event.severity));
+        if alarm != self.alarm {
+            if let Some((rhythm, _)) = self.alarm {
+                self.draw_alarm(rhythm, self.color.background)?;
+            }
+            if let Some((rhythm, severity)) = alarm {
+                self.draw_alarm(rhythm, self.severity_color(severity))?;
+            }
+            self.alarm = alarm;
+        }
+        Ok(())
+    }
+
+    fn draw_alarm(&mut self, rhythm: Rhythm, color: Rgb565) -> Result<(), LCDER> {
+        let text = Text::new(rhythm.name(), self.layout.position(Field::Alarm))
+            .into_styled(TextStyle::new(Font6x8, color));
+        self.lcd.draw(&text).map_err(Error::Lcd)
+    }
+
+    fn draw_lead_off(&mut self, cause: LeadOff) -> Result<(), LCDER> {
+        self.clear_frame()?;
+        let center = self.layout.frame.center();
+        let position = Point::new(
+            center.x - 8 * Banner::TEXT_WIDTH / 2,
+            center.y - DataColumn::TEXT_HEIGHT,
+        );
+        let banner = Text::new("LEAD OFF", position)
+            .into_styled(TextStyle::new(Font12x16, self.color.critical_text));
+        self.lcd.draw(&banner).map_err(Error::Lcd)?;
+        self.draw_lead_off_cause(cause, self.color.critical_text)
+    }
+
+    fn draw_lead_off_cause(&mut self, cause: LeadOff, color: Rgb565) -> Result<(), LCDER> {
+        let name = cause.name();
+        let center = self.layout.frame.center();
+        let position = Point::new(
+            center.x - (name.len() as i32 * Banner::SMALL_TEXT_WIDTH) / 2,
+            center.y + DataColumn::TEXT_SPACING,
+        );
+        let text = Text::new(name, position).into_styled(TextStyle::new(Font6x8, color));
+        self.lcd.draw(&text).map_err(Error::Lcd)
+    }
+
+    // Scrolls back to the initial offset, so the frame is drawn in screen coordinates
+    fn align_scroll(&mut self) -> Result<(), LCDER> {
+        if self.mode == RenderMode::Sweep {
+            return Ok(());
+        }
+        let offset = (self.horizontal_position + 1) % self.layout.frame.width as u16;
+        if offset != 0 {
+            self.lcd
+                .scroll(self.layout.frame.width as u16 - offset)
+                .map_err(Error::Lcd)?;
+        }
+        self.horizontal_position = (self.layout.frame.width - 1) as u16;
+        Ok(())
+    }
+
+    fn clear_frame(&mut self) -> Result<(), LCDER> {
+        let frame = Rectangle::new(self.layout.frame.top_left, self.layout.frame.bottom_right)
+            .into_styled(PrimitiveStyle::with_fill(self.color.background));
+        self.lcd.draw(&frame).map_err(Error::Lcd)
+    }
+
+    // Redraws the grid of all columns, the scroll is aligned while the leads are off
+    fn restore_grid(&mut self) -> Result<(), LCDER> {
+        self.clear_frame()?;
+        self.trace_start = self.index;
+        for data in self.current_data.iter_mut() {
+            data.mark = None;
+        }
+        let top = (self.layout.frame.height - 1) as u16;
+        for (column, data) in self.current_data.iter().enumerate() {
+            let position = ((self.horizontal_position as usize + 1 + column)
+                % self.layout.frame.width as usize) as u16;
+            Display::<'a, LEN, LCD, LCDER>::draw_grid(
+                &mut self.lcd,
+                &self.grid,
+                &self.color,
+                &self.layout.frame,
+                position,
+                data.grid,
+                (0, top),
+            )?;
+        }
+        Ok(())
+    }
+
+    // Stored samples around the cursor in the middle of the frame, every column spans
+    // the samples it covers so no peak is lost. The label shows the time before the
+    // freeze and the rate at the cursor.
+    fn draw_review(&mut self, cursor: u32) -> Result<(), LCDER> {
+        self.restore_grid()?;
+        let (start, end) = self.history.range();
+        let center = self.layout.frame.width / 2;
+        for position in 0..self.layout.frame.width {
+            let from = cursor as i64 + self.scale.samples(position - center) as i64;
+            let to = cursor as i64 + self.scale.samples(position + 1 - center) as i64;
+            if to < start as i64 || from >= end as i64 {
+                continue;
+            }
+            let (min, max) = self
+                .history
+                .extent(self.clamp_cursor(from), self.clamp_cursor(to));
+            let data = Data::new(self.map_sample(min), self.map_sample(max), GridLine::None);
+            self.draw_segment(position as u16, &data, self.color.data)?;
+        }
+
+        for annotation in self.history.annotations() {
+            let from = center + self.scale.columns_between(cursor, annotation.start);
+            let to = center + self.scale.columns_between(cursor, annotation.end);
+            for position in from.max(0)..=to.min(self.layout.frame.width - 1) {
+                Display::<'a, LEN, LCD, LCDER>::draw_mark(
+                    &mut self.lcd,
+                    &self.color,
+                    &self.layout.frame,
+                    position as u16,
+                    annotation.mark,
+                )?;
+            }
+        }
+
+        let x = self.layout.frame.top_left.x + center;
+        let line = Line::new(
+            Point::new(x, self.layout.frame.top_left.y),
+            Point::new(x, self.layout.frame.bottom_right.y),
+        )
+        .into_styled(PrimitiveStyle::with_stroke(self.color.frame_border, 1));
+        self.lcd.draw(&line).map_err(Error::Lcd)?;
+
+        let mut buffer = String::<U32>::new();
+        let tenths = (end - cursor) * 10 / self.sample_rate;
+        write!(&mut buffer, "-{}.{}s ", tenths / 10, tenths % 10)
+            .map_err(|_| Error::BufferWrite)?;
+        match self.history.bpm_at(cursor) {
+            Some(bpm) => write!(&mut buffer, "{} BPM", bpm),
+            None => write!(&mut buffer, "--- BPM"),
+        }
+        .map_err(|_| Error::BufferWrite)?;
+        let style = TextStyleBuilder::new(Font12x16)
+            .text_color(self.color.bpm_text)
+            .background_color(self.color.background)
+            .build();
+        let position = self.layout.frame.top_left + Point::new(Review::MARGIN, Review::MARGIN);
+        let text = Text::new(&buffer, position).into_styled(style);
+        self.lcd.draw(&text).map_err(Error::Lcd)
+    }
+
+    // Cursor within the stored samples
+    fn clamp_cursor(&self, cursor: i64) -> u32 {
+        let (start, end) = self.history.range();
+        cursor.clamp(start as i64, end.max(1) as i64 - 1) as u32
+    }
+
+    fn draw_sweep_speed(&mut self, color: Rgb565) -> Result<(), LCDER> {
+        let text = Text::new(
+            self.scale.speed().name(),
+            self.layout.position(Field::Sweep),
+        )
+        .into_styled(TextStyle::new(Font6x8, color));
+        self.lcd.draw(&text).map_err(Error::Lcd)
+    }
+
+    // 1 mV step in the fixed area left of the frame
+    fn update_calibration(&mut self) -> Result<(), LCDER> {
+        let calibration = self.scale.calibration();
+        if calibration != self.calibration {
+            self.draw_calibration(self.calibration, self.color.background)?;
+            self.draw_calibration(calibration, self.color.frame_border)?;
+            self.calibration = calibration;
+        }
+        Ok(())
+    }
+
+    fn draw_calibration(&mut self, height: i32, color: Rgb565) -> Result<(), LCDER> {
+        let baseline = self.layout.frame.bottom_right.y - self.layout.frame.baseline as i32;
+        let top = (baseline - height).max(self.layout.frame.top_left.y);
+        let pulse = Rectangle::new(
+            Point::new(Calibration::LEFT, top),
+            Point::new(Calibration::RIGHT, baseline),
+        )
+        .into_styled(PrimitiveStyle::with_stroke(color, 1));
+        self.lcd.draw(&pulse).map_err(Error::Lcd)
+    }
+
+    fn draw_quality(&mut self, quality: SignalQuality, color: Rgb565) -> Result<(), LCDER> {
+        let text = Text::new(quality.name(), self.layout.position(Field::Quality))
+            .into_styled(TextStyle::new(Font6x8, color));
+        self.lcd.draw(&text).map_err(Error::Lcd)
+    }
+
+    fn is_trace_visible(&self) -> bool {
+        self.screen == Screen::Waveform && self.contact == Contact::Good && self.review.is_none()
+    }
+
+    fn is_unusable(&self) -> bool {
+        self.quality == Some(SignalQuality::Unusable)
+    }
+
+    fn value_color(&self, color: Rgb565) -> Rgb565 {
+        if self.is_unusable() {
+            self.color.unusable_text
+        } else {
+            color
+        }
+    }
+
+    fn quality_color(&self, quality: SignalQuality) -> Rgb565 {
+        match quality {
+            SignalQuality::Good => self.color.hrv_text,
+            SignalQuality::Acceptable => self.color.advisory_text,
+            SignalQuality::Unusable => self.color.unusable_text,
+        }
+    }
+
+    fn severity_color(&self, severity: Severity) -> Rgb565 {
+        match severity {
+            Severity::Advisory => self.color.advisory_text,
+            Severity::Warning => self.color.warning_text,
+            Severity::Critical => self.color.critical_text,
+        }
+    }
+
+    fn draw_pattern(&mut self, pattern: EctopicPattern, color: Rgb565) -> Result<(), LCDER> {
+        let text = Text::new(pattern.name(), self.layout.position(Field::Pattern))
+            .into_styled(TextStyle::new(Font6x8, color));
+        self.lcd.draw(&text).map_err(Error::Lcd)
+    }
+
+    fn draw_hrv_values(&mut self, hrv: Option<HrvMetrics>, color: Rgb565) -> Result<(), LCDER> {
+        let values = match hrv {
+            Some(hrv) => [Some(hrv.sdnn), Some(hrv.rmssd), Some(hrv.pnn50 as u16)],
+            None => [None; 3],
+        };
+        let positions = [
+            self.layout.position(Field::SdnnValue),
+            self.layout.position(Field::RmssdValue),
+            self.layout.position(Field::Pnn50Value),
+        ];
+        for (value, position) in values.iter().zip(positions.iter()) {
+            self.draw_value(*value, *position, color)?;
+        }
+        Ok(())
+    }
+
+    // Three digit value, dashes if not available
+    fn draw_value(
+        &mut self,
+        value: Option<u16>,
+        position: Point,
+        color: Rgb565,
+    ) -> Result<(), LCDER> {
+        let mut buffer = String::<U8>::new();
+        match value {
+            Some(value) => write!(&mut buffer, "{:>3}", value.min(999)),
+            None => write!(&mut buffer, "---"),
+        }
+        .map_err(|_| Error::BufferWrite)?;
+        let text = Text::new(&buffer, position).into_styled(TextStyle::new(Font12x16, color));
+        self.lcd.draw(&text).map_err(Error::Lcd)?;
+        Ok(())
+    }
+
+    // Ratio with one decimal place, dashes if not available
+    fn draw_ratio(&mut self, ratio: Option<u16>, color: Rgb565) -> Result<(), LCDER> {
+        let mut buffer = String::<U8>::new();
+        match ratio {
+            Some(ratio) => {
+                let tenths = (ratio / 10).min(99);
+                write!(&mut buffer, "{}.{}", tenths / 10, tenths % 10)
+            }
+            None => write!(&mut buffer, "---"),
+        }
+        .map_err(|_| Error::BufferWrite)?;
+        let text = Text::new(&buffer, self.layout.position(Field::LfHfValue))
+            .into_styled(TextStyle::new(Font12x16, color));
+        self.lcd.draw(&text).map_err(Error::Lcd)?;
+        Ok(())
+    }
+
+    // Replaces the oldest column by a segment of the trace
+    fn draw_column(&mut self, from: u16, to: u16) -> Result<(), LCDER> {
+        self.scroll()?;
+        let old = self.current_data.dequeue().ok_or(Error::Queue)?;
+        let line = self.grid.next_column();
+        let (bottom, top) = if line == old.grid && old.mark.is_none() {
+            (old.y, old.y + old.height)
+        } else {
+            (0, (self.layout.frame.height - 1) as u16)
+        };
+        Display::<'a, LEN, LCD, LCDER>::draw_grid(
+            &mut self.lcd,
+            &self.grid,
+            &self.color,
+            &self.layout.frame,
+            self.horizontal_position,
+            line,
+            (bottom, top),
+        )?;
+        if old.mark == Some(Mark::Pvc) {
+            self.erase_label()?;
+        }
+        let data = Data::new(from, to, line);
+        self.draw_single(&data, self.color.data)?;
+        if self.mode == RenderMode::Sweep {
+            self.erase_ahead(Frame::SWEEP_GAP)?;
+        }
+        self.current_data.enqueue(data).map_err(|_| Error::Queue)
+    }
+
+    // Clears the label of the reused column from the columns after it
+    fn erase_label(&mut self) -> Result<(), LCDER> {
+        let width = self.layout.frame.width as u16;
+        let top = (self.layout.frame.height - 1) as u16;
+        for (column, data) in self
+            .current_data
+            .iter()
+            .take(Marker::LABEL_WIDTH as usize)
+            .enumerate()
+        {
+            let position = (self.horizontal_position + 1 + column as u16) % width;
+            Display::<'a, LEN, LCD, LCDER>::draw_grid(
+                &mut self.lcd,
+                &self.grid,
+                &self.color,
+                &self.layout.frame,
+                position,
+                data.grid,
+                (top + 1 - Marker::LABEL_HEIGHT, top),
+            )?;
+        }
+        Ok(())
+    }
+
+    // Marks the shown columns of the annotations whose samples are all drawn
+    fn draw_annotations(&mut self) -> Result<(), LCDER> {
+        while let Some(annotation) = self.annotations.peek().copied() {
+            if (self.index.wrapping_sub(annotation.end) as i32) <= 0 {
+                break;
+            }
+            self.annotations.dequeue();
+            // Columns before the trace was restored show nothing
+            let restored = (annotation.end.wrapping_sub(self.trace_start) as i32) >= 0;
+            if !self.is_trace_visible() || !restored {
+                continue;
+            }
+            // Columns back from the newest one
+            let newest = self.index.wrapping_sub(1);
+            let shown = self.scale.columns(newest.wrapping_sub(self.trace_start));
+            let first = self.scale.columns(newest.wrapping_sub(annotation.end));
+            let last = self
+                .scale
+                .columns(newest.wrapping_sub(annotation.start))
+                .min(shown)
+                .min(self.current_data.len() as u32 - 1);
+            if first > last {
+                continue;
+            }
+            let width = self.layout.frame.width as u32;
+            for (distance, data) in self
+                .current_data
+                .iter_mut()
+                .rev()
+                .enumerate()
+                .skip(first as usize)
+                .take((last - first + 1) as usize)
+            {
+                data.mark = Some(annotation.mark);
+                let position =
+                    ((self.horizontal_position as u32 + width - distance as u32) % width) as u16;
+                Display::<'a, LEN, LCD, LCDER>::draw_mark(
+                    &mut self.lcd,
+                    &self.color,
+                    &self.layout.frame,
+                    position,
+                    annotation.mark,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    // Ticks and labels above the trace, bars and user ticks below, gaps across
+    fn draw_mark(
+        lcd: &mut LCD,
+        color: &Color,
+        frame: &Frame,
+        position: u16,
+        mark: Mark,
+    ) -> Result<(), LCDER> {
+        let x = frame.top_left.x + position as i32;
+        let (top, bottom) = (frame.top_left.y, frame.bottom_right.y);
+        let (from, to, mark_color) = match mark {
+            Mark::Beat => (top, top + Marker::TICK_HEIGHT - 1, color.frame_border),
+            Mark::Pvc => (top, top + Marker::TICK_HEIGHT - 1, color.pvc_text),
+            Mark::LeadOff => (bottom + 1 - Marker::BAR_HEIGHT, bottom, color.critical_text),
+            Mark::User => (
+                bottom + 1 - Marker::USER_HEIGHT,
+                bottom,
+                color.advisory_text,
+            ),
+            Mark::Gap => (top, bottom, color.frame_border),
+        };
+        let tick = Rectangle::new(Point::new(x, from), Point::new(x, to))
+            .into_styled(PrimitiveStyle::with_fill(mark_color));
+        lcd.draw(&tick).map_err(Error::Lcd)?;
+        // Labels that would leave the frame are left out
+        if mark == Mark::Pvc && position + 1 + Marker::LABEL_WIDTH <= frame.width as u16 {
+            let label = Text::new(Marker::LABEL, Point::new(x + 1, top))
+                .into_styled(TextStyle::new(Font6x8, color.pvc_text));
+            lcd.draw(&label).map_err(Error::Lcd)?;
+        }
+        Ok(())
+    }
+
+    // Clears the trace from the column `distance` ahead of the newest one
+    fn erase_ahead(&mut self, distance: u16) -> Result<(), LCDER> {
+        let position = (self.horizontal_position + distance) % self.layout.frame.width as u16;
+        // The oldest column is the next one
+        let line = self
+            .current_data
+            .iter()
+            .nth(distance as usize - 1)
+            .map_or(GridLine::None, |data| data.grid);
+        Display::<'a, LEN, LCD, LCDER>::draw_grid(
+            &mut self.lcd,
+            &self.grid,
+            &self.color,
+            &self.layout.frame,
+            position,
+            line,
+            (0, (self.layout.frame.height - 1) as u16),
+        )
+    }
+
+    // Grid of a column between the `bottom` and `top` rows, rows cross on top of
+    // a weaker line
+    fn draw_grid(
+        lcd: &mut LCD,
+        grid: &Grid,
+        color: &Color,
+        frame: &Frame,
+        position: u16,
+        line: GridLine,
+        (bottom, top): (u16, u16),
+    ) -> Result<(), LCDER> {
+        let x = frame.top_left.x + position as i32;
+        let column = Rectangle::new(
+            Point::new(x, frame.bottom_right.y - top as i32),
+            Point::new(x, frame.bottom_right.y - bottom as i32),
+        )
+        .into_styled(PrimitiveStyle::with_fill(color.grid(line)));
+        lcd.draw(&column).map_err(Error::Lcd)?;
+        for (row, row_line) in grid.rows(bottom, top) {
+            if *row_line > line {
+                let pixel = Pixel(
+                    Point::new(x, frame.bottom_right.y - *row as i32),
+                    color.grid(*row_line),
+                );
+                lcd.draw(pixel).map_err(Error::Lcd)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn draw_single(&mut self, data: &Data, color: Rgb565) -> Result<(), LCDER> {
+        self.draw_segment(self.horizontal_position, data, color)
+    }
+
+    fn draw_segment(&mut self, position: u16, data: &Data, color: Rgb565) -> Result<(), LCDER> {
+        let x = (self.layout.frame.top_left.x as u16 + position) as i32;
+        let y = (self.layout.frame.bottom_right.y as u16 - data.y) as i32;
+        let rect = Rectangle::new(Point::new(x, y - data.height as i32), Point::new(x, y))
+            .into_styled(PrimitiveStyle::with_fill(color));
+        self.lcd.draw(&rect).map_err(Error::Lcd)
+    }
+
+    fn scroll(&mut self) -> Result<(), LCDER> {
+        if self.mode == RenderMode::Scroll {
+            self.lcd.scroll(1).map_err(Error::Lcd)?;
+        }
+        self.horizontal_position += 1;
+        if self.horizontal_position >= self.layout.frame.width as u16 {
+            self.horizontal_position = 0;
+        }
+        Ok(())
+    }
+
+    fn init(&mut self) -> Result<(), LCDER> {
+        self.lcd.clear(self.color.background).map_err(Error::Lcd)?;
+        self.init_frame()?;
+        self.init_data_column()?;
+        self.draw_sweep_speed(self.color.frame_border)?;
+        self.update_calibration()?;
+        self.init_data()?;
+        Ok(())
+    }
+
+    // Everything drawn again, e.g. in the colors of another theme
+    fn redraw(&mut self) -> Result<(), LCDER> {
+        self.align_scroll()?;
+        self.lcd.clear(self.color.background).map_err(Error::Lcd)?;
+        self.init_frame()?;
+        self.init_data_column()?;
+        self.draw_sweep_speed(self.color.frame_border)?;
+        self.draw_calibration(self.calibration, self.color.frame_border)?;
+        if let Some(quality) = self.quality {
+            self.draw_quality(quality, self.quality_color(quality))?;
+        }
+        if let Some(pattern) = self.last_pattern {
+            self.draw_pattern(pattern, self.color.pvc_text)?;
+        }
+        if let Some((rhythm, severity)) = self.alarm {
+            self.draw_alarm(rhythm, self.severity_color(severity))?;
+        }
+        if self.interference_warning {
+            let text = Text::new("HUM", self.layout.position(Field::Warning))
+                .into_styled(TextStyle::new(Font12x16, self.color.warning_text));
+            self.lcd.draw(&text).map_err(Error::Lcd)?;
+        }
+        self.repaint()
+    }
+
+    fn init_frame(&mut self) -> Result<(), LCDER> {
+        let top_left = Point::new(
+            self.layout.frame.top_left.x - Frame::BORDER_WIDTH,
+            self.layout.frame.top_left.y - Frame::BORDER_WIDTH,
+        );
+        let bottom_right = Point::new(
+            self.layout.frame.bottom_right.x + Frame::BORDER_WIDTH,
+            self.layout.frame.bottom_right.y + Frame::BORDER_WIDTH,
+        );
+        let border = Rectangle::new(top_left, bottom_right).into_styled(
+            PrimitiveStyleBuilder::new()
+                .stroke_width(Frame::BORDER_WIDTH as u32)
+                .stroke_color(self.color.frame_border)
+                .fill_color(self.color.background)
+                .build(),
+        );
+        self.lcd.draw(&border).map_err(Error::Lcd)?;
+        Ok(())
+    }
+
+    fn init_data_column(&mut self) -> Result<(), LCDER> {
+        let bpm = Text::new("BPM", self.layout.position(Field::Bpm))
+            .into_styled(TextStyle::new(Font12x16, self.color.bpm_text));
+        self.draw_value(
+            self.last_bpm,
+            self.layout.position(Field::BpmValue),
+            self.value_color(self.color.bpm_text),
+        )?;
+        self.lcd.draw(&bpm).map_err(Error::Lcd)?;
+
+        let labels = [
+            ("SDNN", self.layout.position(Field::Sdnn)),
+            ("RMSSD", self.layout.position(Field::Rmssd)),
+            ("PNN50", self.layout.position(Field::Pnn50)),
+            ("LF/HF", self.layout.position(Field::LfHf)),
+        ];
+        for (label, position) in labels.iter() {
+            let text = Text::new(label, *position)
+                .into_styled(TextStyle::new(Font6x8, self.color.hrv_text));
+            self.lcd.draw(&text).map_err(Error::Lcd)?;
+        }
+        self.draw_hrv_values(self.last_hrv, self.value_color(self.color.hrv_text))?;
+        self.draw_ratio(self.last_lf_hf, self.value_color(self.color.hrv_text))?;
+
+        let pvc = Text::new("PVC/M", self.layout.position(Field::Pvc))
+            .into_styled(TextStyle::new(Font6x8, self.color.pvc_text));
+        self.lcd.draw(&pvc).map_err(Error::Lcd)?;
+        self.draw_value(
+            Some(self.last_pvc),
+            self.layout.position(Field::PvcValue),
+            self.value_color(self.color.pvc_text),
+        )?;
+        Ok(())
+    }
+
+    // Full grid of every column with a flat trace, ending at the initial scroll offset
+    fn init_data(&mut self) -> Result<(), LCDER> {
+        let top = (self.layout.frame.height - 1) as u16;
+        for _ in 0..self.layout.frame.width {
+            self.scroll()?;
+            let line = self.grid.next_column();
+            Display::<'a, LEN, LCD, LCDER>::draw_grid(
+                &mut self.lcd,
+                &self.grid,
+                &self.color,
+                &self.layout.frame,
+                self.horizontal_position,
+                line,
+                (0, top),
+            )?;
+            let data = Data::new(self.layout.frame.baseline, self.layout.frame.baseline, line);
+            self.draw_single(&data, self.color.data)?;
+            self.current_data.enqueue(data).map_err(|_| Error::Queue)?;
+        }
+        Ok(())
+    }
+
+    fn map_sample(&self, sample: u16) -> u16 {
+        let deviation = sample as i32 - self.scale.center();
+        (self.layout.frame.baseline as i32 + self.scale.pixels(deviation))
+            .clamp(0, self.layout.frame.height - 1) as u16
+    }
+}
+
+struct Banner;
+
+impl Banner {
+    const TEXT_WIDTH: i32 = 12;
+    const SMALL_TEXT_WIDTH: i32 = 6;
+}
+
+struct Calibration;
+
+impl Calibration {
+    const LEFT: i32 = 2;
+    const RIGHT: i32 = 6;
+}
+
+struct Review;
+
+impl Review {
+    // Label of the cursor from the top left corner of the frame
+    const MARGIN: i32 = 4;
+}
+
+#[derive(Copy, Clone)]
+struct Color {
+    background: Rgb565,
+    frame_border: Rgb565,
+    data: Rgb565,
+    bpm_text: Rgb565,
+    warning_text: Rgb565,
+    advisory_text: Rgb565,
+    critical_text: Rgb565,
+    hrv_text: Rgb565,
+    pvc_text: Rgb565,
+    unusable_text: Rgb565,
+    grid_minor: Rgb565,
+    grid_major: Rgb565,
+    trend_range: Rgb565,
+}
+
+impl Color {
+    fn new(theme: Theme) -> Self {
+        match theme {
+            Theme::Dark => Color {
+                background: Rgb565::BLACK,
+                frame_border: Rgb565::WHITE,
+                data: Rgb565::YELLOW,
+                bpm_text: Rgb565::RED,
+                warning_text: Rgb565::MAGENTA,
+                advisory_text: Rgb565::YELLOW,
+                critical_text: Rgb565::RED,
+                hrv_text: Rgb565::GREEN,
+                pvc_text: Rgb565::CYAN,
+                unusable_text: Rgb565::new(12, 24, 12),
+                grid_minor: Rgb565::new(6, 4, 2),
+                grid_major: Rgb565::new(14, 8, 4),
+                trend_range: Rgb565::new(16, 8, 8),
+            },
+            Theme::Paper => Color {
+                background: Rgb565::WHITE,
+                frame_border: Rgb565::BLACK,
+                data: Rgb565::BLACK,
+                bpm_text: Rgb565::new(24, 0, 0),
+                warning_text: Rgb565::new(20, 0, 20),
+                advisory_text: Rgb565::new(24, 32, 0),
+                critical_text: Rgb565::new(24, 0, 0),
+                hrv_text: Rgb565::new(0, 36, 0),
+                pvc_text: Rgb565::new(0, 32, 24),
+                unusable_text: Rgb565::new(20, 40, 20),
+                grid_minor: Rgb565::new(31, 54, 27),
+                grid_major: Rgb565::new(31, 38, 19),
+                trend_range: Rgb565::new(31, 44, 22),
+            },
+            Theme::Night => Color {
+                background: Rgb565::BLACK,
+                frame_border: Rgb565::new(14, 6, 0),
+                data: Rgb565::new(24, 16, 0),
+                bpm_text: Rgb565::new(24, 0, 0),
+                warning_text: Rgb565::new(20, 0, 12),
+                advisory_text: Rgb565::new(20, 20, 0),
+                critical_text: Rgb565::new(28, 0, 0),
+                hrv_text: Rgb565::new(16, 20, 0),
+                pvc_text: Rgb565::new(20, 12, 6),
+                unusable_text: Rgb565::new(8, 8, 4),
+                grid_minor: Rgb565::new(4, 2, 0),
+                grid_major: Rgb565::new(9, 4, 0),
+                trend_range: Rgb565::new(10, 4, 2),
+            },
+        }
+    }
+
+    fn grid(&self, line: GridLine) -> Rgb565 {
+        match line {
+            GridLine::None => self.background,
+            GridLine::Minor => self.grid_minor,
+            GridLine::Major => self.grid_major,
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+struct Data {
+    pub(crate) y: u16,
+    pub(crate) height: u16,
+    // Vertical grid line of the column
+    pub(crate) grid: GridLine,
+    pub(crate) mark: Option<Mark>,
+}
+
+impl Data {
+    // Segment between two mapped samples
+    fn new(from: u16, to: u16, grid: GridLine) -> Self {
+        Data {
+            y: from.min(to),
+            height: from.max(to) - from.min(to),
+            grid,
+            mark: None,
+        }
+    }
+}
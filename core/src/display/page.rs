@@ -0,0 +1,319 @@
+use core::fmt::Write;
+use embedded_graphics::fonts::{Font, Font12x16, Font6x8, Text};
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::Point;
+use embedded_graphics::style::TextStyleBuilder;
+use heapless::consts::U64;
+use heapless::{ArrayLength, String};
+
+use super::{Display, Frame, RenderMode};
+use crate::analysis::event_log::EventLog;
+use crate::analysis::hrv::HrvMetrics;
+use crate::analysis::session::Session;
+use crate::analysis::spectrum::FrequencyMetrics;
+use crate::error::{Error, Result};
+use crate::lcd::Lcd;
+use crate::settings::{AutoScale, Settings};
+use crate::ui::{Screen, Setting};
+
+// Row of the HRV screen, the value is shown for both windows
+struct HrvRow {
+    label: &'static str,
+    value: fn(&HrvMetrics) -> u16,
+    unit: &'static str,
+}
+
+// Screens made of text rows, the first row is the title. Rows are padded to the full
+// width and drawn with the background, so they are updated in place without flicker.
+impl<'a, LEN, LCD, LCDER> Display<'a, LEN, LCD, LCDER>
+where
+    LEN: ArrayLength<u16>,
+    LCD: Lcd<Error = LCDER>,
+{
+    pub fn show_hrv(
+        &mut self,
+        short_term: Option<HrvMetrics>,
+        long_term: Option<HrvMetrics>,
+        frequency: Option<FrequencyMetrics>,
+    ) -> Result<(), LCDER> {
+        if self.screen != Screen::Hrv {
+            return Ok(());
+        }
+        self.draw_row(1, "         1 MIN  5 MIN", self.color.frame_border, false)?;
+        let rows = [
+            HrvRow {
+                label: "MEAN NN",
+                value: |hrv| hrv.mean_nn,
+                unit: "ms",
+            },
+            HrvRow {
+                label: "SDNN",
+                value: |hrv| hrv.sdnn,
+                unit: "ms",
+            },
+            HrvRow {
+                label: "RMSSD",
+                value: |hrv| hrv.rmssd,
+                unit: "ms",
+            },
+            HrvRow {
+                label: "PNN50",
+                value: |hrv| hrv.pnn50 as u16,
+                unit: "%",
+            },
+            HrvRow {
+                label: "NN",
+                value: |hrv| hrv.count,
+                unit: "",
+            },
+        ];
+        for (row, HrvRow { label, value, unit }) in rows.iter().enumerate() {
+            let mut buffer = String::<U64>::new();
+            write!(&mut buffer, "{:<8}", label).map_err(|_| Error::BufferWrite)?;
+            for metrics in [short_term, long_term].iter() {
+                match metrics {
+                    Some(metrics) => write!(&mut buffer, " {:>6}", value(metrics)),
+                    None => write!(&mut buffer, " {:>6}", "---"),
+                }
+                .map_err(|_| Error::BufferWrite)?;
+            }
+            write!(&mut buffer, " {}", unit).map_err(|_| Error::BufferWrite)?;
+            self.draw_row(
+                2 + row as u8,
+                &buffer,
+                self.value_color(self.color.hrv_text),
+                false,
+            )?;
+        }
+
+        let values = match frequency {
+            Some(metrics) => [Some(metrics.lf), Some(metrics.hf)],
+            None => [None; 2],
+        };
+        for (row, (label, value)) in ["LF", "HF"].iter().zip(values.iter()).enumerate() {
+            let mut buffer = String::<U64>::new();
+            match value {
+                Some(value) => write!(&mut buffer, "{:<8} {:>6} ms2", label, value),
+                None => write!(&mut buffer, "{:<8} {:>6}", label, "---"),
+            }
+            .map_err(|_| Error::BufferWrite)?;
+            self.draw_row(
+                8 + row as u8,
+                &buffer,
+                self.value_color(self.color.hrv_text),
+                false,
+            )?;
+        }
+        let mut buffer = String::<U64>::new();
+        match frequency {
+            Some(metrics) => write!(
+                &mut buffer,
+                "{:<8} {:>3}.{:02}",
+                "LF/HF",
+                metrics.ratio / 100,
+                metrics.ratio % 100
+            ),
+            None => write!(&mut buffer, "{:<8} {:>6}", "LF/HF", "---"),
+        }
+        .map_err(|_| Error::BufferWrite)?;
+        self.draw_row(10, &buffer, self.value_color(self.color.hrv_text), false)
+    }
+
+    // Newest event first, with the time since power-on and the duration
+    pub fn show_events(&mut self, log: &EventLog) -> Result<(), LCDER> {
+        if self.screen != Screen::EventLog {
+            return Ok(());
+        }
+        let mut buffer = String::<U64>::new();
+        write!(&mut buffer, "{} TOTAL", log.count()).map_err(|_| Error::BufferWrite)?;
+        self.draw_row(1, &buffer, self.color.frame_border, false)?;
+
+        let mut events = log.events();
+        for row in 2..Page::new(&self.layout.frame).rows {
+            let mut buffer = String::<U64>::new();
+            let color = match events.next() {
+                Some(event) => {
+                    write!(
+                        &mut buffer,
+                        "{:<5} {:<8} ",
+                        event.rhythm.name(),
+                        event.severity.name()
+                    )
+                    .map_err(|_| Error::BufferWrite)?;
+                    self.write_time(&mut buffer, event.start / self.sample_rate)?;
+                    match event.end {
+                        Some(end) => {
+                            let duration = end.wrapping_sub(event.start) / self.sample_rate;
+                            write!(&mut buffer, " {:>3}m{:02}s", duration / 60, duration % 60)
+                        }
+                        None => write!(&mut buffer, " NOW"),
+                    }
+                    .map_err(|_| Error::BufferWrite)?;
+                    self.severity_color(event.severity)
+                }
+                None => self.color.background,
+            };
+            self.draw_row(row, &buffer, color, false)?;
+        }
+        Ok(())
+    }
+
+    pub fn show_recording(&mut self, session: &Session, now: u32) -> Result<(), LCDER> {
+        if self.screen != Screen::Recording {
+            return Ok(());
+        }
+        let action = if session.is_running() {
+            "STOP"
+        } else {
+            "START"
+        };
+        self.draw_row(1, action, self.color.frame_border, self.focus == Some(0))?;
+
+        let mut buffer = String::<U64>::new();
+        write!(&mut buffer, "{:<8} ", "TIME").map_err(|_| Error::BufferWrite)?;
+        match session.duration(now) {
+            Some(duration) => self.write_time(&mut buffer, duration)?,
+            None => write!(&mut buffer, "---").map_err(|_| Error::BufferWrite)?,
+        }
+        self.draw_row(3, &buffer, self.color.data, false)?;
+
+        let mut buffer = String::<U64>::new();
+        match session.rates() {
+            Some((min, mean, max)) => {
+                write!(&mut buffer, "{:<8} {}/{}/{} BPM", "HR", min, mean, max)
+            }
+            None => write!(&mut buffer, "{:<8} ---", "HR"),
+        }
+        .map_err(|_| Error::BufferWrite)?;
+        self.draw_row(4, &buffer, self.color.bpm_text, false)?;
+
+        let counts = [
+            ("BEATS", session.beats(), self.color.bpm_text),
+            ("PVC", session.pvcs(), self.color.pvc_text),
+            ("EVENTS", session.events(), self.color.warning_text),
+        ];
+        for (row, (label, count, color)) in counts.iter().enumerate() {
+            let mut buffer = String::<U64>::new();
+            write!(&mut buffer, "{:<8} {}", label, count).map_err(|_| Error::BufferWrite)?;
+            self.draw_row(5 + row as u8, &buffer, *color, false)?;
+        }
+        Ok(())
+    }
+
+    pub fn show_settings(&mut self, settings: &Settings) -> Result<(), LCDER> {
+        if self.screen != Screen::Settings {
+            return Ok(());
+        }
+        for (row, setting) in Setting::ALL.iter().enumerate() {
+            let mut buffer = String::<U64>::new();
+            write!(&mut buffer, "{:<12}", setting.name()).map_err(|_| Error::BufferWrite)?;
+            match setting {
+                Setting::SweepSpeed => write!(&mut buffer, "{}", settings.sweep_speed.name()),
+                Setting::Gain if settings.auto_scale != AutoScale::Off => {
+                    write!(&mut buffer, "AUTO")
+                }
+                Setting::Gain => write!(&mut buffer, "{}", settings.gain.name()),
+                Setting::AutoScale => write!(&mut buffer, "{}", settings.auto_scale.name()),
+                Setting::RenderMode => match settings.render_mode {
+                    RenderMode::Scroll => write!(&mut buffer, "SCROLL"),
+                    RenderMode::Sweep => write!(&mut buffer, "SWEEP"),
+                },
+                Setting::Filter => write!(&mut buffer, "{}", settings.filter.name()),
+                Setting::Mains => write!(&mut buffer, "{}", settings.mains.name()),
+                Setting::Rejection => write!(&mut buffer, "{}", settings.rejection.name()),
+                Setting::Bradycardia => write!(&mut buffer, "{} BPM", settings.bradycardia),
+                Setting::Tachycardia => write!(&mut buffer, "{} BPM", settings.tachycardia),
+                Setting::Theme => write!(&mut buffer, "{}", settings.theme.name()),
+            }
+            .map_err(|_| Error::BufferWrite)?;
+            let focused = self.focus == Some(row as u8);
+            self.draw_row(1 + row as u8, &buffer, self.color.frame_border, focused)?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn draw_title(&mut self) -> Result<(), LCDER> {
+        self.draw_row(0, self.screen.name(), self.color.bpm_text, false)
+    }
+
+    // The focused row is inverted
+    pub(super) fn draw_row(
+        &mut self,
+        row: u8,
+        text: &str,
+        color: Rgb565,
+        focused: bool,
+    ) -> Result<(), LCDER> {
+        let page = Page::new(&self.layout.frame);
+        let mut buffer = String::<U64>::new();
+        write!(&mut buffer, "{:<width$}", text, width = page.columns)
+            .map_err(|_| Error::BufferWrite)?;
+        let colors = if focused {
+            (self.color.background, color)
+        } else {
+            (color, self.color.background)
+        };
+        let position = Point::new(page.left, page.top + row as i32 * page.row_height);
+        if page.large {
+            self.draw_text(Font12x16, &buffer, position, colors)
+        } else {
+            self.draw_text(Font6x8, &buffer, position, colors)
+        }
+    }
+
+    fn draw_text<F: Font + Copy>(
+        &mut self,
+        font: F,
+        text: &str,
+        position: Point,
+        (text_color, background): (Rgb565, Rgb565),
+    ) -> Result<(), LCDER> {
+        let style = TextStyleBuilder::new(font)
+            .text_color(text_color)
+            .background_color(background)
+            .build();
+        let text = Text::new(text, position).into_styled(style);
+        self.lcd.draw(&text).map_err(Error::Lcd)
+    }
+
+    // Hours, minutes and seconds
+    fn write_time(&self, buffer: &mut String<U64>, seconds: u32) -> Result<(), LCDER> {
+        write!(
+            buffer,
+            "{:02}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+        .map_err(|_| Error::BufferWrite)
+    }
+}
+
+struct Page {
+    left: i32,
+    top: i32,
+    row_height: i32,
+    rows: u8,
+    columns: usize,
+    large: bool,
+}
+
+impl Page {
+    const MARGIN: i32 = 8;
+    // Characters of the longest row, narrower frames use the small font
+    const MIN_COLUMNS: i32 = 31;
+
+    fn new(frame: &Frame) -> Self {
+        let width = frame.width - 2 * Page::MARGIN;
+        let large = width / 12 >= Page::MIN_COLUMNS;
+        let (character_width, row_height) = if large { (12, 20) } else { (6, 10) };
+        Page {
+            left: frame.top_left.x + Page::MARGIN,
+            top: frame.top_left.y + Page::MARGIN,
+            row_height,
+            rows: ((frame.height - 2 * Page::MARGIN) / row_height) as u8,
+            columns: (width / character_width) as usize,
+            large,
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use core::fmt::Write;
+use embedded_graphics::fonts::{Font6x8, Text};
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::{Point, Primitive};
+use embedded_graphics::primitives::{Line, Rectangle};
+use embedded_graphics::style::{PrimitiveStyle, TextStyle};
+use heapless::consts::U8;
+use heapless::{ArrayLength, String};
+
+use super::{Display, Frame};
+use crate::analysis::trend::{HrTrend, TrendPoint};
+use crate::error::{Error, Result};
+use crate::lcd::Lcd;
+use crate::ui::Screen;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TrendSpan {
+    Minutes30,
+    Hours2,
+    Hours8,
+}
+
+impl TrendSpan {
+    fn minutes(self) -> usize {
+        match self {
+            TrendSpan::Minutes30 => 30,
+            TrendSpan::Hours2 => 120,
+            TrendSpan::Hours8 => 480,
+        }
+    }
+
+    pub fn next(self) -> TrendSpan {
+        match self {
+            TrendSpan::Minutes30 => TrendSpan::Hours2,
+            TrendSpan::Hours2 => TrendSpan::Hours8,
+            TrendSpan::Hours8 => TrendSpan::Minutes30,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TrendSpan::Minutes30 => "HR 30 MIN",
+            TrendSpan::Hours2 => "HR 2 H",
+            TrendSpan::Hours8 => "HR 8 H",
+        }
+    }
+
+    // Evenly spaced from the start of the span to now
+    fn labels(self) -> &'static [&'static str] {
+        match self {
+            TrendSpan::Minutes30 => &["-30m", "-20m", "-10m", "0"],
+            TrendSpan::Hours2 => &["-2h", "-90m", "-1h", "-30m", "0"],
+            TrendSpan::Hours8 => &["-8h", "-6h", "-4h", "-2h", "0"],
+        }
+    }
+}
+
+// Minutes merged into one bar of the chart
+#[derive(Copy, Clone)]
+struct Bin {
+    min: u8,
+    max: u8,
+    sum: u16,
+    count: u16,
+}
+
+impl Bin {
+    fn new(point: &TrendPoint) -> Self {
+        Bin {
+            min: point.min,
+            max: point.max,
+            sum: point.mean as u16,
+            count: 1,
+        }
+    }
+
+    fn add(&mut self, point: &TrendPoint) {
+        self.min = self.min.min(point.min);
+        self.max = self.max.max(point.max);
+        self.sum += point.mean as u16;
+        self.count += 1;
+    }
+
+    fn mean(&self) -> u8 {
+        (self.sum / self.count) as u8
+    }
+}
+
+impl<'a, LEN, LCD, LCDER> Display<'a, LEN, LCD, LCDER>
+where
+    LEN: ArrayLength<u16>,
+    LCD: Lcd<Error = LCDER>,
+{
+    // Range of every bar from the minimum to the maximum, the means joined by a line
+    pub fn show_trend(&mut self, trend: &HrTrend) -> Result<(), LCDER> {
+        let span = match self.screen {
+            Screen::Trend(span) => span,
+            _ => return Ok(()),
+        };
+        self.clear_frame()?;
+        // The span is selected with the focus on the title
+        self.draw_row(0, span.name(), self.color.bpm_text, self.focus == Some(0))?;
+        let chart = Chart::new(&self.layout.frame);
+        self.draw_chart_axes(&chart, span)?;
+
+        let minutes_per_bin = span.minutes().div_ceil(Chart::MAX_BINS);
+        let bins = span.minutes() / minutes_per_bin;
+        let width = (chart.right - chart.left) / bins as i32;
+        let skip = trend.len().saturating_sub(span.minutes());
+        // Minutes of the span before the first stored one
+        let offset = span.minutes() - (trend.len() - skip);
+
+        let mut bin: Option<(usize, Bin)> = None;
+        let mut previous = None;
+        for (minute, point) in trend.points().skip(skip).enumerate() {
+            let index = (minute + offset) / minutes_per_bin;
+            if let Some((last, value)) = bin {
+                if last != index {
+                    previous = Some(self.draw_bin(&chart, last, width, &value, previous)?);
+                    bin = None;
+                }
+            }
+            if let Some(point) = point {
+                match bin.as_mut() {
+                    Some((_, value)) => value.add(point),
+                    None => bin = Some((index, Bin::new(point))),
+                }
+            }
+        }
+        if let Some((last, value)) = bin {
+            self.draw_bin(&chart, last, width, &value, previous)?;
+        }
+        Ok(())
+    }
+
+    fn draw_chart_axes(&mut self, chart: &Chart, span: TrendSpan) -> Result<(), LCDER> {
+        let mut bpm = Chart::MIN_BPM;
+        while bpm <= Chart::MAX_BPM {
+            let y = chart.y(bpm);
+            let line = Line::new(Point::new(chart.left, y), Point::new(chart.right, y))
+                .into_styled(PrimitiveStyle::with_stroke(self.color.grid_minor, 1));
+            self.lcd.draw(&line).map_err(Error::Lcd)?;
+            let mut buffer = String::<U8>::new();
+            write!(&mut buffer, "{:>3}", bpm).map_err(|_| Error::BufferWrite)?;
+            let position = Point::new(
+                self.layout.frame.top_left.x + Chart::MARGIN,
+                y - Chart::TEXT_HEIGHT / 2,
+            );
+            let text = Text::new(&buffer, position)
+                .into_styled(TextStyle::new(Font6x8, self.color.frame_border));
+            self.lcd.draw(&text).map_err(Error::Lcd)?;
+            bpm += Chart::BPM_STEP;
+        }
+
+        let axes = [
+            (
+                Point::new(chart.left, chart.top),
+                Point::new(chart.left, chart.bottom),
+            ),
+            (
+                Point::new(chart.left, chart.bottom),
+                Point::new(chart.right, chart.bottom),
+            ),
+        ];
+        for (start, end) in axes.iter() {
+            let axis = Line::new(*start, *end)
+                .into_styled(PrimitiveStyle::with_stroke(self.color.frame_border, 1));
+            self.lcd.draw(&axis).map_err(Error::Lcd)?;
+        }
+
+        let labels = span.labels();
+        for (index, label) in labels.iter().enumerate() {
+            let x =
+                chart.left + (chart.right - chart.left) * index as i32 / (labels.len() as i32 - 1);
+            let tick = Line::new(Point::new(x, chart.bottom), Point::new(x, chart.bottom + 2))
+                .into_styled(PrimitiveStyle::with_stroke(self.color.frame_border, 1));
+            self.lcd.draw(&tick).map_err(Error::Lcd)?;
+            let width = label.len() as i32 * Chart::TEXT_WIDTH;
+            let left = (x - width / 2).min(self.layout.frame.bottom_right.x - width);
+            let text = Text::new(label, Point::new(left, chart.bottom + 4))
+                .into_styled(TextStyle::new(Font6x8, self.color.frame_border));
+            self.lcd.draw(&text).map_err(Error::Lcd)?;
+        }
+        Ok(())
+    }
+
+    // Returns the index and the mean of the bar for the line to the next one
+    fn draw_bin(
+        &mut self,
+        chart: &Chart,
+        index: usize,
+        width: i32,
+        bin: &Bin,
+        previous: Option<(usize, Point)>,
+    ) -> Result<(usize, Point), LCDER> {
+        let left = chart.left + index as i32 * width + 1;
+        let right = (left + width - 2).max(left);
+        let range = Rectangle::new(
+            Point::new(left, chart.y(bin.max as i32)),
+            Point::new(right, chart.y(bin.min as i32)),
+        )
+        .into_styled(PrimitiveStyle::with_fill(self.color.trend_range));
+        self.lcd.draw(&range).map_err(Error::Lcd)?;
+
+        let y = chart.y(bin.mean() as i32);
+        let mean = Point::new((left + right) / 2, y);
+        self.draw_line(Point::new(left, y), Point::new(right, y), self.color.data)?;
+        if let Some((last, point)) = previous {
+            // Gaps are not bridged
+            if last + 1 == index {
+                self.draw_line(point, mean, self.color.data)?;
+            }
+        }
+        Ok((index, mean))
+    }
+
+    fn draw_line(&mut self, start: Point, end: Point, color: Rgb565) -> Result<(), LCDER> {
+        let line = Line::new(start, end).into_styled(PrimitiveStyle::with_stroke(color, 1));
+        self.lcd.draw(&line).map_err(Error::Lcd)
+    }
+}
+
+struct Chart {
+    left: i32,
+    right: i32,
+    top: i32,
+    bottom: i32,
+}
+
+impl Chart {
+    const MARGIN: i32 = 4;
+    const TEXT_WIDTH: i32 = 6;
+    const TEXT_HEIGHT: i32 = 8;
+    const TITLE_HEIGHT: i32 = 16;
+    const MIN_BPM: i32 = 40;
+    const MAX_BPM: i32 = 200;
+    const BPM_STEP: i32 = 40;
+    // Longer spans merge minutes into one bar
+    const MAX_BINS: usize = 120;
+
+    // Room for the title on the top, the rate labels on the left and the time labels
+    // on the bottom
+    fn new(frame: &Frame) -> Self {
+        Chart {
+            left: frame.top_left.x + 2 * Chart::MARGIN + 3 * Chart::TEXT_WIDTH,
+            right: frame.bottom_right.x - 2 * Chart::MARGIN,
+            top: frame.top_left.y + 3 * Chart::MARGIN + Chart::TITLE_HEIGHT,
+            bottom: frame.bottom_right.y - Chart::TEXT_HEIGHT - 2 * Chart::MARGIN,
+        }
+    }
+
+    fn y(&self, bpm: i32) -> i32 {
+        let bpm = bpm.clamp(Chart::MIN_BPM, Chart::MAX_BPM);
+        self.bottom
+            - (bpm - Chart::MIN_BPM) * (self.bottom - self.top) / (Chart::MAX_BPM - Chart::MIN_BPM)
+    }
+}
@@ -0,0 +1,255 @@
+use crate::analysis::pvc::BeatClass;
+use crate::display::Scale;
+use crate::filter::MainsFrequency;
+use crate::math::{exp, sin, sqrt, PI};
+use crate::sampler::{BASELINE_MV, SUPPLY_MV};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EcgSynConfig {
+    // Mean rate in BPM
+    pub heart_rate: u16,
+    // Amplitudes of the R-R interval modulation by the respiration at 0.25 Hz and by
+    // the Mayer waves at 0.1 Hz, in ms
+    pub rsa_ms: u16,
+    pub mayer_ms: u16,
+    // Amplitudes at the electrodes, the noise is the RMS value
+    pub r_wave_uv: u16,
+    pub noise_uv: u16,
+    pub wander_uv: u16,
+    pub mains_uv: u16,
+    pub mains: MainsFrequency,
+    // Every n-th beat is a PVC with a compensatory pause
+    pub pvc_every: Option<u16>,
+    pub seed: u32,
+}
+
+impl Default for EcgSynConfig {
+    fn default() -> Self {
+        EcgSynConfig {
+            heart_rate: 60,
+            rsa_ms: 40,
+            mayer_ms: 20,
+            r_wave_uv: 1000,
+            noise_uv: 10,
+            wander_uv: 100,
+            mains_uv: 0,
+            mains: MainsFrequency::Hz50,
+            pvc_every: None,
+            seed: 1,
+        }
+    }
+}
+
+// Gaussian of a wave in the angle of the cycle, the R peak is at zero
+#[derive(Copy, Clone)]
+struct Wave {
+    angle: f64,
+    amplitude: f64,
+    width: f64,
+}
+
+impl Wave {
+    const fn new(angle: f64, amplitude: f64, width: f64) -> Self {
+        Wave {
+            angle,
+            amplitude,
+            width,
+        }
+    }
+}
+
+// Synthetic ECG from the dynamical model of McSharry et al. (ECGSYN).
+//
+// A point circles the limit cycle once per beat and the P, Q, R, S and T waves are
+// Gaussian attractors at fixed angles on it, the ECG is the height of the point pushed
+// up and down by them. On the limit cycle the angle just advances with the rate of the
+// current R-R interval, which is chosen at every R peak. The samples are in mV at the
+// ADC input like the `Sampler` converts them, amplified by the front-end.
+pub struct EcgSyn {
+    config: EcgSynConfig,
+    sample_rate: u32,
+    // Waves of the normal beat and of the PVC, scaled to the mean rate
+    waves: [[Wave; 5]; 2],
+    // Height per mV at the electrodes
+    scale: f64,
+    // Angle from the last R peak, the waves before 2 PI belong to the next beat
+    angle: f64,
+    // Radians per sample
+    step: f64,
+    height: f64,
+    current: BeatClass,
+    next: BeatClass,
+    // Interval after a PVC
+    pause_ms: Option<u32>,
+    beats: u32,
+    index: u32,
+    peak: Option<BeatClass>,
+    random: u32,
+}
+
+impl EcgSyn {
+    // Angle, amplitude and width of the P, Q, R, S and T waves at 60 BPM
+    const NORMAL: [Wave; 5] = [
+        Wave::new(-70.0 * PI / 180.0, 1.2, 0.25),
+        Wave::new(-15.0 * PI / 180.0, -5.0, 0.1),
+        Wave::new(0.0, 30.0, 0.1),
+        Wave::new(15.0 * PI / 180.0, -7.5, 0.1),
+        Wave::new(100.0 * PI / 180.0, 0.75, 0.4),
+    ];
+    // No P wave, wide QRS and discordant T wave
+    const PVC: [Wave; 5] = [
+        Wave::new(-70.0 * PI / 180.0, 0.0, 0.25),
+        Wave::new(-25.0 * PI / 180.0, -1.0, 0.15),
+        Wave::new(0.0, 6.0, 0.25),
+        Wave::new(25.0 * PI / 180.0, -3.0, 0.2),
+        Wave::new(110.0 * PI / 180.0, -0.6, 0.4),
+    ];
+    const RSA_HZ: f64 = 0.25;
+    const MAYER_HZ: f64 = 0.1;
+    // The respiration moves the baseline too
+    const WANDER_HZ: f64 = 0.25;
+    // PVCs come after 60 % of the R-R interval
+    const COUPLING: u32 = 60;
+    // Largest step of the integration in radians
+    const MAX_STEP: f64 = 0.02;
+
+    pub fn new(sample_rate: u32, config: EcgSynConfig) -> Self {
+        // The waves get wider and the P and T waves closer to the R peak with the rate
+        // by the square and the fourth root of the rate relative to 60 BPM
+        let factor = sqrt(config.heart_rate.max(1) as u64 * 1_000_000 / 60) as f64 / 1000.0;
+        let factor2 = sqrt((factor * 1_000_000.0) as u64) as f64 / 1000.0;
+        let mut waves = [EcgSyn::NORMAL, EcgSyn::PVC];
+        for beat in waves.iter_mut() {
+            for (wave, angle) in beat
+                .iter_mut()
+                .zip([factor2, factor, 1.0, factor, factor2].iter())
+            {
+                wave.angle *= angle;
+                wave.width *= factor;
+            }
+        }
+        // The height follows the integral of the attractor, a * b^2 at the R peak
+        let r_wave = waves[0][2];
+        let scale = r_wave.amplitude * r_wave.width * r_wave.width;
+
+        let mut ecgsyn = EcgSyn {
+            config,
+            sample_rate,
+            waves,
+            scale,
+            // Half of the cycle before the first R peak
+            angle: PI,
+            step: 0.0,
+            height: 0.0,
+            current: BeatClass::Normal,
+            next: BeatClass::Normal,
+            pause_ms: None,
+            beats: 0,
+            index: 0,
+            peak: None,
+            random: config.seed,
+        };
+        ecgsyn.next_interval();
+        ecgsyn
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    // Class of the beat with its R peak at the last sample
+    pub fn peak(&self) -> Option<BeatClass> {
+        self.peak
+    }
+
+    // Chooses the class of the next beat and the rate up to it
+    fn next_interval(&mut self) {
+        let time = self.index as f64 / self.sample_rate as f64;
+        let modulation = self.config.rsa_ms as f64 * sin(2.0 * PI * EcgSyn::RSA_HZ * time)
+            + self.config.mayer_ms as f64 * sin(2.0 * PI * EcgSyn::MAYER_HZ * time);
+        let interval = (60_000.0 / self.config.heart_rate.max(1) as f64 + modulation).max(200.0);
+
+        self.beats += 1;
+        let pvc = matches!(self.config.pvc_every, Some(every) if every > 0 && self.beats.is_multiple_of(every as u32));
+        let interval = match (self.pause_ms.take(), pvc) {
+            (Some(pause), _) => pause as f64,
+            (None, true) => {
+                let coupling = interval * EcgSyn::COUPLING as f64 / 100.0;
+                self.pause_ms = Some((2.0 * interval - coupling) as u32);
+                coupling
+            }
+            (None, false) => interval,
+        };
+        self.next = if pvc {
+            BeatClass::Pvc
+        } else {
+            BeatClass::Normal
+        };
+        self.step = 2.0 * PI * 1000.0 / (interval * self.sample_rate as f64);
+    }
+
+    // Pull of the attractors at the angle, the waves after PI belong to the next beat
+    fn force(&self, angle: f64) -> f64 {
+        let (beat, angle) = if angle < PI {
+            (self.current, angle)
+        } else {
+            (self.next, angle - 2.0 * PI)
+        };
+        let waves = &self.waves[(beat == BeatClass::Pvc) as usize];
+        let mut force = 0.0;
+        for wave in waves.iter() {
+            let distance = angle - wave.angle;
+            let exponent = -distance * distance / (2.0 * wave.width * wave.width);
+            // Negligible beyond 6 widths
+            if exponent > -18.0 {
+                force -= wave.amplitude * distance * exp(exponent);
+            }
+        }
+        force
+    }
+
+    // Uniform in -0.5..0.5
+    fn uniform(&mut self) -> f64 {
+        self.random = self
+            .random
+            .wrapping_mul(1_664_525)
+            .wrapping_add(1_013_904_223);
+        (self.random >> 8) as f64 / (1u32 << 24) as f64 - 0.5
+    }
+}
+
+impl Iterator for EcgSyn {
+    type Item = u16;
+
+    fn next(&mut self) -> Option<u16> {
+        self.peak = None;
+        let steps = (self.step / EcgSyn::MAX_STEP) as u32 + 1;
+        let step = self.step / steps as f64;
+        // Time of a step in seconds, the height decays back to zero in about a second
+        let dt = 1.0 / (self.sample_rate * steps) as f64;
+        for _ in 0..steps {
+            let force = self.force(self.angle + step / 2.0);
+            self.height += force * step - self.height * dt;
+            self.angle += step;
+            if self.angle >= 2.0 * PI {
+                self.angle -= 2.0 * PI;
+                self.current = self.next;
+                self.peak = Some(self.current);
+                self.next_interval();
+            }
+        }
+
+        let time = self.index as f64 / self.sample_rate as f64;
+        let config = self.config;
+        // Sum of four uniform values is close to normal, with the variance of 1/3
+        let noise = (0..4).map(|_| self.uniform()).sum::<f64>() * 1.732;
+        let uv = self.height / self.scale * config.r_wave_uv as f64
+            + noise * config.noise_uv as f64
+            + config.wander_uv as f64 * sin(2.0 * PI * EcgSyn::WANDER_HZ * time)
+            + config.mains_uv as f64 * sin(2.0 * PI * config.mains.hz() as f64 * time);
+        self.index = self.index.wrapping_add(1);
+
+        let mv = BASELINE_MV as f64 + uv * Scale::FRONT_END_GAIN as f64 / 1000.0;
+        Some(mv.max(0.0).min(SUPPLY_MV as f64) as u16)
+    }
+}
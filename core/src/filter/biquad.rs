@@ -0,0 +1,148 @@
+use heapless::{ArrayLength, Vec};
+
+use crate::filter::Filter;
+use crate::math::{cos, round, sin, PI};
+
+// Direct form I biquad with Q28 coefficients and first order error feedback,
+// which keeps very low corner frequencies (0.05 Hz at 500 Hz) stable and precise.
+#[derive(Copy, Clone)]
+pub struct Biquad {
+    b: [i32; 3],
+    // a0 is normalized to 1
+    a: [i32; 2],
+    x: [i32; 2],
+    y: [i32; 2],
+    error: i64,
+}
+
+impl Biquad {
+    const FRACTION_BITS: u32 = 28;
+    const ONE: f64 = (1 << Biquad::FRACTION_BITS) as f64;
+    const BUTTERWORTH_Q: f64 = core::f64::consts::FRAC_1_SQRT_2;
+
+    // Coefficients as in "Cookbook formulae for audio EQ biquad filter coefficients"
+    fn from_float(b: [f64; 3], a: [f64; 3]) -> Self {
+        let quantize = |value: f64| round(value / a[0] * Biquad::ONE) as i32;
+        Biquad {
+            b: [quantize(b[0]), quantize(b[1]), quantize(b[2])],
+            a: [quantize(a[1]), quantize(a[2])],
+            x: [0; 2],
+            y: [0; 2],
+            error: 0,
+        }
+    }
+
+    pub fn high_pass(corner: f64, sample_rate: f64) -> Self {
+        let w0 = 2.0 * PI * corner / sample_rate;
+        let cos_w0 = cos(w0);
+        let alpha = sin(w0) / (2.0 * Biquad::BUTTERWORTH_Q);
+        Biquad::from_float(
+            [(1.0 + cos_w0) / 2.0, -(1.0 + cos_w0), (1.0 + cos_w0) / 2.0],
+            [1.0 + alpha, -2.0 * cos_w0, 1.0 - alpha],
+        )
+    }
+
+    pub fn low_pass(corner: f64, sample_rate: f64) -> Self {
+        let w0 = 2.0 * PI * corner / sample_rate;
+        let cos_w0 = cos(w0);
+        let alpha = sin(w0) / (2.0 * Biquad::BUTTERWORTH_Q);
+        Biquad::from_float(
+            [(1.0 - cos_w0) / 2.0, 1.0 - cos_w0, (1.0 - cos_w0) / 2.0],
+            [1.0 + alpha, -2.0 * cos_w0, 1.0 - alpha],
+        )
+    }
+
+    pub fn notch(center: f64, q: f64, sample_rate: f64) -> Self {
+        let w0 = 2.0 * PI * center / sample_rate;
+        let cos_w0 = cos(w0);
+        let alpha = sin(w0) / (2.0 * q);
+        Biquad::from_float(
+            [1.0, -2.0 * cos_w0, 1.0],
+            [1.0 + alpha, -2.0 * cos_w0, 1.0 - alpha],
+        )
+    }
+
+    // Sets the state as if the input was constant `sample` forever
+    pub fn prime(&mut self, sample: i32) {
+        let numerator: i64 = self.b.iter().map(|&b| b as i64).sum();
+        let denominator = (1 << Biquad::FRACTION_BITS) + self.a[0] as i64 + self.a[1] as i64;
+        let output = if denominator == 0 {
+            0
+        } else {
+            (sample as i64 * numerator / denominator) as i32
+        };
+        self.x = [sample; 2];
+        self.y = [output; 2];
+        self.error = 0;
+    }
+}
+
+impl Filter for Biquad {
+    fn process(&mut self, sample: i32) -> i32 {
+        let accumulator = self.b[0] as i64 * sample as i64
+            + self.b[1] as i64 * self.x[0] as i64
+            + self.b[2] as i64 * self.x[1] as i64
+            - self.a[0] as i64 * self.y[0] as i64
+            - self.a[1] as i64 * self.y[1] as i64
+            + self.error;
+        let output = accumulator >> Biquad::FRACTION_BITS;
+        // Keep the truncated part for the next sample
+        self.error = accumulator - (output << Biquad::FRACTION_BITS);
+        let output = output as i32;
+
+        self.x = [sample, self.x[0]];
+        self.y = [output, self.y[0]];
+        output
+    }
+}
+
+// Cascade of up to `N` biquad sections applied in order
+pub struct Cascade<N>
+where
+    N: ArrayLength<Biquad>,
+{
+    sections: Vec<Biquad, N>,
+}
+
+impl<N> Cascade<N>
+where
+    N: ArrayLength<Biquad>,
+{
+    pub fn new() -> Self {
+        Cascade {
+            sections: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, section: Biquad) -> Result<(), Biquad> {
+        self.sections.push(section)
+    }
+
+    pub fn prime(&mut self, sample: i32) {
+        let mut sample = sample;
+        for section in self.sections.iter_mut() {
+            section.prime(sample);
+            sample = section.y[0];
+        }
+    }
+}
+
+impl<N> Filter for Cascade<N>
+where
+    N: ArrayLength<Biquad>,
+{
+    fn process(&mut self, sample: i32) -> i32 {
+        self.sections
+            .iter_mut()
+            .fold(sample, |sample, section| section.process(sample))
+    }
+}
+
+impl<N> Default for Cascade<N>
+where
+    N: ArrayLength<Biquad>,
+{
+    fn default() -> Self {
+        Cascade::new()
+    }
+}
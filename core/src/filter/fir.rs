@@ -0,0 +1,100 @@
+use heapless::{ArrayLength, Vec};
+
+use crate::filter::Filter;
+use crate::math::{cos, round, sin, PI};
+
+// FIR filter with Q15 taps and circular delay line of the same length
+pub struct Fir<N>
+where
+    N: ArrayLength<i32>,
+{
+    taps: Vec<i32, N>,
+    history: Vec<i32, N>,
+    position: usize,
+}
+
+impl<N> Fir<N>
+where
+    N: ArrayLength<i32>,
+{
+    const FRACTION_BITS: u32 = 15;
+
+    pub fn new(taps: &[i32]) -> Self {
+        let mut fir = Fir {
+            taps: Vec::new(),
+            history: Vec::new(),
+            position: 0,
+        };
+        let len = taps.len().min(fir.taps.capacity());
+        // Cannot fail, the length is clamped to the capacity
+        fir.taps.extend_from_slice(&taps[..len]).ok();
+        fir.history.resize(len, 0).ok();
+        fir
+    }
+
+    // Hamming windowed sinc using all `N` taps, odd `N` gives linear phase with integer delay
+    pub fn low_pass(corner: f64, sample_rate: f64) -> Self {
+        let mut fir = Fir::new(&[]);
+        let len = fir.taps.capacity();
+        let middle = (len as f64 - 1.0) / 2.0;
+        let fc = corner / sample_rate;
+        let tap = |n: usize| {
+            let t = n as f64 - middle;
+            let sinc = if t == 0.0 {
+                2.0 * fc
+            } else {
+                sin(2.0 * PI * fc * t) / (PI * t)
+            };
+            let window = if len > 1 {
+                0.54 - 0.46 * cos(2.0 * PI * n as f64 / (len as f64 - 1.0))
+            } else {
+                1.0
+            };
+            sinc * window
+        };
+
+        // Normalize to unity gain at DC
+        let sum: f64 = (0..len).map(tap).sum();
+        let one = (1 << Self::FRACTION_BITS) as f64;
+        for n in 0..len {
+            fir.taps.push(round(tap(n) / sum * one) as i32).ok();
+        }
+        fir.history.resize(len, 0).ok();
+        fir
+    }
+
+    // Number of samples the output lags behind for symmetric taps
+    pub fn delay(&self) -> usize {
+        self.taps.len() / 2
+    }
+
+    pub fn prime(&mut self, sample: i32) {
+        for item in self.history.iter_mut() {
+            *item = sample;
+        }
+    }
+}
+
+impl<N> Filter for Fir<N>
+where
+    N: ArrayLength<i32>,
+{
+    fn process(&mut self, sample: i32) -> i32 {
+        let len = self.history.len();
+        if len == 0 {
+            return sample;
+        }
+        self.history[self.position] = sample;
+        let mut accumulator: i64 = 0;
+        let mut index = self.position;
+        for tap in self.taps.iter() {
+            accumulator += *tap as i64 * self.history[index] as i64;
+            index = if index == 0 { len - 1 } else { index - 1 };
+        }
+        self.position += 1;
+        if self.position >= len {
+            self.position = 0;
+        }
+        ((accumulator + (1 << (Self::FRACTION_BITS - 1))) >> Self::FRACTION_BITS) as i32
+    }
+}
@@ -0,0 +1,231 @@
+use heapless::consts::U4;
+use heapless::Vec;
+
+use crate::filter::Filter;
+use crate::math::{cos, round, sin, sqrt, PI};
+
+// Adaptive power-line interference canceller.
+//
+// A quadrature reference for the mains frequency and its harmonics is generated
+// internally and LMS weights estimate amplitude and phase of each component, which is
+// then subtracted from the input. A drift of the mains frequency shows up as rotation of
+// the fundamental weights, the reference oscillators are retuned by it periodically so
+// the canceller stays locked where a fixed notch would not. Adaptation is slowed down on
+// steep slopes so the QRS complexes do not leak into the estimate.
+pub struct PowerLineCanceller {
+    harmonics: Vec<Harmonic, U4>,
+    previous: i32,
+    slope_limit: i32,
+    tracking: Tracking,
+}
+
+impl PowerLineCanceller {
+    pub const MAX_HARMONICS: usize = 4;
+    // LMS step size as power of two, 2^-6 gives ~0.25 s time constant at 500 Hz
+    const STEP_BITS: u32 = 6;
+    // Weights carry extra fraction bits so small updates are not lost
+    const WEIGHT_BITS: u32 = 12;
+    // Steeper slopes of the cleaned signal in millivolts per millisecond slow down
+    // the adaptation by `SLOW_BITS`
+    const SLOPE_LIMIT: i32 = 20;
+    const SLOW_BITS: u32 = 4;
+    // Retuning period in milliseconds and maximal correction in mHz
+    const TRACKING_PERIOD: u32 = 100;
+    const MAX_DRIFT: u32 = 1500;
+    // Weaker interference in millivolts does not have reliable phase
+    const MIN_TRACKING_AMPLITUDE: i64 = 2;
+
+    // `scale` is the number of fraction bits of the millivolt input samples
+    pub fn new(mains: u32, harmonics: usize, sample_rate: u32, scale: u32) -> Self {
+        let mut canceller = PowerLineCanceller {
+            harmonics: Vec::new(),
+            previous: 0,
+            slope_limit: (PowerLineCanceller::SLOPE_LIMIT << scale) * 1000 / sample_rate as i32,
+            tracking: Tracking {
+                period: (sample_rate * PowerLineCanceller::TRACKING_PERIOD / 1000).max(1),
+                count: 0,
+                weights: (0, 0),
+                offset: 0,
+                max_offset: round(
+                    2.0 * PI * PowerLineCanceller::MAX_DRIFT as f64 / 1000.0 / sample_rate as f64
+                        * Harmonic::ONE as f64,
+                ) as i32,
+                min_power: (PowerLineCanceller::MIN_TRACKING_AMPLITUDE << scale).pow(2),
+            },
+        };
+        let harmonics = harmonics.min(PowerLineCanceller::MAX_HARMONICS);
+        for n in 1..=harmonics as u32 {
+            // Skip components that would alias
+            if 2 * n * mains * 100 >= 90 * sample_rate {
+                break;
+            }
+            let frequency = (n * mains) as f64;
+            canceller
+                .harmonics
+                .push(Harmonic::new(frequency, sample_rate as f64))
+                .ok();
+        }
+        canceller
+    }
+
+    // Estimated amplitude of the removed interference in input units
+    pub fn amplitude(&self) -> u32 {
+        let power: u64 = self.harmonics.iter().map(|h| h.power()).sum();
+        sqrt(power)
+    }
+}
+
+impl Filter for PowerLineCanceller {
+    fn process(&mut self, sample: i32) -> i32 {
+        let estimate: i32 = self.harmonics.iter().map(|h| h.estimate()).sum();
+        let cleaned = sample - estimate;
+
+        let slope = (cleaned - self.previous).abs();
+        self.previous = cleaned;
+        let step = if slope < self.slope_limit {
+            PowerLineCanceller::STEP_BITS
+        } else {
+            PowerLineCanceller::STEP_BITS + PowerLineCanceller::SLOW_BITS
+        };
+        for harmonic in self.harmonics.iter_mut() {
+            harmonic.adapt(cleaned, step);
+            harmonic.rotate();
+        }
+        self.track();
+        cleaned
+    }
+}
+
+impl PowerLineCanceller {
+    fn track(&mut self) {
+        let tracking = &mut self.tracking;
+        tracking.count += 1;
+        if tracking.count < tracking.period {
+            return;
+        }
+        tracking.count = 0;
+        let fundamental = match self.harmonics.first() {
+            Some(fundamental) => fundamental.amplitude(),
+            None => return,
+        };
+        let previous = core::mem::replace(&mut tracking.weights, fundamental);
+
+        let dot = previous.0 * fundamental.0 + previous.1 * fundamental.1;
+        let cross = previous.0 * fundamental.1 - previous.1 * fundamental.0;
+        if dot < tracking.min_power {
+            return;
+        }
+        // Weights rotate against the reference by the frequency error, correct half of it
+        let rotation = (cross << 16) / dot;
+        let correction =
+            -(rotation << (Harmonic::FRACTION_BITS - 16)) / (2 * tracking.period as i64);
+        let offset = (tracking.offset as i64 + correction)
+            .clamp(-tracking.max_offset as i64, tracking.max_offset as i64);
+        let correction = offset - tracking.offset as i64;
+        tracking.offset = offset as i32;
+        for (n, harmonic) in self.harmonics.iter_mut().enumerate() {
+            harmonic.retune(correction * (n as i64 + 1));
+        }
+    }
+}
+
+struct Tracking {
+    period: u32,
+    count: u32,
+    // Fundamental weights at the end of the previous period
+    weights: (i64, i64),
+    // Accumulated correction of the fundamental in Q30 radians per sample
+    offset: i32,
+    max_offset: i32,
+    min_power: i64,
+}
+
+struct Harmonic {
+    // Reference phasor in Q30
+    phasor: (i32, i32),
+    // Rotation per sample in Q30
+    step: (i32, i32),
+    weights: (i32, i32),
+}
+
+impl Harmonic {
+    const FRACTION_BITS: u32 = 30;
+    const ONE: i64 = 1 << Harmonic::FRACTION_BITS;
+    // Reference used by LMS is in Q15
+    const REFERENCE_SHIFT: u32 = Harmonic::FRACTION_BITS - 15;
+
+    fn new(frequency: f64, sample_rate: f64) -> Self {
+        let omega = 2.0 * PI * frequency / sample_rate;
+        let one = Harmonic::ONE as f64;
+        Harmonic {
+            phasor: (Harmonic::ONE as i32, 0),
+            step: (
+                round(cos(omega) * one) as i32,
+                round(sin(omega) * one) as i32,
+            ),
+            weights: (0, 0),
+        }
+    }
+
+    fn reference(&self) -> (i64, i64) {
+        (
+            (self.phasor.0 >> Harmonic::REFERENCE_SHIFT) as i64,
+            (self.phasor.1 >> Harmonic::REFERENCE_SHIFT) as i64,
+        )
+    }
+
+    fn estimate(&self) -> i32 {
+        let (c, s) = self.reference();
+        let sum = self.weights.0 as i64 * c + self.weights.1 as i64 * s;
+        (sum >> (15 + PowerLineCanceller::WEIGHT_BITS)) as i32
+    }
+
+    fn adapt(&mut self, error: i32, step: u32) {
+        let (c, s) = self.reference();
+        let shift = 15 + step - PowerLineCanceller::WEIGHT_BITS;
+        self.weights.0 += ((error as i64 * c) >> shift) as i32;
+        self.weights.1 += ((error as i64 * s) >> shift) as i32;
+    }
+
+    fn rotate(&mut self) {
+        self.phasor = Harmonic::multiply(self.phasor, self.step);
+    }
+
+    // Changes the rotation per sample by `delta` Q30 radians
+    fn retune(&mut self, delta: i64) {
+        let sin = delta;
+        let cos = Harmonic::ONE - ((delta * delta) >> (Harmonic::FRACTION_BITS + 1));
+        self.step = Harmonic::multiply(self.step, (cos as i32, sin as i32));
+    }
+
+    // Complex multiplication of Q30 unit phasors
+    fn multiply(a: (i32, i32), b: (i32, i32)) -> (i32, i32) {
+        let (ac, as_) = (a.0 as i64, a.1 as i64);
+        let (bc, bs) = (b.0 as i64, b.1 as i64);
+        let (c, s) = (
+            (ac * bc - as_ * bs) >> Harmonic::FRACTION_BITS,
+            (as_ * bc + ac * bs) >> Harmonic::FRACTION_BITS,
+        );
+        // One Newton step towards unit magnitude against accumulated rounding errors
+        let magnitude = (c * c + s * s) >> Harmonic::FRACTION_BITS;
+        let gain = (3 * Harmonic::ONE - magnitude) / 2;
+        (
+            ((c * gain) >> Harmonic::FRACTION_BITS) as i32,
+            ((s * gain) >> Harmonic::FRACTION_BITS) as i32,
+        )
+    }
+
+    // Weights in input units
+    fn amplitude(&self) -> (i64, i64) {
+        (
+            (self.weights.0 >> PowerLineCanceller::WEIGHT_BITS) as i64,
+            (self.weights.1 >> PowerLineCanceller::WEIGHT_BITS) as i64,
+        )
+    }
+
+    // Squared amplitude in input units
+    fn power(&self) -> u64 {
+        let (wc, ws) = self.amplitude();
+        (wc * wc + ws * ws) as u64
+    }
+}
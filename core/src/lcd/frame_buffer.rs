@@ -0,0 +1,120 @@
+use core::convert::Infallible;
+use embedded_graphics::drawable::{Drawable, Pixel};
+use embedded_graphics::geometry::Size;
+use embedded_graphics::pixelcolor::{Rgb565, RgbColor};
+use embedded_graphics::DrawTarget;
+
+use crate::display::{Layout, Orientation};
+use crate::lcd::Lcd;
+
+// In-memory panel for running the display on the host.
+//
+// Drawing goes to the memory of the controller, the screen shows it through the
+// vertical scroll like the ILI controllers do. The scroll moves the area between the
+// fixed ones at the start and the end of the long side, `pixel` returns what is seen.
+pub struct FrameBuffer {
+    memory: [Rgb565; FrameBuffer::MAX_PIXELS],
+    width: u16,
+    height: u16,
+    scroll_along_x: bool,
+    // Fixed areas at the start and the end of the scroll axis
+    fixed: (u16, u16),
+    offset: u16,
+}
+
+impl FrameBuffer {
+    // The largest supported panel
+    const MAX_PIXELS: usize = 480 * 320;
+
+    pub fn new(layout: &Layout) -> Self {
+        let (width, height) = layout.size();
+        FrameBuffer {
+            memory: [Rgb565::BLACK; FrameBuffer::MAX_PIXELS],
+            width: width as u16,
+            height: height as u16,
+            scroll_along_x: layout.orientation() == Orientation::Landscape,
+            fixed: layout.scroll_offsets(),
+            offset: 0,
+        }
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    // Color on the screen at the position
+    pub fn pixel(&self, x: u16, y: u16) -> Rgb565 {
+        let (x, y) = if self.scroll_along_x {
+            (self.scrolled(x, self.width), y)
+        } else {
+            (x, self.scrolled(y, self.height))
+        };
+        self.memory[y as usize * self.width as usize + x as usize]
+    }
+
+    // Position in the memory shown at `position` of the scroll axis
+    fn scrolled(&self, position: u16, len: u16) -> u16 {
+        let (start, end) = self.fixed;
+        let area = len - start - end;
+        if position < start || position >= len - end {
+            position
+        } else {
+            start + (position - start + self.offset) % area
+        }
+    }
+
+    fn scroll_area(&self) -> u16 {
+        let len = if self.scroll_along_x {
+            self.width
+        } else {
+            self.height
+        };
+        len - self.fixed.0 - self.fixed.1
+    }
+}
+
+impl DrawTarget<Rgb565> for FrameBuffer {
+    type Error = Infallible;
+
+    fn draw_pixel(&mut self, pixel: Pixel<Rgb565>) -> Result<(), Self::Error> {
+        let Pixel(point, color) = pixel;
+        // Pixels outside of the panel are clipped like by the controller
+        if point.x >= 0
+            && point.y >= 0
+            && point.x < self.width as i32
+            && point.y < self.height as i32
+        {
+            self.memory[point.y as usize * self.width as usize + point.x as usize] = color;
+        }
+        Ok(())
+    }
+
+    fn size(&self) -> Size {
+        Size::new(self.width as u32, self.height as u32)
+    }
+}
+
+impl Lcd for FrameBuffer {
+    type Error = Infallible;
+
+    fn clear(&mut self, color: Rgb565) -> Result<(), Self::Error> {
+        for pixel in self.memory.iter_mut() {
+            *pixel = color;
+        }
+        Ok(())
+    }
+
+    fn draw<D: Drawable<Rgb565>>(&mut self, drawable: D) -> Result<(), Self::Error> {
+        drawable.draw(self)
+    }
+
+    fn scroll(&mut self, num_of_lines: u16) -> Result<(), Self::Error> {
+        self.offset =
+            ((self.offset as u32 + num_of_lines as u32) % self.scroll_area() as u32) as u16;
+        Ok(())
+    }
+}
@@ -1,19 +1,15 @@
 use embedded_graphics::pixelcolor::Rgb565;
 use embedded_graphics::prelude::Drawable;
 
-mod adc;
-mod helper;
-mod lcd;
-mod timers;
+mod frame_buffer;
 
-pub use adc::AdcConfig;
-pub use helper::*;
-pub use lcd::IliError;
-pub use timers::{BeatTimer, FrameTimer};
+pub use frame_buffer::FrameBuffer;
 
 pub trait Lcd {
     type Error;
     fn clear(&mut self, color: Rgb565) -> Result<(), Self::Error>;
     fn draw<D: Drawable<Rgb565>>(&mut self, drawable: D) -> Result<(), Self::Error>;
+    // Panels without a vertical scroll area may do nothing here and be used with
+    // `RenderMode::Sweep`
     fn scroll(&mut self, num_of_lines: u16) -> Result<(), Self::Error>;
 }
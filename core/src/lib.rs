@@ -0,0 +1,19 @@
+#![cfg_attr(not(test), no_std)]
+// heapless 0.6 bounds its containers by the ArrayLength of generic-array 0.14, which
+// is deprecated in its latest patch releases
+#![allow(deprecated)]
+
+pub mod analysis;
+pub mod display;
+pub mod ecgsyn;
+pub mod error;
+pub mod filter;
+pub mod lcd;
+pub mod sampler;
+pub mod settings;
+pub mod ui;
+pub mod wfdb;
+
+mod math;
+
+pub type Buffer = [u16; 4];
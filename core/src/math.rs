@@ -0,0 +1,72 @@
+// Minimal math helpers, `core` does not provide them. The floating point ones are only
+// used when filter coefficients are computed and by the synthetic ECG, never per sample
+// by the analyses.
+
+pub const PI: f64 = core::f64::consts::PI;
+
+pub fn sin(x: f64) -> f64 {
+    // Reduce to [-PI, PI] where the Taylor series converges quickly
+    let mut x = x % (2.0 * PI);
+    if x > PI {
+        x -= 2.0 * PI;
+    } else if x < -PI {
+        x += 2.0 * PI;
+    }
+    let mut term = x;
+    let mut sum = x;
+    for n in 1..12 {
+        let n = n as f64;
+        term *= -x * x / ((2.0 * n) * (2.0 * n + 1.0));
+        sum += term;
+    }
+    sum
+}
+
+pub fn cos(x: f64) -> f64 {
+    sin(x + PI / 2.0)
+}
+
+pub fn exp(x: f64) -> f64 {
+    // e^x = 2^k * e^r with |r| <= ln(2) / 2
+    let k = round(x / core::f64::consts::LN_2);
+    let r = x - k as f64 * core::f64::consts::LN_2;
+    let mut term = 1.0;
+    let mut sum = 1.0;
+    for n in 1..12 {
+        term *= r / n as f64;
+        sum += term;
+    }
+    if k < -1022 {
+        0.0
+    } else {
+        sum * f64::from_bits(((k + 1023).min(2046) as u64) << 52)
+    }
+}
+
+pub fn round(x: f64) -> i64 {
+    if x < 0.0 {
+        (x - 0.5) as i64
+    } else {
+        (x + 0.5) as i64
+    }
+}
+
+pub fn sqrt(value: u64) -> u32 {
+    // Bitwise integer square root, rounds down
+    let mut remainder = value;
+    let mut root = 0u64;
+    let mut bit = 1u64 << 62;
+    while bit > value {
+        bit >>= 2;
+    }
+    while bit != 0 {
+        if remainder >= root + bit {
+            remainder -= root + bit;
+            root = (root >> 1) + bit;
+        } else {
+            root >>= 1;
+        }
+        bit >>= 2;
+    }
+    root as u32
+}
@@ -0,0 +1,104 @@
+use heapless::spsc::{Producer, SingleCore};
+use heapless::ArrayLength;
+
+use crate::error::Error;
+use crate::error::Result;
+use crate::filter::Filter;
+use crate::Buffer;
+
+// Output of the front-end without signal, its reference at half of the 3.3 V supply
+pub const BASELINE_MV: i32 = 1650;
+pub(crate) const SUPPLY_MV: i32 = 3300;
+
+pub struct Sampler<'a, LEN, F>
+where
+    LEN: ArrayLength<u16>,
+    F: Filter,
+{
+    producer: Producer<'a, u16, LEN, u8, SingleCore>,
+    filter: F,
+    buffer: &'static Buffer,
+    first_half: bool,
+    input: u16,
+    calibration: u32,
+    full_scale: u16,
+}
+
+impl<'a, LEN, F> Sampler<'a, LEN, F>
+where
+    LEN: ArrayLength<u16>,
+    F: Filter,
+{
+    pub fn new(
+        buffer: &'static Buffer,
+        producer: Producer<'a, u16, LEN, u8, SingleCore>,
+        filter: F,
+        vref_calibration: u16,
+        full_scale: u16,
+    ) -> Self {
+        // 3V * 1000 to prevent floating math
+        let calibration = vref_calibration as u32 * 3000;
+        Sampler {
+            producer,
+            filter,
+            buffer,
+            calibration,
+            full_scale,
+            first_half: true,
+            input: 0,
+        }
+    }
+
+    pub fn sample<LCDER>(&mut self) -> Result<u16, LCDER> {
+        let (vref, input) = self.get_raw_data();
+        self.first_half ^= true;
+        self.input = input;
+        let sample = self.convert(vref, input);
+        self.push(sample)
+    }
+
+    // Takes a sample in mV from another source in place of the ADC input, e.g. the
+    // synthetic ECG, the raw code is what the ADC would read
+    pub fn inject<LCDER>(&mut self, sample: u16) -> Result<u16, LCDER> {
+        let (vref, _) = self.get_raw_data();
+        self.first_half ^= true;
+        let v_ref = self.calibration / vref as u32;
+        let input = sample as u32 * self.full_scale as u32 / v_ref;
+        self.input = input.min(self.full_scale as u32) as u16;
+        self.push(sample)
+    }
+
+    // Raw ADC code of the last sample
+    pub fn input(&self) -> u16 {
+        self.input
+    }
+
+    pub fn filter(&self) -> &F {
+        &self.filter
+    }
+
+    // Replaces the filter when the settings change
+    pub fn set_filter(&mut self, filter: F) {
+        self.filter = filter;
+    }
+
+    fn push<LCDER>(&mut self, sample: u16) -> Result<u16, LCDER> {
+        let sample = self.filter.process(sample as i32).clamp(0, u16::MAX as i32) as u16;
+        self.producer.enqueue(sample).map_err(|_| Error::Queue)?;
+        Ok(sample)
+    }
+
+    fn get_raw_data(&self) -> (u16, u16) {
+        if self.first_half {
+            (self.buffer[1], self.buffer[0])
+        } else {
+            (self.buffer[3], self.buffer[2])
+        }
+    }
+
+    fn convert(&self, measured_vref: u16, measured_input: u16) -> u16 {
+        let v_ref = self.calibration / measured_vref as u32;
+        let sample = (v_ref * measured_input as u32) / self.full_scale as u32;
+        sample as u16
+    }
+}
@@ -0,0 +1,294 @@
+use crate::analysis::rhythm::RhythmConfig;
+use crate::display::{Gain, RenderMode, SweepSpeed, Theme};
+use crate::filter::{FilterMode, MainsFrequency, MainsRejection};
+use crate::ui::Setting;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AutoScale {
+    Off,
+    On,
+    // Keeps the last fit of the auto-scale
+    Locked,
+}
+
+impl AutoScale {
+    pub fn name(self) -> &'static str {
+        match self {
+            AutoScale::Off => "OFF",
+            AutoScale::On => "ON",
+            AutoScale::Locked => "LOCKED",
+        }
+    }
+}
+
+// Everything that can be changed in the settings menu and survives a power cycle
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Settings {
+    pub filter: FilterMode,
+    pub mains: MainsFrequency,
+    pub rejection: MainsRejection,
+    pub sweep_speed: SweepSpeed,
+    pub gain: Gain,
+    pub auto_scale: AutoScale,
+    pub render_mode: RenderMode,
+    pub theme: Theme,
+    // Alarm limits in BPM, the severe limits follow at a fixed distance
+    pub bradycardia: u8,
+    pub tachycardia: u8,
+}
+
+impl Settings {
+    const BRADYCARDIA: [u8; 5] = [40, 45, 50, 55, 60];
+    const TACHYCARDIA: [u8; 5] = [100, 110, 120, 130, 140];
+    const SEVERE_BRADYCARDIA_BPM: u8 = 10;
+    const SEVERE_TACHYCARDIA_BPM: u8 = 30;
+
+    // Steps the setting to its next value
+    pub fn change(&mut self, setting: Setting) {
+        match setting {
+            Setting::SweepSpeed => self.sweep_speed = self.sweep_speed.next(),
+            Setting::Gain => self.gain = self.gain.next(),
+            Setting::AutoScale => {
+                self.auto_scale = match self.auto_scale {
+                    AutoScale::Off => AutoScale::On,
+                    AutoScale::On => AutoScale::Locked,
+                    AutoScale::Locked => AutoScale::Off,
+                }
+            }
+            Setting::RenderMode => {
+                self.render_mode = match self.render_mode {
+                    RenderMode::Scroll => RenderMode::Sweep,
+                    RenderMode::Sweep => RenderMode::Scroll,
+                }
+            }
+            Setting::Filter => self.filter = self.filter.next(),
+            Setting::Mains => self.mains = self.mains.next(),
+            Setting::Rejection => self.rejection = self.rejection.next(),
+            Setting::Bradycardia => {
+                self.bradycardia = Settings::next_limit(&Settings::BRADYCARDIA, self.bradycardia)
+            }
+            Setting::Tachycardia => {
+                self.tachycardia = Settings::next_limit(&Settings::TACHYCARDIA, self.tachycardia)
+            }
+            Setting::Theme => self.theme = self.theme.next(),
+        }
+    }
+
+    pub fn rhythm_config(&self) -> RhythmConfig {
+        RhythmConfig {
+            bradycardia: self.bradycardia as u16,
+            severe_bradycardia: (self.bradycardia - Settings::SEVERE_BRADYCARDIA_BPM) as u16,
+            tachycardia: self.tachycardia as u16,
+            severe_tachycardia: (self.tachycardia + Settings::SEVERE_TACHYCARDIA_BPM) as u16,
+            ..RhythmConfig::default()
+        }
+    }
+
+    // The filter has to be rebuilt when any of these change
+    pub fn same_filter(&self, other: &Settings) -> bool {
+        self.filter == other.filter
+            && self.mains == other.mains
+            && self.rejection == other.rejection
+    }
+
+    // Record of the current layout, see `Record`
+    pub fn encode(&self) -> [u8; Record::SIZE] {
+        let payload = [
+            match self.filter {
+                FilterMode::Monitor => 0,
+                FilterMode::Diagnostic => 1,
+            },
+            match self.mains {
+                MainsFrequency::Hz50 => 0,
+                MainsFrequency::Hz60 => 1,
+            },
+            match self.rejection {
+                MainsRejection::Notch => 0,
+                MainsRejection::Adaptive => 1,
+            },
+            match self.sweep_speed {
+                SweepSpeed::Mm12_5 => 0,
+                SweepSpeed::Mm25 => 1,
+                SweepSpeed::Mm50 => 2,
+            },
+            match self.gain {
+                Gain::Mm5 => 0,
+                Gain::Mm10 => 1,
+                Gain::Mm20 => 2,
+            },
+            match self.auto_scale {
+                AutoScale::Off => 0,
+                AutoScale::On => 1,
+                AutoScale::Locked => 2,
+            },
+            match self.render_mode {
+                RenderMode::Scroll => 0,
+                RenderMode::Sweep => 1,
+            },
+            match self.theme {
+                Theme::Dark => 0,
+                Theme::Paper => 1,
+                Theme::Night => 2,
+            },
+            self.bradycardia,
+            self.tachycardia,
+        ];
+        let mut record = [Record::ERASED; Record::SIZE];
+        record[0..2].copy_from_slice(&Record::MAGIC.to_le_bytes());
+        record[2] = Record::VERSION;
+        record[3] = payload.len() as u8;
+        let end = Record::HEADER + payload.len();
+        record[Record::HEADER..end].copy_from_slice(&payload);
+        let crc = crc16(&record[..end]);
+        record[end..end + 2].copy_from_slice(&crc.to_le_bytes());
+        record
+    }
+
+    // Defaults if the record is missing or corrupted
+    pub fn decode(record: &[u8]) -> (Settings, Restored) {
+        match Settings::decode_record(record) {
+            Some((settings, Record::VERSION)) => (settings, Restored::Current),
+            Some((settings, _)) => (settings, Restored::Migrated),
+            None => (Settings::default(), Restored::Defaults),
+        }
+    }
+
+    fn decode_record(record: &[u8]) -> Option<(Settings, u8)> {
+        if record.len() < Record::HEADER + 2 {
+            return None;
+        }
+        if u16::from_le_bytes([record[0], record[1]]) != Record::MAGIC {
+            return None;
+        }
+        let version = record[2];
+        let end = Record::HEADER + record[3] as usize;
+        if end + 2 > record.len() {
+            return None;
+        }
+        let crc = u16::from_le_bytes([record[end], record[end + 1]]);
+        if crc != crc16(&record[..end]) {
+            return None;
+        }
+        let payload = &record[Record::HEADER..end];
+
+        // Layouts only ever append fields. Fields missing in a record of an older
+        // version keep their defaults, unknown values of a newer one are ignored.
+        let mut settings = Settings::default();
+        let field = |index: usize| payload.get(index).copied();
+        match field(0) {
+            Some(0) => settings.filter = FilterMode::Monitor,
+            Some(1) => settings.filter = FilterMode::Diagnostic,
+            _ => {}
+        }
+        match field(1) {
+            Some(0) => settings.mains = MainsFrequency::Hz50,
+            Some(1) => settings.mains = MainsFrequency::Hz60,
+            _ => {}
+        }
+        match field(2) {
+            Some(0) => settings.rejection = MainsRejection::Notch,
+            Some(1) => settings.rejection = MainsRejection::Adaptive,
+            _ => {}
+        }
+        match field(3) {
+            Some(0) => settings.sweep_speed = SweepSpeed::Mm12_5,
+            Some(1) => settings.sweep_speed = SweepSpeed::Mm25,
+            Some(2) => settings.sweep_speed = SweepSpeed::Mm50,
+            _ => {}
+        }
+        match field(4) {
+            Some(0) => settings.gain = Gain::Mm5,
+            Some(1) => settings.gain = Gain::Mm10,
+            Some(2) => settings.gain = Gain::Mm20,
+            _ => {}
+        }
+        // A lock only holds a fit made since power-on
+        match field(5) {
+            Some(0) => settings.auto_scale = AutoScale::Off,
+            Some(1) | Some(2) => settings.auto_scale = AutoScale::On,
+            _ => {}
+        }
+        match field(6) {
+            Some(0) => settings.render_mode = RenderMode::Scroll,
+            Some(1) => settings.render_mode = RenderMode::Sweep,
+            _ => {}
+        }
+        match field(7) {
+            Some(0) => settings.theme = Theme::Dark,
+            Some(1) => settings.theme = Theme::Paper,
+            Some(2) => settings.theme = Theme::Night,
+            _ => {}
+        }
+        if let Some(bpm) = field(8).filter(|bpm| Settings::BRADYCARDIA.contains(bpm)) {
+            settings.bradycardia = bpm;
+        }
+        if let Some(bpm) = field(9).filter(|bpm| Settings::TACHYCARDIA.contains(bpm)) {
+            settings.tachycardia = bpm;
+        }
+        Some((settings, version))
+    }
+
+    fn next_limit(limits: &[u8], current: u8) -> u8 {
+        let position = limits.iter().position(|limit| *limit == current);
+        limits[position.map_or(0, |position| (position + 1) % limits.len())]
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let rhythm = RhythmConfig::default();
+        Settings {
+            filter: FilterMode::Monitor,
+            mains: MainsFrequency::Hz50,
+            rejection: MainsRejection::Adaptive,
+            sweep_speed: SweepSpeed::Mm25,
+            gain: Gain::Mm10,
+            auto_scale: AutoScale::Off,
+            render_mode: RenderMode::Scroll,
+            theme: Theme::Dark,
+            bradycardia: rhythm.bradycardia as u8,
+            tachycardia: rhythm.tachycardia as u8,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Restored {
+    Current,
+    // Read from an older layout, should be written again
+    Migrated,
+    // Missing or corrupted record
+    Defaults,
+}
+
+// Layout of the stored settings, little endian:
+//
+// | magic u16 | version u8 | payload length u8 | payload | CRC-16 of all before |
+//
+// The payload has one byte per field in the order of `Settings::encode`. The record
+// is padded by the erased value to whole double words, the unit of flash programming.
+pub struct Record;
+
+impl Record {
+    pub const SIZE: usize = 24;
+    const MAGIC: u16 = 0xec61;
+    const VERSION: u8 = 1;
+    const HEADER: usize = 4;
+    const ERASED: u8 = 0xff;
+}
+
+// CRC-16/CCITT-FALSE
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xffff;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
@@ -0,0 +1,84 @@
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Key {
+    Next,
+    Select,
+    // The third button is optional, a long press of select does the same
+    Back,
+}
+
+impl Key {
+    const ALL: [Key; 3] = [Key::Next, Key::Select, Key::Back];
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Press {
+    // Reported on release
+    Short(Key),
+    // Reported once while the button is still held
+    Long(Key),
+}
+
+#[derive(Copy, Clone)]
+struct State {
+    down: bool,
+    // Consecutive readings that differ from `down`
+    changes: u8,
+    // Ticks since the press, saturating
+    held: u16,
+    long: bool,
+}
+
+// Debouncing and long-press detection of buttons polled at a fixed rate.
+//
+// A button changes state after two equal readings that differ from the current
+// state, so at 30 Hz the contacts have to settle within 33 ms.
+pub struct Buttons {
+    long_press: u16,
+    states: [State; 3],
+}
+
+impl Buttons {
+    const DEBOUNCE_TICKS: u8 = 2;
+    const LONG_PRESS_MS: u32 = 800;
+
+    pub fn new(tick_rate: u32) -> Self {
+        let state = State {
+            down: false,
+            changes: 0,
+            held: 0,
+            long: false,
+        };
+        Buttons {
+            long_press: (Buttons::LONG_PRESS_MS * tick_rate / 1000).max(1) as u16,
+            states: [state; 3],
+        }
+    }
+
+    // Takes the raw state of the next, select and back buttons, true if pressed
+    pub fn update(&mut self, down: [bool; 3]) -> Option<Press> {
+        let mut press = None;
+        for ((state, down), key) in self.states.iter_mut().zip(down.iter()).zip(Key::ALL.iter()) {
+            if *down != state.down {
+                state.changes += 1;
+            } else {
+                state.changes = 0;
+            }
+            if state.changes >= Buttons::DEBOUNCE_TICKS {
+                state.down = *down;
+                state.changes = 0;
+                if !state.down && !state.long {
+                    press = press.or(Some(Press::Short(*key)));
+                }
+                state.held = 0;
+                state.long = false;
+            } else if state.down {
+                state.held = state.held.saturating_add(1);
+                if state.held >= self.long_press && !state.long {
+                    state.long = true;
+                    press = press.or(Some(Press::Long(*key)));
+                }
+            }
+        }
+        press
+    }
+}
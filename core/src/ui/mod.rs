@@ -0,0 +1,226 @@
+use crate::display::TrendSpan;
+
+pub use buttons::{Buttons, Key, Press};
+
+mod buttons;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Screen {
+    Waveform,
+    Trend(TrendSpan),
+    Hrv,
+    Settings,
+    EventLog,
+    Recording,
+}
+
+impl Screen {
+    // Order in which the next button steps through the screens
+    const ALL: [Screen; 6] = [
+        Screen::Waveform,
+        Screen::Trend(TrendSpan::Minutes30),
+        Screen::Hrv,
+        Screen::Settings,
+        Screen::EventLog,
+        Screen::Recording,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Screen::Waveform => "ECG",
+            Screen::Trend(span) => span.name(),
+            Screen::Hrv => "HRV",
+            Screen::Settings => "SETTINGS",
+            Screen::EventLog => "EVENTS",
+            Screen::Recording => "RECORDING",
+        }
+    }
+
+    // Number of items that can take the focus
+    pub fn items(self) -> u8 {
+        match self {
+            // The focus on the waveform freezes the trace
+            Screen::Waveform | Screen::Trend(_) | Screen::Recording => 1,
+            Screen::Settings => Setting::ALL.len() as u8,
+            Screen::Hrv | Screen::EventLog => 0,
+        }
+    }
+
+    fn position(self) -> usize {
+        match self {
+            Screen::Waveform => 0,
+            Screen::Trend(_) => 1,
+            Screen::Hrv => 2,
+            Screen::Settings => 3,
+            Screen::EventLog => 4,
+            Screen::Recording => 5,
+        }
+    }
+
+    fn step(self, forward: bool) -> Screen {
+        let len = Screen::ALL.len();
+        let position = if forward {
+            self.position() + 1
+        } else {
+            self.position() + len - 1
+        };
+        Screen::ALL[position % len]
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Setting {
+    SweepSpeed,
+    Gain,
+    AutoScale,
+    RenderMode,
+    Filter,
+    Mains,
+    Rejection,
+    Bradycardia,
+    Tachycardia,
+    Theme,
+}
+
+impl Setting {
+    // Rows of the settings screen
+    pub const ALL: [Setting; 10] = [
+        Setting::SweepSpeed,
+        Setting::Gain,
+        Setting::AutoScale,
+        Setting::RenderMode,
+        Setting::Filter,
+        Setting::Mains,
+        Setting::Rejection,
+        Setting::Bradycardia,
+        Setting::Tachycardia,
+        Setting::Theme,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Setting::SweepSpeed => "SWEEP",
+            Setting::Gain => "GAIN",
+            Setting::AutoScale => "AUTO SCALE",
+            Setting::RenderMode => "RENDERING",
+            Setting::Filter => "FILTER",
+            Setting::Mains => "MAINS",
+            Setting::Rejection => "REJECTION",
+            Setting::Bradycardia => "BRADY <",
+            Setting::Tachycardia => "TACHY >",
+            Setting::Theme => "THEME",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Action {
+    // Switch to the screen and repaint it
+    Show(Screen),
+    // Move the focus within the current screen
+    Focus(Option<u8>),
+    // Step to the next value of the setting
+    Change(Setting),
+    StartStopRecording,
+    // Move the frozen trace by quarters of the frame, negative back in time
+    Pan(i8),
+    // Put a user event marker on the trace
+    Mark,
+}
+
+// Screen navigation with a focus model.
+//
+// Without focus the next button steps through the screens, long press goes back,
+// select gives the focus to the first item of the screen and back returns to the
+// waveform. With focus next moves between the items, select activates the focused
+// one and back releases the focus. A long press of select works as back.
+//
+// Back on the waveform puts a user event marker on the trace. The focus on the
+// waveform freezes the trace, next pans back in time by a quarter of
+// the frame, long press by the whole frame, and select pans forward.
+pub struct Navigator {
+    screen: Screen,
+    focus: Option<u8>,
+}
+
+impl Navigator {
+    pub fn new() -> Self {
+        Navigator {
+            screen: Screen::Waveform,
+            focus: None,
+        }
+    }
+
+    pub fn screen(&self) -> Screen {
+        self.screen
+    }
+
+    pub fn focus(&self) -> Option<u8> {
+        self.focus
+    }
+
+    pub fn handle(&mut self, press: Press) -> Option<Action> {
+        let back = matches!(press, Press::Short(Key::Back) | Press::Long(Key::Select));
+        match self.focus {
+            None if back && self.screen == Screen::Waveform => Some(Action::Mark),
+            None if back => self.show(Screen::Waveform),
+            None => match press {
+                Press::Short(Key::Next) => self.show(self.screen.step(true)),
+                Press::Long(Key::Next) => self.show(self.screen.step(false)),
+                Press::Short(Key::Select) if self.screen.items() > 0 => self.set_focus(Some(0)),
+                _ => None,
+            },
+            Some(_) if back => self.set_focus(None),
+            Some(_) if self.screen == Screen::Waveform => match press {
+                Press::Short(Key::Next) => Some(Action::Pan(-1)),
+                Press::Long(Key::Next) => Some(Action::Pan(-4)),
+                Press::Short(Key::Select) => Some(Action::Pan(1)),
+                _ => None,
+            },
+            Some(focus) => {
+                let items = self.screen.items();
+                match press {
+                    Press::Short(Key::Next) => self.set_focus(Some((focus + 1) % items)),
+                    Press::Long(Key::Next) => self.set_focus(Some((focus + items - 1) % items)),
+                    Press::Short(Key::Select) => self.activate(focus),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    fn show(&mut self, screen: Screen) -> Option<Action> {
+        if screen == self.screen {
+            return None;
+        }
+        self.screen = screen;
+        self.focus = None;
+        Some(Action::Show(screen))
+    }
+
+    fn set_focus(&mut self, focus: Option<u8>) -> Option<Action> {
+        self.focus = focus;
+        Some(Action::Focus(focus))
+    }
+
+    fn activate(&mut self, item: u8) -> Option<Action> {
+        match self.screen {
+            Screen::Trend(span) => {
+                // The focus stays on the span
+                self.screen = Screen::Trend(span.next());
+                Some(Action::Show(self.screen))
+            }
+            Screen::Settings => Setting::ALL
+                .get(item as usize)
+                .map(|setting| Action::Change(*setting)),
+            Screen::Recording => Some(Action::StartStopRecording),
+            Screen::Waveform | Screen::Hrv | Screen::EventLog => None,
+        }
+    }
+}
+
+impl Default for Navigator {
+    fn default() -> Self {
+        Navigator::new()
+    }
+}
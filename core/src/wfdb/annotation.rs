@@ -0,0 +1,94 @@
+// Label of a sample in an annotation file, `code` is one of the WFDB annotation codes
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Annotation {
+    pub index: u32,
+    pub code: u8,
+}
+
+impl Annotation {
+    pub const NORMAL: u8 = 1;
+    pub const PVC: u8 = 5;
+
+    // Mnemonics of the codes as printed by `rdann`, `?` for unused ones
+    const MNEMONICS: &'static [u8; 42] = b" NLRaVFJASEj/Q~?|?sT*D\"=pB^t+u?![]en@xf()r";
+    // Codes of beats, the others label rhythm changes, noise or waves
+    const BEATS: [u8; 20] = [
+        1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 25, 30, 34, 35, 37, 38, 41,
+    ];
+
+    pub fn is_beat(&self) -> bool {
+        Annotation::BEATS.contains(&self.code)
+    }
+
+    pub fn mnemonic(&self) -> char {
+        Annotation::MNEMONICS
+            .get(self.code as usize)
+            .map_or('?', |mnemonic| *mnemonic as char)
+    }
+}
+
+// Annotations of an `.atr` file in the MIT format. Every label is a 16 bit word with
+// the code in the top 6 bits and the samples since the previous label in the rest,
+// pseudo codes carry longer intervals and additional fields.
+pub struct Annotations<'a> {
+    data: &'a [u8],
+    index: u32,
+}
+
+impl<'a> Annotations<'a> {
+    // Pseudo codes
+    const SKIP: u8 = 59;
+    const NUM: u8 = 60;
+    const SUB: u8 = 61;
+    const CHN: u8 = 62;
+    const AUX: u8 = 63;
+
+    pub fn new(data: &'a [u8]) -> Self {
+        Annotations { data, index: 0 }
+    }
+
+    fn word(&mut self) -> Option<u16> {
+        let word = self.data.get(..2)?;
+        let word = u16::from_le_bytes([word[0], word[1]]);
+        self.data = &self.data[2..];
+        Some(word)
+    }
+}
+
+impl<'a> Iterator for Annotations<'a> {
+    type Item = Annotation;
+
+    fn next(&mut self) -> Option<Annotation> {
+        loop {
+            let word = self.word()?;
+            let code = (word >> 10) as u8;
+            let value = word & 0x3ff;
+            match code {
+                // End of the file
+                0 if value == 0 => {
+                    self.data = &[];
+                    return None;
+                }
+                // The interval follows as 32 bits with the high word first
+                Annotations::SKIP => {
+                    let high = self.word()? as u32;
+                    let low = self.word()? as u32;
+                    self.index = self.index.wrapping_add(high << 16 | low);
+                }
+                Annotations::NUM | Annotations::SUB | Annotations::CHN => {}
+                // Text of `value` bytes padded to an even length
+                Annotations::AUX => {
+                    let len = (value as usize + 1) & !1;
+                    self.data = self.data.get(len..).unwrap_or(&[]);
+                }
+                _ => {
+                    self.index += value as u32;
+                    return Some(Annotation {
+                        index: self.index,
+                        code,
+                    });
+                }
+            }
+        }
+    }
+}
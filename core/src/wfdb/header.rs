@@ -0,0 +1,175 @@
+use heapless::consts::U8;
+use heapless::Vec;
+
+use super::{Format, WfdbError};
+
+// Parsed `.hea` file of a single segment record, the names and units borrow its text
+pub struct Header<'a> {
+    pub name: &'a str,
+    pub sample_rate: u32,
+    // Samples per signal, if known
+    pub len: Option<u32>,
+    signals: Vec<Signal<'a>, U8>,
+}
+
+// One signal of the record, `value` in ADC units is `(value - baseline) / gain` in
+// `units`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Signal<'a> {
+    pub file: &'a str,
+    pub format: Format,
+    // Bytes before the first sample in the file
+    pub offset: usize,
+    // Thousandths of ADC units per physical unit, records may give the gain with decimals
+    pub gain_x1000: u32,
+    pub baseline: i32,
+    pub units: &'a str,
+    pub description: &'a str,
+}
+
+impl<'a> Header<'a> {
+    // Defaults of the WFDB specification for omitted fields
+    const SAMPLE_RATE: f32 = 250.0;
+    const GAIN: f32 = 200.0;
+
+    pub fn parse(text: &'a str) -> Result<Self, WfdbError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+        let (number, record) = lines.next().ok_or(WfdbError::Header(1))?;
+        let error = WfdbError::Header(number);
+        let mut fields = record.split_whitespace();
+        let name = fields.next().ok_or(error)?;
+        // Multi-segment records name the number of segments after a slash
+        if name.contains('/') {
+            return Err(error);
+        }
+        let count: usize = fields
+            .next()
+            .and_then(|count| count.parse().ok())
+            .ok_or(error)?;
+        // The counter frequency and base counter value may follow the sample rate
+        let sample_rate = match fields.next() {
+            Some(field) => field
+                .split(&['/', '('][..])
+                .next()
+                .and_then(|rate| rate.parse::<f32>().ok())
+                .filter(|rate| *rate >= 1.0)
+                .ok_or(error)?,
+            None => Header::SAMPLE_RATE,
+        };
+        let len = match fields.next() {
+            Some(field) => Some(field.parse().map_err(|_| error)?),
+            None => None,
+        };
+
+        let mut signals = Vec::new();
+        let mut last = number;
+        for _ in 0..count {
+            let (number, line) = lines.next().ok_or(WfdbError::Header(last + 1))?;
+            let signal = Signal::parse(line, WfdbError::Header(number))?;
+            signals
+                .push(signal)
+                .map_err(|_| WfdbError::Header(number))?;
+            last = number;
+        }
+        Ok(Header {
+            name,
+            sample_rate: (sample_rate + 0.5) as u32,
+            len,
+            signals,
+        })
+    }
+
+    pub fn signals(&self) -> &[Signal<'a>] {
+        &self.signals
+    }
+
+    // Position of the signal among the interleaved signals of its file and their count
+    pub fn frame(&self, channel: usize) -> Result<(usize, usize), WfdbError> {
+        let signal = self
+            .signals
+            .get(channel)
+            .ok_or(WfdbError::Channel(channel))?;
+        let same_file = |other: &&Signal| other.file == signal.file;
+        let position = self.signals[..channel].iter().filter(same_file).count();
+        let count = self.signals.iter().filter(same_file).count();
+        Ok((position, count))
+    }
+}
+
+impl<'a> Signal<'a> {
+    // `file format[xN][:skew][+offset] gain[(baseline)][/units] resolution zero initial
+    // checksum block description`, all but the first two fields can be omitted
+    fn parse(line: &'a str, error: WfdbError) -> Result<Self, WfdbError> {
+        let mut rest = line;
+        let mut field = || {
+            let start = rest.trim_start();
+            let end = start.find(char::is_whitespace).unwrap_or(start.len());
+            rest = &start[end..];
+            Some(&start[..end]).filter(|field| !field.is_empty())
+        };
+        let file = field().ok_or(error)?;
+
+        let spec = field().ok_or(error)?;
+        let digits = spec
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(spec.len());
+        let code = spec[..digits].parse().map_err(|_| error)?;
+        let format = Format::from_code(code).ok_or(WfdbError::Format(code))?;
+        let offset = match spec.find('+') {
+            Some(start) => spec[start + 1..].parse().map_err(|_| error)?,
+            None => 0,
+        };
+
+        let (gain, baseline, units) = match field() {
+            Some(spec) => {
+                let (spec, units) = match spec.find('/') {
+                    Some(slash) => (&spec[..slash], &spec[slash + 1..]),
+                    None => (spec, "mV"),
+                };
+                let (gain, baseline) = match spec.find('(') {
+                    Some(open) => {
+                        let baseline = spec[open + 1..]
+                            .strip_suffix(')')
+                            .and_then(|baseline| baseline.parse().ok())
+                            .ok_or(error)?;
+                        (&spec[..open], Some(baseline))
+                    }
+                    None => (spec, None),
+                };
+                (gain.parse::<f32>().map_err(|_| error)?, baseline, units)
+            }
+            None => (0.0, None, "mV"),
+        };
+        // The ADC zero after the resolution is the default baseline
+        field();
+        let zero = match field() {
+            Some(zero) => zero.parse().map_err(|_| error)?,
+            None => 0,
+        };
+        // Initial value, checksum and block size
+        field();
+        field();
+        field();
+
+        // Zero gain stands for uncalibrated signals with the default gain
+        let gain = if gain > 0.0 { gain } else { Header::GAIN };
+        let gain_x1000 = (gain * 1000.0 + 0.5) as u32;
+        if gain_x1000 == 0 {
+            return Err(error);
+        }
+        Ok(Signal {
+            file,
+            format,
+            offset,
+            gain_x1000,
+            baseline: baseline.unwrap_or(zero),
+            units,
+            description: rest.trim(),
+        })
+    }
+}
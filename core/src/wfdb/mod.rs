@@ -0,0 +1,27 @@
+// Records of the PhysioNet databases in the WFDB format, e.g. the MIT-BIH arrhythmia
+// database, played back instead of the ADC to validate the analyses and the display.
+//
+// The files are given as byte slices, so a record can be read from the disk on the
+// host or included in the firmware.
+
+pub use annotation::{Annotation, Annotations};
+pub use header::{Header, Signal};
+pub use playback::Playback;
+pub use signal::{Format, Samples};
+
+mod annotation;
+mod header;
+mod playback;
+mod signal;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WfdbError {
+    // Malformed line of the header, counted from 1
+    Header(usize),
+    // Storage format of the signal file that is not supported
+    Format(u16),
+    // Signal that is not in the header
+    Channel(usize),
+    // Physical units other than millivolts
+    Units,
+}
@@ -0,0 +1,110 @@
+use heapless::spsc::{Producer, SingleCore};
+use heapless::ArrayLength;
+
+use super::{Header, Samples, WfdbError};
+use crate::display::Scale;
+use crate::error::Error;
+use crate::error::Result;
+use crate::filter::Filter;
+use crate::sampler::{BASELINE_MV, SUPPLY_MV};
+
+// Plays a signal of a record in place of the `Sampler`.
+//
+// The signal is resampled to the sample rate of the device by linear interpolation
+// and amplified like by the front-end, so the samples are in mV at the ADC input with
+// the baseline at the reference of the front-end. Filtered samples are pushed into the
+// queue of the display.
+pub struct Playback<'a, 'b, LEN, F>
+where
+    LEN: ArrayLength<u16>,
+    F: Filter,
+{
+    producer: Producer<'a, u16, LEN, u8, SingleCore>,
+    filter: F,
+    samples: Samples<'b>,
+    gain_x1000: i64,
+    baseline: i32,
+    // Sample rates of the record and of the output
+    from: u32,
+    to: u32,
+    // Position after the `previous` record sample in 1/`to` of the sample interval
+    phase: u32,
+    previous: i32,
+    next: Option<i32>,
+}
+
+impl<'a, 'b, LEN, F> Playback<'a, 'b, LEN, F>
+where
+    LEN: ArrayLength<u16>,
+    F: Filter,
+{
+    pub fn new(
+        header: &Header,
+        channel: usize,
+        data: &'b [u8],
+        producer: Producer<'a, u16, LEN, u8, SingleCore>,
+        filter: F,
+        sample_rate: u32,
+    ) -> core::result::Result<Self, WfdbError> {
+        let mut samples = Samples::new(header, channel, data)?;
+        let signal = header.signals()[channel];
+        if signal.units != "mV" {
+            return Err(WfdbError::Units);
+        }
+        // Starts a whole interval before the first sample
+        let next = samples.next().map(i32::from);
+        Ok(Playback {
+            producer,
+            filter,
+            samples,
+            gain_x1000: signal.gain_x1000 as i64,
+            baseline: signal.baseline,
+            from: header.sample_rate,
+            to: sample_rate,
+            phase: sample_rate,
+            previous: signal.baseline,
+            next,
+        })
+    }
+
+    // Pushes the next sample, `None` once the record is over
+    pub fn sample<LCDER>(&mut self) -> Result<Option<u16>, LCDER> {
+        while self.phase >= self.to {
+            self.previous = match self.next {
+                Some(next) => next,
+                None => return Ok(None),
+            };
+            self.next = self.samples.next().map(i32::from);
+            self.phase -= self.to;
+        }
+        let value = match self.next {
+            Some(next) => {
+                self.previous + (next - self.previous) * self.phase as i32 / self.to as i32
+            }
+            // The last sample is played only if it falls on an output sample
+            None if self.phase == 0 => self.previous,
+            None => return Ok(None),
+        };
+        self.phase += self.from;
+        let sample = self.filter.process(self.convert(value) as i32);
+        let sample = sample.clamp(0, u16::MAX as i32) as u16;
+        self.producer.enqueue(sample).map_err(|_| Error::Queue)?;
+        Ok(Some(sample))
+    }
+
+    pub fn filter(&self) -> &F {
+        &self.filter
+    }
+
+    // Replaces the filter when the settings change
+    pub fn set_filter(&mut self, filter: F) {
+        self.filter = filter;
+    }
+
+    // mV at the ADC input, clipped by the supply like the output of the front-end
+    fn convert(&self, value: i32) -> u16 {
+        let mv =
+            (value - self.baseline) as i64 * Scale::FRONT_END_GAIN as i64 * 1000 / self.gain_x1000;
+        (BASELINE_MV as i64 + mv).clamp(0, SUPPLY_MV as i64) as u16
+    }
+}
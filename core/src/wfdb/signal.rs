@@ -0,0 +1,88 @@
+use super::{Header, WfdbError};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Format {
+    // Pairs of 12 bit samples packed in 3 bytes, the MIT-BIH databases
+    Bits12,
+    // 16 bit little-endian samples
+    Bits16,
+}
+
+impl Format {
+    pub fn from_code(code: u16) -> Option<Format> {
+        match code {
+            212 => Some(Format::Bits12),
+            16 => Some(Format::Bits16),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> u16 {
+        match self {
+            Format::Bits12 => 212,
+            Format::Bits16 => 16,
+        }
+    }
+}
+
+// Samples of one signal in ADC units from the data of its file. The signals of a file
+// are interleaved, a frame holds one sample of each.
+pub struct Samples<'a> {
+    data: &'a [u8],
+    format: Format,
+    position: usize,
+    count: usize,
+    frame: usize,
+}
+
+impl<'a> Samples<'a> {
+    pub fn new(header: &Header, channel: usize, data: &'a [u8]) -> Result<Self, WfdbError> {
+        let (position, count) = header.frame(channel)?;
+        let signal = header.signals()[channel];
+        Ok(Samples {
+            data: data.get(signal.offset..).unwrap_or(&[]),
+            format: signal.format,
+            position,
+            count,
+            frame: 0,
+        })
+    }
+
+    // Samples left in the data, an incomplete last frame is ignored
+    pub fn remaining(&self) -> usize {
+        let frames = match self.format {
+            Format::Bits12 => self.data.len() * 2 / 3 / self.count,
+            Format::Bits16 => self.data.len() / 2 / self.count,
+        };
+        frames.saturating_sub(self.frame)
+    }
+
+    fn decode(&self, index: usize) -> i16 {
+        match self.format {
+            Format::Bits12 => {
+                let bytes = &self.data[index / 2 * 3..];
+                let value = if index & 1 == 0 {
+                    bytes[0] as u16 | (bytes[1] as u16 & 0x0f) << 8
+                } else {
+                    bytes[2] as u16 | (bytes[1] as u16 & 0xf0) << 4
+                };
+                // Sign extension of the 12 bits
+                (value << 4) as i16 >> 4
+            }
+            Format::Bits16 => i16::from_le_bytes([self.data[2 * index], self.data[2 * index + 1]]),
+        }
+    }
+}
+
+impl<'a> Iterator for Samples<'a> {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if self.remaining() == 0 {
+            return None;
+        }
+        let sample = self.decode(self.frame * self.count + self.position);
+        self.frame += 1;
+        Some(sample)
+    }
+}
@@ -0,0 +1,199 @@
+// Synthetic signals shared by the tests
+#![allow(dead_code)]
+
+use std::f64::consts::PI;
+
+use ecg_core::filter::{EcgFilter, Filter, FilterMode, MainsFrequency, MainsRejection};
+
+// Sampler millivolts at the ADC input, 12 bits over 3.3 V
+pub const FULL_SCALE: u16 = 4095;
+const REFERENCE_MV: f64 = 3300.0;
+const BASELINE_MV: f64 = 1500.0;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Beat {
+    Normal,
+    Pvc,
+}
+
+pub struct Recording {
+    pub sample_rate: u32,
+    // Input in millivolts like the sampler converts it, before the filter
+    pub samples: Vec<f64>,
+    // Sample indices of the R peaks
+    pub beats: Vec<(u32, Beat)>,
+}
+
+impl Recording {
+    // Beats follow each other by the R-R intervals in ms, the first one after 0.5 s
+    pub fn new(sample_rate: u32, rhythm: &[(u32, Beat)]) -> Self {
+        let mut time = 0.5;
+        let mut peaks = Vec::new();
+        for (interval, beat) in rhythm {
+            time += *interval as f64 / 1000.0;
+            peaks.push((time, *beat));
+        }
+        let len = ((time + 1.0) * sample_rate as f64) as usize;
+        let mut samples = vec![BASELINE_MV; len];
+        for (index, sample) in samples.iter_mut().enumerate() {
+            let t = index as f64 / sample_rate as f64;
+            for (peak, beat) in &peaks {
+                let d = t - peak;
+                if d.abs() < 0.6 {
+                    *sample += wave(d, *beat);
+                }
+            }
+        }
+        let beats = peaks
+            .iter()
+            .map(|(peak, beat)| ((peak * sample_rate as f64).round() as u32, *beat))
+            .collect();
+        Recording {
+            sample_rate,
+            samples,
+            beats,
+        }
+    }
+
+    // Regular normal beats
+    pub fn regular(sample_rate: u32, interval: u32, beats: usize) -> Self {
+        Recording::new(sample_rate, &vec![(interval, Beat::Normal); beats])
+    }
+
+    pub fn with_mains(mut self, frequency: f64, amplitude: f64) -> Self {
+        let rate = self.sample_rate as f64;
+        for (index, sample) in self.samples.iter_mut().enumerate() {
+            *sample += amplitude * (2.0 * PI * frequency * index as f64 / rate).sin();
+        }
+        self
+    }
+
+    pub fn with_wander(mut self, frequency: f64, amplitude: f64) -> Self {
+        let rate = self.sample_rate as f64;
+        for (index, sample) in self.samples.iter_mut().enumerate() {
+            *sample += amplitude * (2.0 * PI * frequency * index as f64 / rate).sin();
+        }
+        self
+    }
+
+    // Uniform noise of the given peak to peak amplitude over a span in seconds
+    pub fn with_noise(mut self, amplitude: f64, span: (f64, f64)) -> Self {
+        let mut noise = Noise::new(7);
+        let range = self.span(span);
+        for sample in &mut self.samples[range] {
+            *sample += amplitude * noise.next();
+        }
+        self
+    }
+
+    pub fn with_constant(mut self, value: f64, span: (f64, f64)) -> Self {
+        let range = self.span(span);
+        for sample in &mut self.samples[range] {
+            *sample = value;
+        }
+        self
+    }
+
+    // ADC codes of the samples
+    pub fn codes(&self) -> Vec<u16> {
+        self.samples
+            .iter()
+            .map(|sample| {
+                (sample * FULL_SCALE as f64 / REFERENCE_MV).clamp(0.0, FULL_SCALE as f64) as u16
+            })
+            .collect()
+    }
+
+    // Output of the default filter as the detectors get it
+    pub fn filtered(&self) -> Vec<u16> {
+        let mut filter = EcgFilter::new(
+            self.sample_rate,
+            FilterMode::Monitor,
+            MainsFrequency::Hz50,
+            MainsRejection::Adaptive,
+        );
+        self.samples
+            .iter()
+            .map(|sample| {
+                let sample = sample.clamp(0.0, REFERENCE_MV) as i32;
+                filter.process(sample).clamp(0, u16::MAX as i32) as u16
+            })
+            .collect()
+    }
+
+    pub fn seconds(&self, seconds: f64) -> u32 {
+        (seconds * self.sample_rate as f64) as u32
+    }
+
+    fn span(&self, (start, end): (f64, f64)) -> std::ops::Range<usize> {
+        let end = (self.seconds(end) as usize).min(self.samples.len());
+        (self.seconds(start) as usize).min(end)..end
+    }
+}
+
+// P, QRS and T waves as Gaussians around the R peak, the PVC is wide without P wave
+// and with discordant T wave
+fn wave(d: f64, beat: Beat) -> f64 {
+    match beat {
+        Beat::Normal => {
+            gauss(d, -0.18, 0.02, 60.0)
+                + gauss(d, -0.02, 0.008, -50.0)
+                + gauss(d, 0.0, 0.01, 700.0)
+                + gauss(d, 0.025, 0.008, -120.0)
+                + gauss(d, 0.25, 0.04, 150.0)
+        }
+        Beat::Pvc => {
+            gauss(d, 0.0, 0.035, 600.0)
+                + gauss(d, 0.06, 0.03, -250.0)
+                + gauss(d, 0.28, 0.05, -200.0)
+        }
+    }
+}
+
+fn gauss(t: f64, center: f64, width: f64, amplitude: f64) -> f64 {
+    amplitude * (-(t - center).powi(2) / (2.0 * width * width)).exp()
+}
+
+// Deterministic uniform noise in -0.5..0.5
+pub struct Noise(u64);
+
+impl Noise {
+    pub fn new(seed: u64) -> Self {
+        Noise(seed)
+    }
+
+    pub fn next(&mut self) -> f64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) as f64 / (1u64 << 31) as f64 - 0.5
+    }
+}
+
+// Found beats within 150 ms of the reference ones as (matched, missed, extra),
+// the first seconds of learning are skipped
+pub fn match_beats(
+    reference: &[u32],
+    found: &[u32],
+    sample_rate: u32,
+    skip: u32,
+) -> (usize, usize, usize) {
+    let tolerance = (sample_rate * 150 / 1000) as i64;
+    let reference: Vec<u32> = reference
+        .iter()
+        .copied()
+        .filter(|beat| *beat >= skip)
+        .collect();
+    let found: Vec<u32> = found.iter().copied().filter(|beat| *beat >= skip).collect();
+    let near = |a: u32, b: u32| (a as i64 - b as i64).abs() <= tolerance;
+    let matched = reference
+        .iter()
+        .filter(|beat| found.iter().any(|other| near(**beat, *other)))
+        .count();
+    let extra = found
+        .iter()
+        .filter(|beat| !reference.iter().any(|other| near(**beat, *other)))
+        .count();
+    (matched, reference.len() - matched, extra)
+}
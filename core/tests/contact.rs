@@ -0,0 +1,66 @@
+mod common;
+
+use common::{Recording, FULL_SCALE};
+use ecg_core::analysis::contact::{Contact, ContactMonitor, LeadOff};
+
+const SAMPLE_RATE: u32 = 500;
+
+// Contact at every second of the recording, the electrode is off within `off`
+fn monitor(recording: &Recording, off: (f64, f64)) -> Vec<Contact> {
+    let mut monitor = ContactMonitor::new(SAMPLE_RATE, FULL_SCALE);
+    let off = recording.seconds(off.0)..recording.seconds(off.1);
+    let contacts: Vec<Contact> = recording
+        .codes()
+        .into_iter()
+        .zip(recording.filtered())
+        .enumerate()
+        .map(|(index, (code, sample))| monitor.process(code, sample, off.contains(&(index as u32))))
+        .collect();
+    contacts
+        .chunks(SAMPLE_RATE as usize)
+        .map(|second| second[second.len() - 1])
+        .collect()
+}
+
+fn ecg() -> Recording {
+    Recording::regular(SAMPLE_RATE, 800, 40).with_mains(50.0, 20.0)
+}
+
+#[test]
+fn good_contact() {
+    let contacts = monitor(&ecg().with_noise(100.0, (10.0, 20.0)), (0.0, 0.0));
+    assert!(contacts.iter().all(|contact| *contact == Contact::Good));
+}
+
+#[test]
+fn saturation() {
+    let contacts = monitor(&ecg().with_constant(3300.0, (10.0, 15.0)), (0.0, 0.0));
+    assert_eq!(contacts[10], Contact::LeadOff(LeadOff::Saturation));
+    assert_eq!(contacts[14], Contact::LeadOff(LeadOff::Saturation));
+    // Good again after 2 s
+    assert_ne!(contacts[16], Contact::Good);
+    assert_eq!(contacts[18], Contact::Good);
+}
+
+#[test]
+fn flat_line() {
+    let contacts = monitor(&ecg().with_constant(1500.0, (10.0, 15.0)), (0.0, 0.0));
+    assert_eq!(contacts[9], Contact::Good);
+    assert_eq!(contacts[12], Contact::LeadOff(LeadOff::FlatLine));
+    assert_eq!(contacts[19], Contact::Good);
+}
+
+#[test]
+fn noise() {
+    let contacts = monitor(&ecg().with_noise(800.0, (10.0, 15.0)), (0.0, 0.0));
+    assert_eq!(contacts[12], Contact::LeadOff(LeadOff::Noise));
+    assert_eq!(contacts[19], Contact::Good);
+}
+
+#[test]
+fn electrode_input() {
+    let contacts = monitor(&ecg(), (10.0, 12.0));
+    assert_eq!(contacts[10], Contact::LeadOff(LeadOff::Electrode));
+    assert_eq!(contacts[11], Contact::LeadOff(LeadOff::Electrode));
+    assert_eq!(contacts[15], Contact::Good);
+}
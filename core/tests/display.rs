@@ -0,0 +1,378 @@
+mod common;
+
+use std::convert::Infallible;
+use std::thread;
+
+use common::Recording;
+use ecg_core::analysis::event_log::EventLog;
+use ecg_core::analysis::pvc::BeatClass;
+use ecg_core::analysis::session::Session;
+use ecg_core::analysis::trend::HrTrend;
+use ecg_core::display::{
+    Annotation, Display, Layout, Orientation, Panel, RenderMode, Theme, TrendSpan,
+};
+use ecg_core::lcd::{FrameBuffer, Lcd};
+use ecg_core::sampler::BASELINE_MV;
+use ecg_core::settings::Settings;
+use ecg_core::ui::Screen;
+use embedded_graphics::drawable::Pixel;
+use embedded_graphics::pixelcolor::{Rgb565, RgbColor};
+use embedded_graphics::prelude::Point;
+use embedded_graphics::DrawTarget;
+use heapless::consts::U128;
+use heapless::spsc::{Producer, Queue, SingleCore};
+
+const SAMPLE_RATE: u32 = 500;
+// Samples per frame at 30 frames per second
+const CHUNK: usize = 16;
+
+type Screenshot = Vec<Rgb565>;
+
+// The frame buffer does not fit on the default stack of the test threads
+fn run(test: impl FnOnce() + Send + 'static) {
+    thread::Builder::new()
+        .stack_size(32 << 20)
+        .spawn(test)
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+fn feed<'a>(
+    display: &mut Display<'a, U128, FrameBuffer, Infallible>,
+    producer: &mut Producer<'a, u16, U128, u8, SingleCore>,
+    samples: &[u16],
+) {
+    for chunk in samples.chunks(CHUNK) {
+        for sample in chunk {
+            producer.enqueue(*sample).unwrap();
+        }
+        display.frame().unwrap();
+    }
+}
+
+fn screenshot(lcd: &FrameBuffer) -> Screenshot {
+    (0..lcd.height())
+        .flat_map(|y| (0..lcd.width()).map(move |x| (x, y)))
+        .map(|(x, y)| lcd.pixel(x, y))
+        .collect()
+}
+
+// Row with the most yellow pixels, where the flat trace runs
+fn center_row(lcd: &FrameBuffer) -> u16 {
+    (0..lcd.height())
+        .max_by_key(|y| {
+            (0..lcd.width())
+                .filter(|x| lcd.pixel(*x, *y) == Rgb565::YELLOW)
+                .count()
+        })
+        .unwrap()
+}
+
+// Yellow pixels a few rows above and below the center row
+fn away(lcd: &FrameBuffer, center: u16) -> (usize, usize) {
+    let mut above = 0;
+    let mut below = 0;
+    for y in 0..lcd.height() {
+        for x in 0..lcd.width() {
+            if lcd.pixel(x, y) == Rgb565::YELLOW {
+                if y + 3 < center {
+                    above += 1;
+                } else if y > center + 3 {
+                    below += 1;
+                }
+            }
+        }
+    }
+    (above, below)
+}
+
+// Rows between the highest and the lowest yellow pixel
+fn trace_height(lcd: &FrameBuffer) -> u16 {
+    let rows: Vec<u16> = (0..lcd.height())
+        .filter(|y| (0..lcd.width()).any(|x| lcd.pixel(x, *y) == Rgb565::YELLOW))
+        .collect();
+    rows.last().unwrap() - rows[0]
+}
+
+#[test]
+fn draws_the_trace() {
+    run(|| {
+        let samples = Recording::regular(SAMPLE_RATE, 800, 5).filtered();
+        let mut queue: Queue<u16, U128, u8, SingleCore> = unsafe { Queue::u8_sc() };
+        let (mut producer, consumer) = queue.split();
+        let layout = Layout::new(Panel::Size480x320, Orientation::Landscape);
+        let settings = Settings::default();
+        let mut display = Display::new(
+            FrameBuffer::new(&layout),
+            consumer,
+            layout,
+            SAMPLE_RATE,
+            &settings,
+        )
+        .unwrap();
+        // The dark theme draws the flat trace in yellow along the center row
+        let center = center_row(display.lcd());
+        assert_eq!(away(display.lcd(), center), (0, 0));
+        feed(&mut display, &mut producer, &samples);
+        // The R waves rise above it, the S waves dip below
+        let (above, below) = away(display.lcd(), center);
+        assert!(above > 100 && below > 10, "{} {}", above, below);
+    });
+}
+
+#[test]
+fn auto_scale_fits_the_trace() {
+    run(|| {
+        let samples = Recording::regular(SAMPLE_RATE, 800, 10).filtered();
+        let mut heights = Vec::new();
+        for fraction in [None, Some(60)].iter() {
+            let mut queue: Queue<u16, U128, u8, SingleCore> = unsafe { Queue::u8_sc() };
+            let (mut producer, consumer) = queue.split();
+            let layout = Layout::new(Panel::Size480x320, Orientation::Landscape);
+            let settings = Settings::default();
+            let mut display = Display::new(
+                FrameBuffer::new(&layout),
+                consumer,
+                layout,
+                SAMPLE_RATE,
+                &settings,
+            )
+            .unwrap();
+            display.set_auto_scale(*fraction).unwrap();
+            feed(&mut display, &mut producer, &samples);
+            heights.push(trace_height(display.lcd()));
+
+            display.set_scale_lock(true);
+            assert_eq!(display.scale_locked(), fraction.is_some());
+            display.set_auto_scale(None).unwrap();
+            assert!(!display.scale_locked());
+        }
+        // About 7 mm at 10 mm/mV, then 60 % of the frame
+        assert!(heights[1] > 3 * heights[0], "{:?}", heights);
+    });
+}
+
+// Columns of the scrolled area without any trace
+fn blank_columns(lcd: &FrameBuffer, layout: &Layout) -> usize {
+    let (start, end) = layout.scroll_offsets();
+    (start..lcd.width() - end)
+        .filter(|x| (0..lcd.height()).all(|y| lcd.pixel(*x, y) != Rgb565::YELLOW))
+        .count()
+}
+
+#[test]
+fn sweep_erases_ahead_of_the_trace() {
+    run(|| {
+        let samples = Recording::regular(SAMPLE_RATE, 800, 3).filtered();
+        let mut blank = Vec::new();
+        for mode in [RenderMode::Scroll, RenderMode::Sweep].iter() {
+            let mut queue: Queue<u16, U128, u8, SingleCore> = unsafe { Queue::u8_sc() };
+            let (mut producer, consumer) = queue.split();
+            let layout = Layout::new(Panel::Size480x320, Orientation::Landscape);
+            let settings = Settings {
+                render_mode: *mode,
+                ..Settings::default()
+            };
+            let mut display = Display::new(
+                FrameBuffer::new(&layout),
+                consumer,
+                layout,
+                SAMPLE_RATE,
+                &settings,
+            )
+            .unwrap();
+            feed(&mut display, &mut producer, &samples);
+            blank.push(blank_columns(display.lcd(), &layout));
+        }
+        // The erase bar is 12 columns wide
+        assert_eq!(blank, vec![0, 12]);
+    });
+}
+
+fn count(lcd: &FrameBuffer, color: Rgb565) -> usize {
+    screenshot(lcd)
+        .into_iter()
+        .filter(|pixel| *pixel == color)
+        .count()
+}
+
+#[test]
+fn marks_the_beats_once_shown() {
+    run(|| {
+        let recording = Recording::regular(SAMPLE_RATE, 800, 4);
+        let samples = recording.filtered();
+        let mut queue: Queue<u16, U128, u8, SingleCore> = unsafe { Queue::u8_sc() };
+        let (mut producer, consumer) = queue.split();
+        let layout = Layout::new(Panel::Size480x320, Orientation::Landscape);
+        let settings = Settings::default();
+        let mut display = Display::new(
+            FrameBuffer::new(&layout),
+            consumer,
+            layout,
+            SAMPLE_RATE,
+            &settings,
+        )
+        .unwrap();
+        // The dark theme draws the PVC tick and its label in cyan like the PVC field
+        let before = count(display.lcd(), Rgb565::CYAN);
+        let (pvc, _) = recording.beats[2];
+        display.annotate(Annotation::beat(pvc, BeatClass::Pvc));
+        let (shown, rest) = samples.split_at(pvc as usize - CHUNK);
+        feed(&mut display, &mut producer, shown);
+        assert_eq!(count(display.lcd(), Rgb565::CYAN), before);
+        feed(&mut display, &mut producer, rest);
+        // More than the 6 pixels of the tick with the label
+        assert!(count(display.lcd(), Rgb565::CYAN) > before + 6);
+    });
+}
+
+#[test]
+fn keeps_narrow_spikes_at_high_rates() {
+    run(|| {
+        // Single samples 0.7 mV high every 250 ms at 1 kHz, 6 samples per column
+        let rate = 1000;
+        let samples: Vec<u16> = (0..2 * rate)
+            .map(|index| match index % 250 {
+                125 => (BASELINE_MV + 770) as u16,
+                _ => BASELINE_MV as u16,
+            })
+            .collect();
+        let mut queue: Queue<u16, U128, u8, SingleCore> = unsafe { Queue::u8_sc() };
+        let (mut producer, consumer) = queue.split();
+        let layout = Layout::new(Panel::Size480x320, Orientation::Landscape);
+        let settings = Settings::default();
+        let mut display =
+            Display::new(FrameBuffer::new(&layout), consumer, layout, rate, &settings).unwrap();
+        feed(&mut display, &mut producer, &samples);
+
+        // Every spike reaches 7 mm, about 45 pixels above the center
+        let lcd = display.lcd();
+        let center = center_row(lcd);
+        let tall: Vec<bool> = (0..lcd.width())
+            .map(|x| (0..center - 40).any(|y| lcd.pixel(x, y) == Rgb565::YELLOW))
+            .collect();
+        let spikes = tall.windows(2).filter(|pair| !pair[0] && pair[1]).count();
+        assert_eq!(spikes, 8);
+    });
+}
+
+#[test]
+fn frozen_trace_stays() {
+    run(|| {
+        let samples = Recording::regular(SAMPLE_RATE, 800, 15).filtered();
+        let (first, rest) = samples.split_at(samples.len() / 2);
+        let mut queue: Queue<u16, U128, u8, SingleCore> = unsafe { Queue::u8_sc() };
+        let (mut producer, consumer) = queue.split();
+        let layout = Layout::new(Panel::Size480x320, Orientation::Landscape);
+        let settings = Settings::default();
+        let mut display = Display::new(
+            FrameBuffer::new(&layout),
+            consumer,
+            layout,
+            SAMPLE_RATE,
+            &settings,
+        )
+        .unwrap();
+        feed(&mut display, &mut producer, first);
+
+        display.set_focus(Some(0)).unwrap();
+        let frozen = screenshot(display.lcd());
+        feed(&mut display, &mut producer, &rest[..SAMPLE_RATE as usize]);
+        assert!(screenshot(display.lcd()) == frozen);
+
+        display.pan(-1).unwrap();
+        assert!(screenshot(display.lcd()) != frozen);
+
+        display.set_focus(None).unwrap();
+        feed(&mut display, &mut producer, &rest[SAMPLE_RATE as usize..]);
+        assert!(screenshot(display.lcd()) != frozen);
+    });
+}
+
+#[test]
+fn draws_every_screen_of_every_layout() {
+    run(|| {
+        let samples = Recording::regular(SAMPLE_RATE, 800, 3).filtered();
+        let screens = [
+            Screen::Waveform,
+            Screen::Trend(TrendSpan::Minutes30),
+            Screen::Hrv,
+            Screen::Settings,
+            Screen::EventLog,
+            Screen::Recording,
+        ];
+        for panel in [Panel::Size320x240, Panel::Size480x320].iter() {
+            for orientation in [Orientation::Landscape, Orientation::Portrait].iter() {
+                for theme in [Theme::Dark, Theme::Paper, Theme::Night].iter() {
+                    let mut queue: Queue<u16, U128, u8, SingleCore> = unsafe { Queue::u8_sc() };
+                    let (mut producer, consumer) = queue.split();
+                    let layout = Layout::new(*panel, *orientation);
+                    let settings = Settings {
+                        theme: *theme,
+                        ..Settings::default()
+                    };
+                    let mut display = Display::new(
+                        FrameBuffer::new(&layout),
+                        consumer,
+                        layout,
+                        SAMPLE_RATE,
+                        &settings,
+                    )
+                    .unwrap();
+                    feed(&mut display, &mut producer, &samples);
+                    for screen in screens.iter() {
+                        display.set_screen(*screen, None).unwrap();
+                        assert_eq!(display.screen(), *screen);
+                        display.show_trend(&HrTrend::new(SAMPLE_RATE)).unwrap();
+                        display.show_hrv(None, None, None).unwrap();
+                        display.show_settings(&settings).unwrap();
+                        display.show_events(&EventLog::new()).unwrap();
+                        display
+                            .show_recording(&Session::new(SAMPLE_RATE), 0)
+                            .unwrap();
+                    }
+                }
+            }
+        }
+    });
+}
+
+#[test]
+fn layout_follows_the_panel() {
+    for panel in [Panel::Size320x240, Panel::Size480x320].iter() {
+        let landscape = Layout::new(*panel, Orientation::Landscape);
+        let portrait = Layout::new(*panel, Orientation::Portrait);
+        let (width, height) = landscape.size();
+        assert!(width > height);
+        assert_eq!(portrait.size(), (height, width));
+        // Only the long side scrolls
+        assert!(landscape.can_scroll());
+        assert!(!portrait.can_scroll());
+        let (start, end) = landscape.scroll_offsets();
+        assert!(start + end < width as u16);
+    }
+}
+
+#[test]
+fn frame_buffer_scrolls_between_the_fixed_areas() {
+    run(|| {
+        let layout = Layout::new(Panel::Size320x240, Orientation::Landscape);
+        let (start, end) = layout.scroll_offsets();
+        let mut lcd = FrameBuffer::new(&layout);
+        let area = lcd.width() - start - end;
+        for x in [0, start, lcd.width() - 1].iter() {
+            lcd.draw_pixel(Pixel(Point::new(*x as i32, 10), Rgb565::RED))
+                .unwrap();
+        }
+        lcd.scroll(1).unwrap();
+        // The fixed areas stay, the first scrolled column moves to the end of the area
+        assert_eq!(lcd.pixel(0, 10), Rgb565::RED);
+        assert_eq!(lcd.pixel(lcd.width() - 1, 10), Rgb565::RED);
+        assert_eq!(lcd.pixel(start, 10), Rgb565::BLACK);
+        assert_eq!(lcd.pixel(start + area - 1, 10), Rgb565::RED);
+
+        lcd.scroll(area - 1).unwrap();
+        assert_eq!(lcd.pixel(start, 10), Rgb565::RED);
+    });
+}
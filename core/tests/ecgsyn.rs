@@ -0,0 +1,233 @@
+mod common;
+
+use common::match_beats;
+use ecg_core::analysis::pvc::{BeatClass, BeatClassifier};
+use ecg_core::analysis::qrs::QrsDetector;
+use ecg_core::ecgsyn::{EcgSyn, EcgSynConfig};
+use ecg_core::filter::{EcgFilter, Filter, FilterMode, MainsFrequency, MainsRejection};
+use ecg_core::sampler::BASELINE_MV;
+
+// Without rate variability, noise and wander
+fn steady(heart_rate: u16) -> EcgSynConfig {
+    EcgSynConfig {
+        heart_rate,
+        rsa_ms: 0,
+        mayer_ms: 0,
+        noise_uv: 0,
+        wander_uv: 0,
+        ..EcgSynConfig::default()
+    }
+}
+
+// Samples and the R peaks among them
+fn generate(config: EcgSynConfig, seconds: u32) -> (Vec<u16>, Vec<(u32, BeatClass)>) {
+    let mut ecgsyn = EcgSyn::new(500, config);
+    let mut samples = Vec::new();
+    let mut peaks = Vec::new();
+    for index in 0..seconds * 500 {
+        samples.push(ecgsyn.next().unwrap());
+        if let Some(class) = ecgsyn.peak() {
+            peaks.push((index, class));
+        }
+    }
+    (samples, peaks)
+}
+
+fn intervals(peaks: &[(u32, BeatClass)]) -> Vec<u32> {
+    peaks.windows(2).map(|pair| pair[1].0 - pair[0].0).collect()
+}
+
+#[test]
+fn beats_at_the_heart_rate() {
+    for heart_rate in [40, 60, 75, 120, 180].iter() {
+        let (samples, peaks) = generate(steady(*heart_rate), 20);
+        let interval = 30_000 / *heart_rate as u32;
+        assert!(
+            peaks.len() as u32 >= 20 * 500 / interval - 1,
+            "{}",
+            heart_rate
+        );
+        for found in intervals(&peaks) {
+            assert!(
+                (found as i32 - interval as i32).abs() <= 1,
+                "{}: {}",
+                heart_rate,
+                found
+            );
+        }
+        // The R wave is the maximum of its beat, 1 mV amplified by the front-end above
+        // the baseline
+        for (index, _) in &peaks[1..peaks.len() - 1] {
+            let index = *index as usize;
+            let beat = &samples[index - interval as usize / 2..index + interval as usize / 2];
+            let (top, max) = beat
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, sample)| **sample)
+                .unwrap();
+            let min = *beat.iter().min().unwrap();
+            assert!(
+                (top as i32 - interval as i32 / 2).abs() <= 2,
+                "{}",
+                heart_rate
+            );
+            assert!(
+                (900..=1400).contains(&(max - min)),
+                "{}: {}",
+                heart_rate,
+                max - min
+            );
+        }
+    }
+}
+
+#[test]
+fn scales_the_amplitude() {
+    let (small, _) = generate(
+        EcgSynConfig {
+            r_wave_uv: 500,
+            ..steady(60)
+        },
+        5,
+    );
+    let (large, _) = generate(
+        EcgSynConfig {
+            r_wave_uv: 1000,
+            ..steady(60)
+        },
+        5,
+    );
+    for (small, large) in small.iter().zip(large.iter()) {
+        let small = *small as i32 - BASELINE_MV;
+        let large = *large as i32 - BASELINE_MV;
+        assert!((large - 2 * small).abs() <= 2, "{} {}", small, large);
+    }
+}
+
+#[test]
+fn varies_the_rate() {
+    let (_, peaks) = generate(EcgSynConfig::default(), 60);
+    let intervals = intervals(&peaks);
+    let min = *intervals.iter().min().unwrap();
+    let max = *intervals.iter().max().unwrap();
+    let mean = intervals.iter().sum::<u32>() / intervals.len() as u32;
+    // 1 s +- 60 ms
+    assert!(min >= 465 && max <= 535, "{} {}", min, max);
+    assert!(max - min >= 30, "{} {}", min, max);
+    assert!((490..=510).contains(&mean), "{}", mean);
+}
+
+#[test]
+fn inserts_pvcs_with_a_compensatory_pause() {
+    let (_, peaks) = generate(
+        EcgSynConfig {
+            pvc_every: Some(4),
+            ..steady(75)
+        },
+        30,
+    );
+    for (beat, (_, class)) in peaks.iter().enumerate() {
+        let expected = if beat % 4 == 3 {
+            BeatClass::Pvc
+        } else {
+            BeatClass::Normal
+        };
+        assert_eq!(*class, expected, "{}", beat);
+    }
+    // 800 ms, the PVC comes after 480 ms and the pause makes up for it
+    for (beat, interval) in intervals(&peaks).iter().enumerate() {
+        let expected = match beat % 4 {
+            2 => 240,
+            3 => 560,
+            _ => 400,
+        };
+        assert!(
+            (*interval as i32 - expected).abs() <= 1,
+            "{}: {}",
+            beat,
+            interval
+        );
+    }
+}
+
+#[test]
+fn repeats_with_the_seed() {
+    let config = EcgSynConfig {
+        noise_uv: 50,
+        ..EcgSynConfig::default()
+    };
+    let (first, _) = generate(config, 5);
+    let (second, _) = generate(config, 5);
+    assert_eq!(first, second);
+    let (other, _) = generate(EcgSynConfig { seed: 2, ..config }, 5);
+    assert_ne!(first, other);
+}
+
+#[test]
+fn adds_mains_interference() {
+    let config = EcgSynConfig {
+        mains_uv: 50,
+        mains: MainsFrequency::Hz60,
+        ..EcgSynConfig::default()
+    };
+    let mut filter = EcgFilter::new(
+        500,
+        FilterMode::Monitor,
+        MainsFrequency::Hz60,
+        MainsRejection::Adaptive,
+    );
+    for sample in generate(config, 10).0 {
+        filter.process(sample as i32);
+    }
+    // 55 mV after the front-end
+    let amplitude = filter.interference().unwrap();
+    assert!((45..=65).contains(&amplitude), "{}", amplitude);
+}
+
+#[test]
+fn detects_and_classifies_the_beats() {
+    let config = EcgSynConfig {
+        heart_rate: 72,
+        noise_uv: 20,
+        mains_uv: 20,
+        pvc_every: Some(10),
+        ..EcgSynConfig::default()
+    };
+    let (samples, peaks) = generate(config, 120);
+    let mut filter = EcgFilter::new(
+        500,
+        FilterMode::Monitor,
+        MainsFrequency::Hz50,
+        MainsRejection::Adaptive,
+    );
+    let mut detector = QrsDetector::new(500);
+    let mut classifier = BeatClassifier::new(500);
+    let mut found = Vec::new();
+    let mut classes = Vec::new();
+    for sample in samples {
+        let sample = filter.process(sample as i32) as u16;
+        classifier.push(sample);
+        if let Some(index) = detector.process(sample) {
+            found.push(index);
+            classes.push((index, classifier.classify(index)));
+        }
+    }
+
+    let reference: Vec<u32> = peaks.iter().map(|(index, _)| *index).collect();
+    let (matched, missed, extra) = match_beats(&reference, &found, 500, 2500);
+    assert!(matched > 120);
+    assert_eq!((missed, extra), (0, 0));
+    // The PVCs after the learning are found, no normal beat is taken for one
+    let mut unknown = 0;
+    for (index, class) in classes.iter().filter(|(index, _)| *index >= 10_000) {
+        let (_, expected) = peaks
+            .iter()
+            .min_by_key(|(peak, _)| (*peak as i64 - *index as i64).abs())
+            .unwrap();
+        match (expected, class) {
+            (BeatClass::Normal, BeatClass::Unknown) => unknown += 1,
+            _ => assert_eq!(class, expected, "{}", index),
+        }
+    }
+    assert!(unknown <= 10, "{}", unknown);
+}
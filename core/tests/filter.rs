@@ -0,0 +1,118 @@
+use std::f64::consts::PI;
+
+use ecg_core::filter::{EcgFilter, Filter, FilterMode, MainsFrequency, MainsRejection};
+
+const SAMPLE_RATE: u32 = 500;
+
+// Peak output of a settled sine relative to its input amplitude
+fn gain(filter: &mut EcgFilter, frequency: f64) -> f64 {
+    let amplitude = 500.0;
+    let len = 60 * SAMPLE_RATE as usize;
+    let mut peak: i32 = 0;
+    for index in 0..len {
+        let phase = 2.0 * PI * frequency * index as f64 / SAMPLE_RATE as f64;
+        let sample = 1500.0 + amplitude * phase.sin();
+        let output = filter.process(sample.round() as i32) - EcgFilter::OFFSET;
+        if index > len * 3 / 4 {
+            peak = peak.max(output.abs());
+        }
+    }
+    peak as f64 / amplitude
+}
+
+fn filter(mode: FilterMode, mains: MainsFrequency, rejection: MainsRejection) -> EcgFilter {
+    EcgFilter::new(SAMPLE_RATE, mode, mains, rejection)
+}
+
+#[test]
+fn passes_the_qrs_band() {
+    for mode in [FilterMode::Monitor, FilterMode::Diagnostic].iter() {
+        for frequency in [5.0, 10.0, 20.0].iter() {
+            let mut filter = filter(*mode, MainsFrequency::Hz50, MainsRejection::Notch);
+            let gain = gain(&mut filter, *frequency);
+            assert!((0.9..1.1).contains(&gain), "{} Hz: {}", frequency, gain);
+        }
+    }
+}
+
+#[test]
+fn removes_baseline_wander() {
+    let mut monitor = filter(
+        FilterMode::Monitor,
+        MainsFrequency::Hz50,
+        MainsRejection::Notch,
+    );
+    assert!(gain(&mut monitor, 0.05) < 0.05);
+    // The diagnostic band keeps the ST segment, so only the slowest drift goes
+    let mut diagnostic = filter(
+        FilterMode::Diagnostic,
+        MainsFrequency::Hz50,
+        MainsRejection::Notch,
+    );
+    assert!(gain(&mut diagnostic, 0.5) > 0.9);
+    assert!(gain(&mut diagnostic, 0.005) < 0.2);
+}
+
+#[test]
+fn rejects_mains() {
+    for (mains, hz) in [(MainsFrequency::Hz50, 50.0), (MainsFrequency::Hz60, 60.0)].iter() {
+        for rejection in [MainsRejection::Notch, MainsRejection::Adaptive].iter() {
+            let mut filter = filter(FilterMode::Diagnostic, *mains, *rejection);
+            let gain = gain(&mut filter, *hz);
+            assert!(
+                gain < 0.1,
+                "{} {}: {}",
+                mains.name(),
+                rejection.name(),
+                gain
+            );
+        }
+    }
+}
+
+#[test]
+fn smooths_high_frequencies() {
+    let mut monitor = filter(
+        FilterMode::Monitor,
+        MainsFrequency::Hz50,
+        MainsRejection::Notch,
+    );
+    assert!(gain(&mut monitor, 100.0) < 0.1);
+    let mut diagnostic = filter(
+        FilterMode::Diagnostic,
+        MainsFrequency::Hz50,
+        MainsRejection::Notch,
+    );
+    assert!(gain(&mut diagnostic, 100.0) > 0.7);
+}
+
+#[test]
+fn starts_settled() {
+    let mut filter = filter(
+        FilterMode::Monitor,
+        MainsFrequency::Hz50,
+        MainsRejection::Adaptive,
+    );
+    for _ in 0..100 {
+        assert_eq!(filter.process(1600), EcgFilter::OFFSET);
+    }
+}
+
+#[test]
+fn measures_interference() {
+    let mut adaptive = filter(
+        FilterMode::Monitor,
+        MainsFrequency::Hz50,
+        MainsRejection::Adaptive,
+    );
+    gain(&mut adaptive, 50.0);
+    let amplitude = adaptive.interference().unwrap();
+    assert!((400..=600).contains(&amplitude), "{}", amplitude);
+
+    let notch = filter(
+        FilterMode::Monitor,
+        MainsFrequency::Hz50,
+        MainsRejection::Notch,
+    );
+    assert_eq!(notch.interference(), None);
+}
@@ -0,0 +1,57 @@
+use ecg_core::display::{Gain, Grid, GridLine, Scale, SweepSpeed};
+
+// 3.5" panel
+const PITCH_UM: u32 = 153;
+
+#[test]
+fn columns_follow_the_paper() {
+    let mut grid = Grid::new(PITCH_UM, 280, 140);
+    let lines: Vec<GridLine> = (0..327).map(|_| grid.next_column()).collect();
+    // 50 mm
+    let count = |kind| lines.iter().filter(|line| **line == kind).count();
+    assert_eq!(count(GridLine::Major), 10);
+    assert_eq!(count(GridLine::Minor), 40);
+}
+
+#[test]
+fn rows_around_the_baseline() {
+    let grid = Grid::new(PITCH_UM, 280, 140);
+    let rows: Vec<(u16, GridLine)> = grid.rows(0, 279).copied().collect();
+    assert!(rows.contains(&(140, GridLine::Major)));
+    // 5 mm above and below
+    assert!(rows.contains(&(140 + 33, GridLine::Major)));
+    assert!(rows.contains(&(140 - 33, GridLine::Major)));
+    assert!(rows.contains(&(140 + 7, GridLine::Minor)));
+    assert!(rows.iter().all(|(row, _)| *row < 280));
+    assert_eq!(grid.rows(100, 120).count(), 4);
+}
+
+#[test]
+fn sweep_speed() {
+    let mut scale = Scale::new(500, PITCH_UM, SweepSpeed::Mm25, Gain::Mm10);
+    // 25 mm are 163.4 pixels
+    let columns: u32 = (0..500).map(|_| scale.advance()).sum();
+    assert_eq!(columns, 163);
+    assert_eq!(scale.columns(500), 163);
+    assert!((498..=502).contains(&scale.samples(163)));
+    assert_eq!(scale.columns_between(1000, 500), -164);
+
+    scale.set_speed(SweepSpeed::Mm50);
+    assert_eq!(scale.columns(500), 326);
+}
+
+#[test]
+fn gain() {
+    let mut scale = Scale::new(500, PITCH_UM, SweepSpeed::Mm25, Gain::Mm10);
+    // 10 mm
+    assert_eq!(scale.calibration(), 65);
+    assert_eq!(scale.pixels(-Scale::FRONT_END_GAIN / 2), -32);
+    let gain_x10 = scale.gain_for(2 * Scale::FRONT_END_GAIN, 130);
+    assert_eq!(gain_x10, 99);
+
+    scale.set_auto(1800, 200);
+    assert_eq!(scale.calibration(), 130);
+    assert_eq!((scale.center(), scale.gain()), (1800, Gain::Mm10));
+    scale.set_gain(Gain::Mm5);
+    assert_eq!((scale.center(), scale.gain_x10()), (Scale::CENTER, 50));
+}
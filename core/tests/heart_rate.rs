@@ -0,0 +1,54 @@
+use ecg_core::analysis::heart_rate::{Averaging, HeartRate};
+
+const SAMPLE_RATE: u32 = 500;
+
+// Feeds beats at the R-R intervals in ms, returns what every beat reported
+fn feed(heart_rate: &mut HeartRate, start: u32, intervals: &[u32]) -> Vec<Option<u16>> {
+    let mut index = start;
+    intervals
+        .iter()
+        .map(|interval| {
+            index += interval * SAMPLE_RATE / 1000;
+            heart_rate.beat(index)
+        })
+        .collect()
+}
+
+#[test]
+fn averages_the_configured_beats() {
+    let mut heart_rate = HeartRate::new(SAMPLE_RATE, Averaging::Beats4);
+    let rates = feed(&mut heart_rate, 0, &[0, 800, 800, 800, 800, 800]);
+    assert_eq!(rates, [None, None, None, None, Some(75), Some(75)]);
+    assert_eq!(heart_rate.instantaneous(), Some(75));
+    assert_eq!(heart_rate.average(8), None);
+
+    heart_rate.set_averaging(Averaging::Instantaneous);
+    assert_eq!(feed(&mut heart_rate, 2000, &[750]), [Some(80)]);
+    assert_eq!(heart_rate.average(2), Some(77));
+}
+
+#[test]
+fn ignores_impossible_intervals() {
+    let mut heart_rate = HeartRate::new(SAMPLE_RATE, Averaging::Instantaneous);
+    // A double detection 100 ms after the beat and a gap of 4 s
+    let rates = feed(&mut heart_rate, 0, &[0, 1000, 100, 900, 4000, 1000]);
+    assert_eq!(rates, [None, Some(60), None, Some(66), None, Some(60)]);
+}
+
+#[test]
+fn follows_a_change_of_rhythm() {
+    let mut heart_rate = HeartRate::new(SAMPLE_RATE, Averaging::Instantaneous);
+    feed(&mut heart_rate, 0, &[0, 1000, 1000, 1000, 1000]);
+    // Outliers are rejected until they persist
+    let rates = feed(&mut heart_rate, 2000, &[500, 500, 500, 500, 500]);
+    assert_eq!(rates, [None, None, None, Some(120), Some(120)]);
+}
+
+#[test]
+fn starts_over_after_reset() {
+    let mut heart_rate = HeartRate::new(SAMPLE_RATE, Averaging::Instantaneous);
+    feed(&mut heart_rate, 0, &[0, 1000, 1000]);
+    heart_rate.reset();
+    assert_eq!(heart_rate.rate(), None);
+    assert_eq!(feed(&mut heart_rate, 10_000, &[0, 600]), [None, Some(100)]);
+}
@@ -0,0 +1,71 @@
+use ecg_core::display::{Annotation, History, Scale};
+
+// A narrow spike every second on a slightly moving baseline
+fn filled(history: &mut History, len: u32) {
+    for index in 0..len {
+        let sample = if index % 500 == 101 {
+            3000
+        } else {
+            Scale::CENTER as u16 + (index % 10) as u16
+        };
+        history.push(sample);
+    }
+}
+
+#[test]
+fn keeps_the_peaks() {
+    let mut history = History::new(500);
+    filled(&mut history, 10_000);
+    assert_eq!(history.range(), (10_000 - 8192, 10_000));
+    // Within a step
+    assert!(history.sample(9601) > 3000 - 16);
+    assert_eq!(history.extent(9590, 9610).1, history.sample(9601));
+    assert!(history.extent(9400, 9500).1 < Scale::CENTER as u16 + 16);
+}
+
+#[test]
+fn rate_after_the_last_beat() {
+    let mut history = History::new(500);
+    filled(&mut history, 10_000);
+    history.beat(8000, 60);
+    history.beat(8500, 62);
+    assert_eq!(history.bpm_at(7999), None);
+    assert_eq!(history.bpm_at(8200), Some(60));
+    assert_eq!(history.bpm_at(9000), Some(62));
+    // 3 s after the beat
+    assert_eq!(history.bpm_at(10_001), None);
+}
+
+#[test]
+fn hold_and_release() {
+    let mut history = History::new(500);
+    filled(&mut history, 10_000);
+    let range = history.range();
+    history.hold();
+    for _ in 0..3000 {
+        history.push(3000);
+    }
+    assert_eq!(history.range(), range);
+    assert!(history.sample(9000) < Scale::CENTER as u16 + 16);
+
+    history.release();
+    assert_eq!(history.range(), (range.0 + 3000, range.1 + 3000));
+    // The gap is flat
+    assert_eq!(
+        history.extent(10_000, 12_999),
+        (Scale::CENTER as u16, Scale::CENTER as u16)
+    );
+    history.push(3000);
+    history.push(3000);
+    assert!(history.sample(13_000) > 3000 - 16);
+}
+
+#[test]
+fn drops_the_oldest_annotations() {
+    let mut history = History::new(500);
+    for index in 0..40 {
+        history.annotate(Annotation::user(index));
+    }
+    let starts: Vec<u32> = history.annotations().map(|mark| mark.start).collect();
+    assert_eq!(starts, (8..40).collect::<Vec<u32>>());
+}
@@ -0,0 +1,102 @@
+use std::f64::consts::PI;
+
+use ecg_core::analysis::hrv::Hrv;
+use ecg_core::analysis::spectrum::HrvSpectrum;
+
+// Milliseconds as samples
+const SAMPLE_RATE: u32 = 1000;
+
+fn feed(hrv: &mut Hrv, intervals: impl IntoIterator<Item = u32>) {
+    let mut index = 0;
+    hrv.beat(index);
+    for interval in intervals {
+        index += interval;
+        hrv.beat(index);
+    }
+}
+
+#[test]
+fn needs_the_whole_window() {
+    let mut hrv = Hrv::new(SAMPLE_RATE);
+    feed(&mut hrv, vec![800; 74]);
+    assert!(hrv.short_term().is_none());
+    hrv.beat(75 * 800);
+    assert!(hrv.short_term().is_some());
+    assert!(hrv.long_term().is_none());
+}
+
+#[test]
+fn time_domain_metrics() {
+    let mut hrv = Hrv::new(SAMPLE_RATE);
+    feed(
+        &mut hrv,
+        (0..400).map(|beat| if beat % 2 == 0 { 800 } else { 880 }),
+    );
+    let metrics = hrv.short_term().unwrap();
+    assert_eq!(metrics.mean_nn, 840);
+    assert_eq!(metrics.sdnn, 40);
+    assert_eq!(metrics.rmssd, 80);
+    assert_eq!(metrics.pnn50, 100);
+    assert_eq!(metrics.count as u32, Hrv::SHORT_TERM_MS / 840);
+
+    let metrics = hrv.long_term().unwrap();
+    assert_eq!((metrics.mean_nn, metrics.rmssd), (840, 80));
+}
+
+#[test]
+fn excludes_ectopic_beats() {
+    let mut hrv = Hrv::new(SAMPLE_RATE);
+    // Every 10th beat is premature with a compensatory pause
+    let intervals = (0..200).map(|beat| match beat % 10 {
+        8 => 500,
+        9 => 1100,
+        _ => 800,
+    });
+    feed(&mut hrv, intervals);
+    let metrics = hrv.short_term().unwrap();
+    assert_eq!((metrics.mean_nn, metrics.sdnn, metrics.rmssd), (800, 0, 0));
+    assert!(hrv.nn_intervals(Hrv::SHORT_TERM_MS).all(|nn| nn == 800));
+}
+
+#[test]
+fn interruption_keeps_the_data() {
+    let mut hrv = Hrv::new(SAMPLE_RATE);
+    feed(&mut hrv, vec![800; 100]);
+    hrv.interrupt();
+    // The gap is not an interval
+    hrv.beat(200_000);
+    hrv.beat(200_800);
+    assert!(hrv.nn_intervals(Hrv::LONG_TERM_MS).all(|nn| nn == 800));
+    hrv.reset();
+    assert_eq!(hrv.nn_intervals(Hrv::LONG_TERM_MS).count(), 0);
+}
+
+// R-R intervals modulated by a sine of the frequency in Hz
+fn modulated(frequency: f64) -> Hrv {
+    let mut hrv = Hrv::new(SAMPLE_RATE);
+    let mut time = 0.0;
+    let intervals = (0..400).map(|_| {
+        let interval = 800.0 + 40.0 * (2.0 * PI * frequency * time).sin();
+        time += interval / 1000.0;
+        interval as u32
+    });
+    feed(&mut hrv, intervals);
+    hrv
+}
+
+#[test]
+fn spectrum_bands() {
+    let mut spectrum = HrvSpectrum::new();
+    assert!(spectrum.analyze(&Hrv::new(SAMPLE_RATE)).is_none());
+
+    let low = spectrum.analyze(&modulated(0.1)).unwrap();
+    assert!(low.lf > 10 * low.hf, "{} {}", low.lf, low.hf);
+    assert!(low.ratio > 1000);
+
+    let high = spectrum.analyze(&modulated(0.3)).unwrap();
+    assert!(high.hf > 10 * high.lf, "{} {}", high.lf, high.hf);
+    assert!(high.ratio < 10);
+    // A sine of 40 ms amplitude has 800 ms^2 of power, the interpolation between the
+    // beats smooths some of it
+    assert!((400..=800).contains(&high.hf), "{}", high.hf);
+}
@@ -0,0 +1,116 @@
+mod common;
+
+use common::{Beat, Recording};
+use ecg_core::analysis::pvc::BeatClass::{Normal as N, Pvc as V, Unknown as U};
+use ecg_core::analysis::pvc::{BeatClass, BeatClassifier, EctopicPattern, EctopyCounter};
+use ecg_core::analysis::qrs::QrsDetector;
+
+// Classes of the detected beats matched to the reference within 150 ms
+fn classify(recording: &Recording) -> Vec<(Beat, BeatClass)> {
+    let sample_rate = recording.sample_rate;
+    let mut detector = QrsDetector::new(sample_rate);
+    let mut classifier = BeatClassifier::new(sample_rate);
+    let mut classes = Vec::new();
+    for sample in recording.filtered() {
+        classifier.push(sample);
+        if let Some(index) = detector.process(sample) {
+            classes.push((index, classifier.classify(index)));
+        }
+    }
+    let tolerance = (sample_rate * 150 / 1000) as i64;
+    classes
+        .into_iter()
+        .filter_map(|(index, class)| {
+            recording
+                .beats
+                .iter()
+                .find(|(beat, _)| (*beat as i64 - index as i64).abs() <= tolerance)
+                .map(|(_, beat)| (*beat, class))
+        })
+        .collect()
+}
+
+// Premature PVC after every 9th normal beat
+fn occasional_pvcs(sample_rate: u32) -> Recording {
+    let rhythm: Vec<(u32, Beat)> = (0..120)
+        .map(|beat| match beat % 10 {
+            9 if beat > 20 => (550, Beat::Pvc),
+            _ => (800, Beat::Normal),
+        })
+        .collect();
+    Recording::new(sample_rate, &rhythm).with_mains(50.0, 20.0)
+}
+
+#[test]
+fn classifies_pvcs() {
+    for sample_rate in [250, 360, 500, 1000].iter() {
+        let classes = classify(&occasional_pvcs(*sample_rate));
+        let count = |beat: Beat, class: BeatClass| {
+            classes
+                .iter()
+                .filter(|classified| **classified == (beat, class))
+                .count()
+        };
+        let pvcs = classes
+            .iter()
+            .filter(|(beat, _)| *beat == Beat::Pvc)
+            .count();
+        assert_eq!(count(Beat::Pvc, BeatClass::Pvc), pvcs, "{} Hz", sample_rate);
+        assert_eq!(count(Beat::Normal, BeatClass::Pvc), 0, "{} Hz", sample_rate);
+        // Only the learning is unknown
+        assert!(
+            count(Beat::Normal, BeatClass::Unknown) <= 10,
+            "{} Hz",
+            sample_rate
+        );
+    }
+}
+
+#[test]
+fn learns_the_template_first() {
+    let classes = classify(&occasional_pvcs(500));
+    assert!(classes[..4]
+        .iter()
+        .all(|(_, class)| *class == BeatClass::Unknown));
+}
+
+// Feeds beats 1 s apart at 500 Hz from the second `start`, returns the next one
+fn count(counter: &mut EctopyCounter, start: u32, classes: &[BeatClass]) -> u32 {
+    for (beat, class) in classes.iter().enumerate() {
+        counter.beat((start + beat as u32) * 500, *class);
+    }
+    start + classes.len() as u32
+}
+
+#[test]
+fn finds_patterns() {
+    let mut counter = EctopyCounter::new(500);
+    let next = count(&mut counter, 0, &[N, N, V, N]);
+    assert_eq!(counter.pattern(), None);
+    let next = count(&mut counter, next, &[V, V]);
+    assert_eq!(counter.pattern(), Some(EctopicPattern::Couplet));
+    let next = count(&mut counter, next, &[N, V, N, V, N, V]);
+    assert_eq!(counter.pattern(), Some(EctopicPattern::Bigeminy));
+    let next = count(&mut counter, next, &[N]);
+    assert_eq!(counter.pattern(), Some(EctopicPattern::Bigeminy));
+    let next = count(&mut counter, next, &[N]);
+    assert_eq!(counter.pattern(), None);
+    // An unclassified beat breaks the run
+    count(&mut counter, next, &[V, N, V, U, V, N]);
+    assert_eq!(counter.pattern(), None);
+}
+
+#[test]
+fn counts_the_last_minute() {
+    let mut counter = EctopyCounter::new(500);
+    let next = count(&mut counter, 0, &[N, V].repeat(10));
+    assert_eq!(counter.per_minute(), 10);
+    let next = count(&mut counter, next, &[N; 50]);
+    assert_eq!(counter.per_minute(), 5);
+    count(&mut counter, next, &[N; 20]);
+    assert_eq!(counter.per_minute(), 0);
+
+    count(&mut counter, 100, &[V, V]);
+    counter.reset();
+    assert_eq!((counter.per_minute(), counter.pattern()), (0, None));
+}
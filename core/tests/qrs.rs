@@ -0,0 +1,115 @@
+mod common;
+
+use common::{match_beats, Beat, Noise, Recording};
+use ecg_core::analysis::qrs::QrsDetector;
+
+// Thresholds are learned over the first 2 s
+const LEARNING_MS: u32 = 2500;
+
+fn detect(recording: &Recording) -> Vec<u32> {
+    let mut detector = QrsDetector::new(recording.sample_rate);
+    recording
+        .filtered()
+        .into_iter()
+        .filter_map(|sample| detector.process(sample))
+        .collect()
+}
+
+// Every beat after the learning is found exactly once
+fn assert_detected(recording: &Recording) {
+    let reference: Vec<u32> = recording.beats.iter().map(|(beat, _)| *beat).collect();
+    let found = detect(recording);
+    let skip = recording.sample_rate * LEARNING_MS / 1000;
+    let (matched, missed, extra) = match_beats(&reference, &found, recording.sample_rate, skip);
+    assert!(matched > 0);
+    assert_eq!((missed, extra), (0, 0), "{} Hz", recording.sample_rate);
+}
+
+#[test]
+fn detects_regular_rhythm() {
+    for sample_rate in [250, 360, 500, 1000].iter() {
+        assert_detected(&Recording::regular(*sample_rate, 800, 40).with_mains(50.0, 20.0));
+    }
+}
+
+#[test]
+fn detects_slow_and_fast_rates() {
+    assert_detected(&Recording::regular(500, 1600, 20));
+    assert_detected(&Recording::regular(500, 350, 80));
+}
+
+#[test]
+fn detects_irregular_rhythm() {
+    let mut noise = Noise::new(3);
+    let rhythm: Vec<(u32, Beat)> = (0..60)
+        .map(|_| ((700.0 + 500.0 * noise.next()) as u32, Beat::Normal))
+        .collect();
+    assert_detected(&Recording::new(500, &rhythm).with_noise(40.0, (0.0, 60.0)));
+}
+
+#[test]
+fn detects_wide_beats() {
+    let rhythm: Vec<(u32, Beat)> = (0..40)
+        .map(|beat| match beat % 5 {
+            4 => (550, Beat::Pvc),
+            _ => (800, Beat::Normal),
+        })
+        .collect();
+    assert_detected(&Recording::new(500, &rhythm));
+}
+
+#[test]
+fn locates_the_r_peak() {
+    // Unfiltered, the smoothing of the filter delays the stream
+    let recording = Recording::regular(500, 800, 30);
+    let mut detector = QrsDetector::new(500);
+    let found: Vec<u32> = recording
+        .samples
+        .iter()
+        .filter_map(|sample| detector.process(*sample as u16))
+        .collect();
+    let skip = recording.seconds(2.5);
+    for (beat, _) in recording.beats.iter().filter(|(beat, _)| *beat > skip) {
+        let nearest = found
+            .iter()
+            .map(|found| (*found as i64 - *beat as i64).abs())
+            .min()
+            .unwrap();
+        // 10 ms
+        assert!(nearest <= 5, "{} off by {}", beat, nearest);
+    }
+}
+
+#[test]
+fn stays_quiet_without_beats() {
+    let recording = Recording::regular(500, 800, 0).with_noise(20.0, (0.0, 2.0));
+    let mut detector = QrsDetector::new(500);
+    let mut flat = recording.filtered();
+    flat.resize(30 * 500, flat[flat.len() - 1]);
+    assert!(flat
+        .into_iter()
+        .all(|sample| detector.process(sample).is_none()));
+    assert_eq!(detector.index(), 30 * 500);
+}
+
+// R-R intervals in ms of the annotated fixture, speeding up and slowing down
+const ANNOTATED_RR_MS: [u32; 32] = [
+    820, 810, 790, 770, 760, 740, 700, 660, 630, 610, 600, 620, 650, 690, 740, 800, 860, 920, 980,
+    1040, 1100, 1080, 1020, 950, 900, 870, 850, 830, 840, 860, 880, 900,
+];
+
+#[test]
+fn detects_the_annotated_beats() {
+    // Beats annotated at their R peaks with wander, noise and mains interference
+    let rhythm: Vec<(u32, Beat)> = ANNOTATED_RR_MS
+        .iter()
+        .map(|interval| (*interval, Beat::Normal))
+        .collect();
+    for sample_rate in [250, 360, 500, 1000].iter() {
+        let recording = Recording::new(*sample_rate, &rhythm)
+            .with_wander(0.3, 150.0)
+            .with_noise(30.0, (0.0, 30.0))
+            .with_mains(50.0, 20.0);
+        assert_detected(&recording);
+    }
+}
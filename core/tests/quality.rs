@@ -0,0 +1,73 @@
+mod common;
+
+use common::{Recording, FULL_SCALE};
+use ecg_core::analysis::qrs::QrsDetector;
+use ecg_core::analysis::quality::{QualityIndex, QualityMetrics, SignalQuality};
+
+// Metrics of every 10 s window
+fn assess(recording: &Recording) -> Vec<QualityMetrics> {
+    let sample_rate = recording.sample_rate;
+    let mut index = QualityIndex::new(sample_rate, FULL_SCALE);
+    let mut detector = QrsDetector::new(sample_rate);
+    let mut windows = Vec::new();
+    for (code, sample) in recording.codes().into_iter().zip(recording.filtered()) {
+        if let Some(beat) = detector.process(sample) {
+            index.beat(beat);
+        }
+        windows.extend(index.process(code, sample));
+    }
+    assert_eq!(index.metrics(), windows.last().copied());
+    windows
+}
+
+fn ecg(sample_rate: u32) -> Recording {
+    Recording::regular(sample_rate, 750, 80).with_mains(50.0, 20.0)
+}
+
+#[test]
+fn clean_signal_is_good() {
+    for sample_rate in [250, 500, 1000].iter() {
+        let windows = assess(&ecg(*sample_rate));
+        assert_eq!(windows.len(), 6);
+        // The first windows include the learning of both detectors
+        for metrics in &windows[2..] {
+            assert_eq!(metrics.quality, SignalQuality::Good, "{:?}", metrics);
+        }
+    }
+}
+
+#[test]
+fn noise_lowers_the_quality() {
+    let windows = assess(&ecg(500).with_noise(1200.0, (30.0, 40.0)));
+    assert_eq!(windows[2].quality, SignalQuality::Good);
+    assert_eq!(
+        windows[3].quality,
+        SignalQuality::Unusable,
+        "{:?}",
+        windows[3]
+    );
+}
+
+#[test]
+fn baseline_wander() {
+    let windows = assess(&ecg(500).with_wander(0.3, 600.0));
+    assert!(windows[1..]
+        .iter()
+        .all(|metrics| metrics.quality < SignalQuality::Good && metrics.baseline < 95));
+}
+
+#[test]
+fn clipping() {
+    let clipped = ecg(500).with_constant(3300.0, (30.0, 32.0));
+    let windows = assess(&clipped);
+    assert!(windows[3].clipping >= 190, "{:?}", windows[3]);
+    assert_eq!(windows[3].quality, SignalQuality::Unusable);
+}
+
+#[test]
+fn no_beats_are_not_disagreement() {
+    // Asystole from 30 s
+    let windows = assess(&ecg(500).with_constant(1500.0, (30.0, 60.0)));
+    assert_eq!(windows[2].agreement, Some(100));
+    assert_eq!(windows[4].agreement, None);
+}
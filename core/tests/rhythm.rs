@@ -0,0 +1,242 @@
+mod common;
+
+use common::Noise;
+use ecg_core::analysis::event_log::EventLog;
+use ecg_core::analysis::rhythm::{Rhythm, RhythmAnalyzer, RhythmConfig, RhythmEvent, Severity};
+
+// Milliseconds as samples
+const SAMPLE_RATE: u32 = 1000;
+
+struct Monitor {
+    analyzer: RhythmAnalyzer,
+    log: EventLog,
+    now: u32,
+}
+
+impl Monitor {
+    fn new() -> Self {
+        let mut analyzer = RhythmAnalyzer::new(SAMPLE_RATE, RhythmConfig::default());
+        analyzer.beat(0);
+        Monitor {
+            analyzer,
+            log: EventLog::new(),
+            now: 0,
+        }
+    }
+
+    // Beats at the R-R intervals with the pause check every 100 ms in between, returns
+    // all changes
+    fn feed(&mut self, intervals: impl IntoIterator<Item = u32>) -> Vec<RhythmEvent> {
+        let mut changes = Vec::new();
+        for interval in intervals {
+            let beat = self.now + interval;
+            while self.now + 100 < beat {
+                self.now += 100;
+                let update = self.analyzer.update(self.now);
+                self.log.record(update);
+                changes.extend_from_slice(update);
+            }
+            self.now = beat;
+            let update = self.analyzer.beat(beat);
+            self.log.record(update);
+            changes.extend_from_slice(update);
+        }
+        changes
+    }
+
+    fn active(&self) -> Vec<(Rhythm, Severity)> {
+        self.analyzer
+            .active()
+            .map(|event| (event.rhythm, event.severity))
+            .collect()
+    }
+}
+
+fn irregular(beats: usize) -> Vec<u32> {
+    let mut noise = Noise::new(11);
+    (0..beats)
+        .map(|_| (750.0 + 300.0 * noise.next()) as u32)
+        .collect()
+}
+
+#[test]
+fn bradycardia_with_hysteresis() {
+    let mut monitor = Monitor::new();
+    assert!(monitor.feed(vec![800; 20]).is_empty());
+    // 45 BPM
+    let changes = monitor.feed(vec![1333; 8]);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].rhythm, Rhythm::Bradycardia);
+    assert_eq!(changes[0].severity, Severity::Warning);
+    assert_eq!(changes[0].end, None);
+    // 37 BPM
+    monitor.feed(vec![1600; 8]);
+    assert_eq!(
+        monitor.active(),
+        [(Rhythm::Bradycardia, Severity::Critical)]
+    );
+    // 52 BPM is within the hysteresis
+    monitor.feed(vec![1150; 8]);
+    assert_eq!(monitor.active(), [(Rhythm::Bradycardia, Severity::Warning)]);
+    let changes = monitor.feed(vec![1000; 8]);
+    assert_eq!(changes.len(), 1);
+    assert!(changes[0].end.is_some());
+    assert!(monitor.active().is_empty());
+}
+
+#[test]
+fn tachycardia() {
+    let mut monitor = Monitor::new();
+    monitor.feed(vec![800; 20]);
+    // 125 and 171 BPM
+    monitor.feed(vec![480; 8]);
+    assert_eq!(monitor.active(), [(Rhythm::Tachycardia, Severity::Warning)]);
+    monitor.feed(vec![350; 8]);
+    assert_eq!(
+        monitor.active(),
+        [(Rhythm::Tachycardia, Severity::Critical)]
+    );
+    monitor.feed(vec![600; 8]);
+    assert!(monitor.active().is_empty());
+}
+
+#[test]
+fn pause() {
+    let mut monitor = Monitor::new();
+    monitor.feed(vec![800; 10]);
+    let last_beat = monitor.now;
+    let changes: Vec<RhythmEvent> = monitor
+        .feed(vec![7000])
+        .into_iter()
+        .filter(|event| event.rhythm == Rhythm::Pause)
+        .collect();
+    let severities: Vec<Severity> = changes.iter().map(|event| event.severity).collect();
+    assert_eq!(
+        severities,
+        [Severity::Warning, Severity::Critical, Severity::Critical]
+    );
+    assert!(changes.iter().all(|event| event.start == last_beat));
+    assert_eq!(changes[2].end, Some(last_beat + 7000));
+}
+
+#[test]
+fn atrial_fibrillation() {
+    let mut monitor = Monitor::new();
+    monitor.feed(irregular(100));
+    assert_eq!(
+        monitor.active(),
+        [(Rhythm::AtrialFibrillation, Severity::Warning)]
+    );
+    monitor.feed(vec![600; 40]);
+    assert!(monitor.active().is_empty());
+}
+
+#[test]
+fn regular_variations_are_not_fibrillation() {
+    // Sinus arrhythmia
+    let breathing = (0..200).map(|beat| (800.0 + 100.0 * (beat as f64 * 0.7).sin()) as u32);
+    assert!(Monitor::new().feed(breathing).is_empty());
+    let bigeminy = (0..200).map(|beat| if beat % 2 == 0 { 600 } else { 1000 });
+    assert!(Monitor::new().feed(bigeminy).is_empty());
+}
+
+#[test]
+fn alarm_is_the_most_severe() {
+    let mut monitor = Monitor::new();
+    monitor.feed(irregular(100));
+    monitor.feed(vec![340; 8]);
+    let alarm = monitor.analyzer.alarm().unwrap();
+    assert_eq!(
+        (alarm.rhythm, alarm.severity),
+        (Rhythm::Tachycardia, Severity::Critical)
+    );
+    monitor.analyzer.reset(monitor.now);
+    assert!(monitor.analyzer.alarm().is_none());
+}
+
+#[test]
+fn reset_ends_the_events() {
+    let mut monitor = Monitor::new();
+    monitor.feed(vec![800; 10]);
+    monitor.feed(vec![350; 10]);
+    monitor.now += 4000;
+    let update = monitor.analyzer.update(monitor.now);
+    monitor.log.record(update);
+    assert_eq!(
+        monitor.active(),
+        [
+            (Rhythm::Tachycardia, Severity::Critical),
+            (Rhythm::Pause, Severity::Warning)
+        ]
+    );
+    let ended = monitor.analyzer.reset(monitor.now).to_vec();
+    assert_eq!(ended.len(), 2);
+    assert!(ended.iter().all(|event| event.end == Some(monitor.now)));
+    monitor.log.record(&ended);
+    assert!(monitor.log.events().all(|event| event.end.is_some()));
+    assert!(monitor.active().is_empty());
+    // Nothing is left to end
+    assert!(monitor.analyzer.reset(monitor.now).is_empty());
+}
+
+#[test]
+fn log_updates_events_in_place() {
+    let mut monitor = Monitor::new();
+    monitor.feed(vec![800; 20]);
+    monitor.feed(vec![1333; 8]);
+    monitor.feed(vec![1600; 8]);
+    monitor.feed(vec![800; 8]);
+    monitor.feed(vec![350; 8]);
+    assert_eq!(monitor.log.count(), 2);
+    let events: Vec<&RhythmEvent> = monitor.log.events().collect();
+    assert_eq!(events[0].rhythm, Rhythm::Tachycardia);
+    assert_eq!(events[0].end, None);
+    // The highest severity stays
+    assert_eq!(events[1].rhythm, Rhythm::Bradycardia);
+    assert_eq!(events[1].severity, Severity::Critical);
+    assert!(events[1].end.is_some());
+}
+
+#[test]
+fn log_keeps_the_last_events() {
+    let mut log = EventLog::new();
+    for start in 0..20 {
+        let event = RhythmEvent {
+            rhythm: Rhythm::Pause,
+            severity: Severity::Warning,
+            start,
+            end: None,
+        };
+        assert_eq!(log.record(&[event]), 1);
+    }
+    assert_eq!(log.count(), 20);
+    let starts: Vec<u32> = log.events().map(|event| event.start).collect();
+    assert_eq!(starts, (4..20).rev().collect::<Vec<u32>>());
+    log.clear();
+    assert_eq!(log.events().count(), 0);
+}
+
+#[test]
+fn interrupted_beats_keep_the_pause_check() {
+    let mut monitor = Monitor::new();
+    monitor.feed(vec![350; 10]);
+    monitor.now += 350;
+    let ended = monitor.analyzer.interrupt(monitor.now).to_vec();
+    assert_eq!(ended.len(), 1);
+    assert_eq!(ended[0].rhythm, Rhythm::Tachycardia);
+    assert_eq!(ended[0].end, Some(monitor.now));
+    // Rate is judged again only after a full window of trusted beats
+    assert!(monitor.feed(vec![350; 7]).is_empty());
+    assert_eq!(monitor.feed(vec![350]).len(), 1);
+    // The pause is measured from the interrupted beat
+    monitor.now += 350;
+    monitor.analyzer.interrupt(monitor.now);
+    let last_beat = monitor.now;
+    monitor.now += 3500;
+    let changes = monitor.analyzer.update(monitor.now);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(
+        (changes[0].rhythm, changes[0].start),
+        (Rhythm::Pause, last_beat)
+    );
+}
@@ -0,0 +1,81 @@
+use heapless::consts::U8;
+use heapless::spsc::{Queue, SingleCore};
+
+use ecg_core::error::Error;
+use ecg_core::filter::Filter;
+use ecg_core::sampler::Sampler;
+use ecg_core::Buffer;
+
+// Input and internal reference of both halves of the DMA buffer
+static BUFFER: Buffer = [2048, 1650, 1024, 1500];
+// Reference reading at 3 V from the factory calibration
+const VREF_CALIBRATION: u16 = 1650;
+const FULL_SCALE: u16 = 4095;
+
+struct Offset(i32);
+
+impl Filter for Offset {
+    fn process(&mut self, sample: i32) -> i32 {
+        sample + self.0
+    }
+}
+
+#[test]
+fn converts_both_halves() {
+    let mut queue: Queue<u16, U8, u8, SingleCore> = unsafe { Queue::u8_sc() };
+    let (producer, mut consumer) = queue.split();
+    let mut sampler = Sampler::new(&BUFFER, producer, Offset(0), VREF_CALIBRATION, FULL_SCALE);
+
+    // 3 V supply
+    assert_eq!(sampler.sample::<()>().unwrap(), 1500);
+    assert_eq!(sampler.input(), 2048);
+    // The supply dropped to 3.3 V * 1650 / 1500
+    assert_eq!(sampler.sample::<()>().unwrap(), 825);
+    assert_eq!(sampler.input(), 1024);
+    assert_eq!(sampler.sample::<()>().unwrap(), 1500);
+
+    assert_eq!(consumer.dequeue(), Some(1500));
+    assert_eq!(consumer.dequeue(), Some(825));
+    assert_eq!(consumer.dequeue(), Some(1500));
+    assert_eq!(consumer.dequeue(), None);
+}
+
+#[test]
+fn filters_and_clamps() {
+    let mut queue: Queue<u16, U8, u8, SingleCore> = unsafe { Queue::u8_sc() };
+    let (producer, mut consumer) = queue.split();
+    let mut sampler = Sampler::new(&BUFFER, producer, Offset(100), VREF_CALIBRATION, FULL_SCALE);
+    assert_eq!(sampler.sample::<()>().unwrap(), 1600);
+    assert_eq!(sampler.filter().0, 100);
+
+    sampler.set_filter(Offset(-2000));
+    assert_eq!(sampler.sample::<()>().unwrap(), 0);
+    assert_eq!(consumer.dequeue(), Some(1600));
+    assert_eq!(consumer.dequeue(), Some(0));
+}
+
+#[test]
+fn reports_full_queue() {
+    let mut queue: Queue<u16, U8, u8, SingleCore> = unsafe { Queue::u8_sc() };
+    let (producer, _consumer) = queue.split();
+    let mut sampler = Sampler::new(&BUFFER, producer, Offset(0), VREF_CALIBRATION, FULL_SCALE);
+    let results: Vec<_> = (0..16).map(|_| sampler.sample::<()>()).collect();
+    assert!(results[..8].iter().all(|result| result.is_ok()));
+    assert!(matches!(results[15], Err(Error::Queue)));
+}
+
+#[test]
+fn injects_samples() {
+    let mut queue: Queue<u16, U8, u8, SingleCore> = unsafe { Queue::u8_sc() };
+    let (producer, mut consumer) = queue.split();
+    let mut sampler = Sampler::new(&BUFFER, producer, Offset(100), VREF_CALIBRATION, FULL_SCALE);
+    assert_eq!(sampler.inject::<()>(1500).unwrap(), 1600);
+    // What the ADC reads at 3 V supply
+    assert_eq!(sampler.input(), 2047);
+    assert_eq!(sampler.inject::<()>(3300).unwrap(), 3400);
+    assert_eq!(sampler.input(), FULL_SCALE);
+    // Both halves are taken in turn like with the ADC
+    assert_eq!(sampler.sample::<()>().unwrap(), 1600);
+    assert_eq!(consumer.dequeue(), Some(1600));
+    assert_eq!(consumer.dequeue(), Some(3400));
+}
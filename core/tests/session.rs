@@ -0,0 +1,68 @@
+use ecg_core::analysis::session::Session;
+use ecg_core::analysis::trend::{HrTrend, TrendPoint};
+
+const SAMPLE_RATE: u32 = 500;
+const MINUTE: u32 = 60 * SAMPLE_RATE;
+
+#[test]
+fn session_summary() {
+    let mut session = Session::new(SAMPLE_RATE);
+    assert!(!session.is_running());
+    assert_eq!(session.duration(1000), None);
+    // Nothing counts before the start
+    session.beat(Some(70), false);
+
+    session.start(1000);
+    session.beat(Some(60), false);
+    session.beat(None, true);
+    session.beat(Some(90), true);
+    session.add_events(2);
+    assert!(session.is_running());
+    assert_eq!(session.duration(1000 + MINUTE), Some(60));
+
+    session.stop(1000 + 2 * MINUTE);
+    session.beat(Some(200), false);
+    assert_eq!(session.duration(1000 + 10 * MINUTE), Some(120));
+    assert_eq!(session.beats(), 3);
+    assert_eq!(session.pvcs(), 2);
+    assert_eq!(session.events(), 2);
+    assert_eq!(session.rates(), Some((60, 75, 90)));
+
+    // A new start discards the previous session
+    session.start(0);
+    assert_eq!((session.beats(), session.rates()), (0, None));
+}
+
+#[test]
+fn trend_points_per_minute() {
+    let mut trend = HrTrend::new(SAMPLE_RATE);
+    assert!(!trend.update(0));
+    for bpm in [60, 70, 80].iter() {
+        trend.beat(*bpm);
+    }
+    assert!(!trend.update(MINUTE - 1));
+    assert!(trend.update(MINUTE));
+    // A minute without beats is a gap
+    assert!(trend.update(2 * MINUTE));
+    trend.beat(300);
+    trend.update(3 * MINUTE);
+
+    let points: Vec<Option<TrendPoint>> = trend.points().copied().collect();
+    let point = |min, mean, max| Some(TrendPoint { min, mean, max });
+    assert_eq!(points, [point(60, 70, 80), None, point(255, 255, 255)]);
+    assert_eq!(trend.len(), 3);
+}
+
+#[test]
+fn trend_catches_up_and_drops_the_oldest() {
+    let mut trend = HrTrend::new(SAMPLE_RATE);
+    assert!(trend.is_empty());
+    trend.update(0);
+    trend.beat(65);
+    // Several minutes closed at once
+    assert!(trend.update(5 * MINUTE + 10));
+    assert_eq!(trend.len(), 5);
+    trend.update(600 * MINUTE);
+    assert_eq!(trend.len(), 512);
+    assert!(trend.points().all(|point| point.is_none()));
+}
@@ -0,0 +1,138 @@
+use ecg_core::display::{Gain, RenderMode, SweepSpeed, Theme};
+use ecg_core::filter::{FilterMode, MainsFrequency, MainsRejection};
+use ecg_core::settings::{AutoScale, Record, Restored, Settings};
+use ecg_core::ui::Setting;
+
+// CRC-16/CCITT-FALSE, as the record is checked
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xffff;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+// Record of the version with the payload
+fn record(version: u8, payload: &[u8]) -> [u8; Record::SIZE] {
+    let mut record = [0xff; Record::SIZE];
+    record[0..2].copy_from_slice(&0xec61u16.to_le_bytes());
+    record[2] = version;
+    record[3] = payload.len() as u8;
+    let end = 4 + payload.len();
+    record[4..end].copy_from_slice(payload);
+    let crc = crc16(&record[..end]);
+    record[end..end + 2].copy_from_slice(&crc.to_le_bytes());
+    record
+}
+
+fn changed() -> Settings {
+    let mut settings = Settings::default();
+    for setting in Setting::ALL.iter() {
+        settings.change(*setting);
+    }
+    settings
+}
+
+#[test]
+fn change_steps_through_values() {
+    let settings = changed();
+    assert_eq!(settings.sweep_speed, SweepSpeed::Mm50);
+    assert_eq!(settings.gain, Gain::Mm20);
+    assert_eq!(settings.auto_scale, AutoScale::On);
+    assert_eq!(settings.render_mode, RenderMode::Sweep);
+    assert_eq!(settings.filter, FilterMode::Diagnostic);
+    assert_eq!(settings.mains, MainsFrequency::Hz60);
+    assert_eq!(settings.rejection, MainsRejection::Notch);
+    assert_eq!(settings.theme, Theme::Paper);
+    assert_eq!((settings.bradycardia, settings.tachycardia), (55, 130));
+
+    let mut settings = Settings::default();
+    for _ in 0..3 {
+        settings.change(Setting::Bradycardia);
+    }
+    // The limits wrap around
+    assert_eq!(settings.bradycardia, 40);
+}
+
+#[test]
+fn rhythm_limits() {
+    let config = changed().rhythm_config();
+    assert_eq!((config.bradycardia, config.severe_bradycardia), (55, 45));
+    assert_eq!((config.tachycardia, config.severe_tachycardia), (130, 160));
+}
+
+#[test]
+fn filter_changes() {
+    let settings = Settings::default();
+    let mut other = settings;
+    other.change(Setting::Theme);
+    assert!(settings.same_filter(&other));
+    other.change(Setting::Mains);
+    assert!(!settings.same_filter(&other));
+}
+
+#[test]
+fn round_trip() {
+    for settings in [Settings::default(), changed()].iter() {
+        assert_eq!(
+            Settings::decode(&settings.encode()),
+            (*settings, Restored::Current)
+        );
+    }
+    // Only a fit made since power-on can be locked
+    let mut locked = changed();
+    locked.change(Setting::AutoScale);
+    assert_eq!(locked.auto_scale, AutoScale::Locked);
+    let (decoded, _) = Settings::decode(&locked.encode());
+    assert_eq!(decoded.auto_scale, AutoScale::On);
+}
+
+#[test]
+fn corrupted_record() {
+    assert_eq!(Settings::decode(&[0xff; 2048]).1, Restored::Defaults);
+    assert_eq!(Settings::decode(&[]).1, Restored::Defaults);
+    for byte in 0..Record::SIZE {
+        let mut record = changed().encode();
+        record[byte] ^= 0x10;
+        let (settings, restored) = Settings::decode(&record);
+        // Bits flipped in the padding do not matter
+        if restored != Restored::Current {
+            assert_eq!(
+                (settings, restored),
+                (Settings::default(), Restored::Defaults)
+            );
+        }
+    }
+}
+
+#[test]
+fn migrates_older_records() {
+    // Version 0 ended with the mains rejection
+    let (settings, restored) = Settings::decode(&record(0, &[1, 1, 0]));
+    assert_eq!(restored, Restored::Migrated);
+    assert_eq!(settings.filter, FilterMode::Diagnostic);
+    assert_eq!(settings.mains, MainsFrequency::Hz60);
+    assert_eq!(settings.rejection, MainsRejection::Notch);
+    assert_eq!(settings.theme, Settings::default().theme);
+}
+
+#[test]
+fn ignores_unknown_values() {
+    // A newer layout with an extra field and a value this one does not know
+    let mut payload = changed().encode()[4..14].to_vec();
+    payload[7] = 9;
+    payload[8] = 47;
+    payload.push(3);
+    let (settings, restored) = Settings::decode(&record(2, &payload));
+    assert_eq!(restored, Restored::Migrated);
+    assert_eq!(settings.theme, Theme::Dark);
+    assert_eq!(settings.bradycardia, 50);
+    assert_eq!(settings.tachycardia, 130);
+}
@@ -0,0 +1,159 @@
+use ecg_core::display::TrendSpan;
+use ecg_core::ui::{Action, Buttons, Key, Navigator, Press, Screen, Setting};
+
+const TICK_RATE: u32 = 30;
+
+// Polls the buttons with the readings repeated for the number of ticks
+fn poll(buttons: &mut Buttons, readings: &[([bool; 3], usize)]) -> Vec<Press> {
+    let mut presses = Vec::new();
+    for (down, ticks) in readings {
+        for _ in 0..*ticks {
+            presses.extend(buttons.update(*down));
+        }
+    }
+    presses
+}
+
+const NONE: [bool; 3] = [false, false, false];
+const NEXT: [bool; 3] = [true, false, false];
+const SELECT: [bool; 3] = [false, true, false];
+
+#[test]
+fn short_and_long_presses() {
+    let mut buttons = Buttons::new(TICK_RATE);
+    let presses = poll(&mut buttons, &[(NEXT, 5), (NONE, 5)]);
+    assert_eq!(presses, [Press::Short(Key::Next)]);
+    // 800 ms, reported once while held and not on release
+    let presses = poll(&mut buttons, &[(SELECT, 40), (NONE, 5)]);
+    assert_eq!(presses, [Press::Long(Key::Select)]);
+}
+
+#[test]
+fn debounces_contacts() {
+    let mut buttons = Buttons::new(TICK_RATE);
+    let bouncing = [
+        (NEXT, 1),
+        (NONE, 1),
+        (NEXT, 1),
+        (NONE, 1),
+        (NEXT, 1),
+        (NONE, 5),
+    ];
+    assert!(poll(&mut buttons, &bouncing).is_empty());
+    let presses = poll(&mut buttons, &[(NEXT, 2), (NONE, 1), (NEXT, 1), (NONE, 2)]);
+    assert_eq!(presses, [Press::Short(Key::Next)]);
+}
+
+fn handle(navigator: &mut Navigator, presses: &[Press]) -> Vec<Option<Action>> {
+    presses
+        .iter()
+        .map(|press| navigator.handle(*press))
+        .collect()
+}
+
+const SHORT_NEXT: Press = Press::Short(Key::Next);
+const LONG_NEXT: Press = Press::Long(Key::Next);
+const SHORT_SELECT: Press = Press::Short(Key::Select);
+const LONG_SELECT: Press = Press::Long(Key::Select);
+const BACK: Press = Press::Short(Key::Back);
+
+#[test]
+fn steps_through_screens() {
+    let mut navigator = Navigator::new();
+    let actions = handle(
+        &mut navigator,
+        &[SHORT_NEXT, SHORT_NEXT, LONG_NEXT, LONG_NEXT],
+    );
+    assert_eq!(
+        actions,
+        [
+            Some(Action::Show(Screen::Trend(TrendSpan::Minutes30))),
+            Some(Action::Show(Screen::Hrv)),
+            Some(Action::Show(Screen::Trend(TrendSpan::Minutes30))),
+            Some(Action::Show(Screen::Waveform)),
+        ]
+    );
+    let actions = handle(&mut navigator, &[LONG_NEXT, BACK, BACK]);
+    assert_eq!(
+        actions,
+        [
+            Some(Action::Show(Screen::Recording)),
+            Some(Action::Show(Screen::Waveform)),
+            Some(Action::Mark),
+        ]
+    );
+}
+
+#[test]
+fn changes_settings() {
+    let mut navigator = Navigator::new();
+    handle(&mut navigator, &[SHORT_NEXT, SHORT_NEXT, SHORT_NEXT]);
+    assert_eq!(navigator.screen(), Screen::Settings);
+    let actions = handle(&mut navigator, &[SHORT_SELECT, SHORT_NEXT, SHORT_SELECT]);
+    assert_eq!(
+        actions,
+        [
+            Some(Action::Focus(Some(0))),
+            Some(Action::Focus(Some(1))),
+            Some(Action::Change(Setting::Gain)),
+        ]
+    );
+    // The focus wraps around and is released by back
+    let actions = handle(&mut navigator, &[LONG_NEXT, LONG_NEXT, LONG_SELECT]);
+    assert_eq!(
+        actions,
+        [
+            Some(Action::Focus(Some(0))),
+            Some(Action::Focus(Some(9))),
+            Some(Action::Focus(None)),
+        ]
+    );
+    assert_eq!(navigator.focus(), None);
+    assert_eq!(navigator.screen(), Screen::Settings);
+}
+
+#[test]
+fn steps_trend_span() {
+    let mut navigator = Navigator::new();
+    let actions = handle(&mut navigator, &[SHORT_NEXT, SHORT_SELECT, SHORT_SELECT]);
+    assert_eq!(
+        actions[2],
+        Some(Action::Show(Screen::Trend(TrendSpan::Hours2)))
+    );
+    assert_eq!(navigator.focus(), Some(0));
+}
+
+#[test]
+fn pans_frozen_trace() {
+    let mut navigator = Navigator::new();
+    let actions = handle(
+        &mut navigator,
+        &[SHORT_SELECT, SHORT_NEXT, LONG_NEXT, SHORT_SELECT, BACK],
+    );
+    assert_eq!(
+        actions,
+        [
+            Some(Action::Focus(Some(0))),
+            Some(Action::Pan(-1)),
+            Some(Action::Pan(-4)),
+            Some(Action::Pan(1)),
+            Some(Action::Focus(None)),
+        ]
+    );
+}
+
+#[test]
+fn screens_without_items() {
+    let mut navigator = Navigator::new();
+    handle(&mut navigator, &[SHORT_NEXT, SHORT_NEXT]);
+    assert_eq!(navigator.screen(), Screen::Hrv);
+    assert_eq!(handle(&mut navigator, &[SHORT_SELECT]), [None]);
+    handle(&mut navigator, &[SHORT_NEXT, SHORT_NEXT, SHORT_NEXT]);
+    assert_eq!(
+        handle(&mut navigator, &[SHORT_SELECT, SHORT_SELECT]),
+        [
+            Some(Action::Focus(Some(0))),
+            Some(Action::StartStopRecording)
+        ]
+    );
+}
@@ -0,0 +1,310 @@
+mod common;
+
+use heapless::consts::{U1024, U8};
+use heapless::spsc::{Consumer, Queue, SingleCore};
+
+use common::{match_beats, Recording};
+use ecg_core::analysis::qrs::QrsDetector;
+use ecg_core::error::Error;
+use ecg_core::filter::{EcgFilter, Filter, FilterMode, MainsFrequency, MainsRejection};
+use ecg_core::sampler::BASELINE_MV;
+use ecg_core::wfdb::{Annotation, Annotations, Format, Header, Playback, Samples, WfdbError};
+
+const MITDB_100: &str = "100 2 360 650000
+100.dat 212 200 11 1024 995 -22131 0 MLII
+100.dat 212 200 11 1024 1011 20052 0 V5
+# 69 M 1085 1629 x1
+# Aldomet, Inderal
+";
+
+struct Identity;
+
+impl Filter for Identity {
+    fn process(&mut self, sample: i32) -> i32 {
+        sample
+    }
+}
+
+// Interleaved samples of the signals packed in pairs into 3 bytes
+fn format_212(samples: &[i16]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for pair in samples.chunks(2) {
+        let first = pair[0] as u16 & 0xfff;
+        let second = pair.get(1).map_or(0, |sample| *sample as u16 & 0xfff);
+        data.push(first as u8);
+        data.push((first >> 8) as u8 | ((second >> 4) as u8 & 0xf0));
+        data.push(second as u8);
+    }
+    data
+}
+
+fn word(code: u8, value: u16) -> [u8; 2] {
+    ((code as u16) << 10 | value).to_le_bytes()
+}
+
+fn drain(consumer: &mut Consumer<u16, U1024, u8, SingleCore>) -> Vec<u16> {
+    let mut samples = Vec::new();
+    while let Some(sample) = consumer.dequeue() {
+        samples.push(sample);
+    }
+    samples
+}
+
+#[test]
+fn parses_the_header() {
+    let header = Header::parse(MITDB_100).unwrap();
+    assert_eq!(header.name, "100");
+    assert_eq!(header.sample_rate, 360);
+    assert_eq!(header.len, Some(650000));
+    assert_eq!(header.signals().len(), 2);
+    let signal = header.signals()[1];
+    assert_eq!(signal.file, "100.dat");
+    assert_eq!(signal.format, Format::Bits12);
+    assert_eq!((signal.gain_x1000, signal.baseline), (200_000, 1024));
+    assert_eq!(signal.units, "mV");
+    assert_eq!(signal.description, "V5");
+    assert_eq!(header.frame(0), Ok((0, 2)));
+    assert_eq!(header.frame(1), Ok((1, 2)));
+    assert_eq!(header.frame(2).unwrap_err(), WfdbError::Channel(2));
+}
+
+#[test]
+fn fills_in_the_defaults() {
+    let text = "# comment\n\nrec 3\nrec.dat 16+24 100(-5)/uV\nrec.dat 16\nother.dat 212 0 12 7 0 0 0 lead I\n";
+    let header = Header::parse(text).unwrap();
+    assert_eq!(header.sample_rate, 250);
+    assert_eq!(header.len, None);
+    let signals = header.signals();
+    assert_eq!(signals[0].format, Format::Bits16);
+    assert_eq!(signals[0].offset, 24);
+    assert_eq!((signals[0].gain_x1000, signals[0].baseline), (100_000, -5));
+    assert_eq!(signals[0].units, "uV");
+    assert_eq!((signals[1].gain_x1000, signals[1].baseline), (200_000, 0));
+    // Uncalibrated, the baseline is the ADC zero
+    assert_eq!((signals[2].gain_x1000, signals[2].baseline), (200_000, 7));
+    assert_eq!(signals[2].description, "lead I");
+    assert_eq!(header.frame(1), Ok((1, 2)));
+    assert_eq!(header.frame(2), Ok((0, 1)));
+}
+
+#[test]
+fn rejects_invalid_headers() {
+    assert_eq!(Header::parse("").err(), Some(WfdbError::Header(1)));
+    assert_eq!(Header::parse("rec x 360").err(), Some(WfdbError::Header(1)));
+    assert_eq!(
+        Header::parse("rec/2 1 360").err(),
+        Some(WfdbError::Header(1))
+    );
+    assert_eq!(
+        Header::parse("rec 2 360\nrec.dat 212").err(),
+        Some(WfdbError::Header(3))
+    );
+    assert_eq!(
+        Header::parse("rec 1 360\nrec.dat 8").err(),
+        Some(WfdbError::Format(8))
+    );
+    assert_eq!(
+        Header::parse("rec 1\nrec.dat 16 20x").err(),
+        Some(WfdbError::Header(2))
+    );
+    // Too small to keep
+    assert_eq!(
+        Header::parse("rec 1\nrec.dat 16 0.0001").err(),
+        Some(WfdbError::Header(2))
+    );
+}
+
+#[test]
+fn decodes_format_212() {
+    let header = Header::parse(MITDB_100).unwrap();
+    let signals = [1024, -1, 2047, -2048, 0, 995, 5, 6, 7, -7];
+    let data = format_212(&signals);
+    assert_eq!(data.len(), 15);
+
+    let first: Vec<i16> = Samples::new(&header, 0, &data).unwrap().collect();
+    assert_eq!(first, vec![1024, 2047, 0, 5, 7]);
+    let second: Vec<i16> = Samples::new(&header, 1, &data).unwrap().collect();
+    assert_eq!(second, vec![-1, -2048, 995, 6, -7]);
+}
+
+#[test]
+fn decodes_format_16() {
+    let header = Header::parse("rec 1 500\nrec.dat 16+2").unwrap();
+    let mut data = vec![0xff, 0xff];
+    for sample in [300i16, -300, i16::MAX, i16::MIN].iter() {
+        data.extend_from_slice(&sample.to_le_bytes());
+    }
+    data.push(1);
+    let mut samples = Samples::new(&header, 0, &data).unwrap();
+    assert_eq!(samples.remaining(), 4);
+    assert_eq!(samples.next(), Some(300));
+    assert_eq!(samples.collect::<Vec<_>>(), vec![-300, i16::MAX, i16::MIN]);
+}
+
+#[test]
+fn reads_annotations() {
+    let mut data = Vec::new();
+    data.extend_from_slice(&word(Annotation::NORMAL, 18));
+    // Rhythm change with its text, padded to an even length
+    data.extend_from_slice(&word(28, 0));
+    data.extend_from_slice(&word(63, 3));
+    data.extend_from_slice(b"(N\0\0");
+    // Interval longer than 10 bits
+    data.extend_from_slice(&word(59, 0));
+    data.extend_from_slice(&[0x01, 0x00, 0x70, 0x11]);
+    data.extend_from_slice(&word(Annotation::PVC, 2));
+    data.extend_from_slice(&word(62, 1));
+    data.extend_from_slice(&word(Annotation::NORMAL, 1023));
+    data.extend_from_slice(&word(0, 0));
+    data.extend_from_slice(&word(Annotation::NORMAL, 1));
+
+    let annotations: Vec<Annotation> = Annotations::new(&data).collect();
+    let indices: Vec<u32> = annotations.iter().map(|a| a.index).collect();
+    assert_eq!(
+        indices,
+        vec![18, 18, 18 + 0x1_1170 + 2, 18 + 0x1_1170 + 1025]
+    );
+    let labels: String = annotations.iter().map(|a| a.mnemonic()).collect();
+    assert_eq!(labels, "N+VN");
+    let beats: Vec<bool> = annotations.iter().map(|a| a.is_beat()).collect();
+    assert_eq!(beats, vec![true, false, true, true]);
+}
+
+#[test]
+fn converts_to_millivolts_at_the_adc() {
+    // 1 mV is 1100 mV after the front-end, clipped at the supply
+    let header = Header::parse("rec 1 500\nrec.dat 16 100(10)").unwrap();
+    let values: Vec<i16> = vec![10, 110, 60, -40, 310, 10];
+    let data: Vec<u8> = values
+        .iter()
+        .flat_map(|value| value.to_le_bytes().to_vec())
+        .collect();
+    let mut queue: Queue<u16, U1024, u8, SingleCore> = unsafe { Queue::u8_sc() };
+    let (producer, mut consumer) = queue.split();
+    let mut playback = Playback::new(&header, 0, &data, producer, Identity, 500).unwrap();
+
+    let mut played = Vec::new();
+    while let Some(sample) = playback.sample::<()>().unwrap() {
+        played.push(sample);
+    }
+    assert_eq!(played, vec![1650, 2750, 2200, 1100, 3300, 1650]);
+    assert_eq!(drain(&mut consumer), played);
+    assert_eq!(playback.sample::<()>().unwrap(), None);
+}
+
+#[test]
+fn keeps_fractional_gains() {
+    let header = Header::parse("rec 1 500\nrec.dat 16 5.5").unwrap();
+    assert_eq!(header.signals()[0].gain_x1000, 5500);
+    let data: Vec<u8> = [0i16, 1, -11]
+        .iter()
+        .flat_map(|value| value.to_le_bytes().to_vec())
+        .collect();
+    let mut queue: Queue<u16, U8, u8, SingleCore> = unsafe { Queue::u8_sc() };
+    let (producer, _consumer) = queue.split();
+    let mut playback = Playback::new(&header, 0, &data, producer, Identity, 500).unwrap();
+    let played: Vec<u16> = (0..3)
+        .map(|_| playback.sample::<()>().unwrap().unwrap())
+        .collect();
+    assert_eq!(played, vec![1650, 1850, 0]);
+}
+
+#[test]
+fn resamples_to_the_device_rate() {
+    // Ramp of 0.5 mV per second at the record rate
+    let header = Header::parse("rec 1 360\nrec.dat 16 720").unwrap();
+    let data: Vec<u8> = (0..720i16)
+        .flat_map(|value| value.to_le_bytes().to_vec())
+        .collect();
+    for rate in [250, 360, 500, 1000].iter() {
+        let mut queue: Queue<u16, U1024, u8, SingleCore> = unsafe { Queue::u8_sc() };
+        let (producer, mut consumer) = queue.split();
+        let mut playback = Playback::new(&header, 0, &data, producer, Identity, *rate).unwrap();
+        let mut played = Vec::new();
+        while let Some(sample) = playback.sample::<()>().unwrap() {
+            assert_eq!(consumer.dequeue(), Some(sample));
+            played.push(sample);
+        }
+        // Up to the last sample of the record
+        assert_eq!(played.len() as u32, 719 * rate / 360 + 1, "{} Hz", rate);
+        for (index, sample) in played.iter().enumerate() {
+            let mv = BASELINE_MV as u32 + 550 * index as u32 / rate;
+            assert!(
+                (*sample as i32 - mv as i32).abs() <= 2,
+                "{} Hz: {}",
+                rate,
+                index
+            );
+        }
+    }
+}
+
+#[test]
+fn reports_full_queue() {
+    let header = Header::parse("rec 1 500\nrec.dat 16").unwrap();
+    let data = [0; 64];
+    let mut queue: Queue<u16, U8, u8, SingleCore> = unsafe { Queue::u8_sc() };
+    let (producer, _consumer) = queue.split();
+    let mut playback = Playback::new(&header, 0, &data, producer, Identity, 500).unwrap();
+    let results: Vec<_> = (0..16).map(|_| playback.sample::<()>()).collect();
+    assert!(results[..8].iter().all(|result| result.is_ok()));
+    assert!(matches!(results[15], Err(Error::Queue)));
+}
+
+#[test]
+fn rejects_other_units() {
+    let header = Header::parse("rec 1 500\nrec.dat 16 100/uV").unwrap();
+    let mut queue: Queue<u16, U8, u8, SingleCore> = unsafe { Queue::u8_sc() };
+    let (producer, _consumer) = queue.split();
+    let playback = Playback::new(&header, 0, &[], producer, Identity, 500);
+    assert_eq!(playback.err(), Some(WfdbError::Units));
+}
+
+#[test]
+fn detects_the_annotated_beats() {
+    // Synthetic record at the MIT-BIH rate, played at the device rate
+    let recording = Recording::regular(360, 800, 30);
+    let values: Vec<i16> = recording
+        .samples
+        .iter()
+        .map(|mv| ((mv - 1500.0) * 200.0 / 1100.0).round() as i16 + 1024)
+        .collect();
+    let mut atr = Vec::new();
+    let mut last = 0;
+    for (beat, _) in &recording.beats {
+        atr.extend_from_slice(&word(Annotation::NORMAL, (beat - last) as u16));
+        last = *beat;
+    }
+    atr.extend_from_slice(&word(0, 0));
+
+    let header = Header::parse(MITDB_100).unwrap();
+    let mut interleaved = Vec::new();
+    for value in values {
+        interleaved.extend_from_slice(&[value, 1024]);
+    }
+    let data = format_212(&interleaved);
+    let mut queue: Queue<u16, U1024, u8, SingleCore> = unsafe { Queue::u8_sc() };
+    let (producer, mut consumer) = queue.split();
+    let filter = EcgFilter::new(
+        500,
+        FilterMode::Monitor,
+        MainsFrequency::Hz50,
+        MainsRejection::Adaptive,
+    );
+    let mut playback = Playback::new(&header, 0, &data, producer, filter, 500).unwrap();
+    let mut detector = QrsDetector::new(500);
+    let mut found = Vec::new();
+    while playback.sample::<()>().unwrap().is_some() {
+        let sample = consumer.dequeue().unwrap();
+        found.extend(detector.process(sample));
+    }
+
+    let reference: Vec<u32> = Annotations::new(&atr)
+        .filter(|annotation| annotation.is_beat())
+        .map(|annotation| annotation.index * 500 / 360)
+        .collect();
+    assert_eq!(reference.len(), 30);
+    let (matched, missed, extra) = match_beats(&reference, &found, 500, 1250);
+    assert!(matched > 20);
+    assert_eq!((missed, extra), (0, 0));
+}
@@ -0,0 +1,6 @@
+MEMORY
+{
+  /* The last 2K page keeps the settings, see `SettingsFlash` */
+  FLASH : ORIGIN = 0x08000000, LENGTH = 126K
+  RAM : ORIGIN = 0x20000000, LENGTH = 36K
+}
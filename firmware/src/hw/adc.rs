@@ -1,4 +1,5 @@
 use core::ops::Deref;
+use ecg_core::Buffer;
 use stm32g0xx_hal::analog::adc::{Adc as HalAdc, VRef};
 use stm32g0xx_hal::delay::Delay;
 use stm32g0xx_hal::dma::{Channel as DmaChannel, Direction, Event, Priority, WordSize};
@@ -10,7 +11,6 @@ use stm32g0xx_hal::time::Hertz;
 use volatile_register::RO;
 
 use crate::hw::timers::SampleTimer;
-use crate::Buffer;
 
 pub struct AdcConfig<I, C> {
     input: I,
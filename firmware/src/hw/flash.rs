@@ -0,0 +1,114 @@
+use core::ptr;
+use core::slice;
+use stm32g0xx_hal::stm32g0::stm32g070::FLASH;
+
+// Error bits of the status register after a failed erase or program
+#[derive(Debug)]
+pub struct FlashError(pub u32);
+
+// The last page of the flash, kept out of the program by `memory.x`.
+//
+// Erase and program stall every fetch from the flash, the erase for up to 40 ms, so
+// interrupts are late and samples are lost. Writes should be rare, e.g. when leaving
+// the settings menu.
+pub struct SettingsFlash {
+    flash: FLASH,
+}
+
+impl SettingsFlash {
+    const PAGE: u8 = 63;
+    const PAGE_SIZE: usize = 2048;
+    const ADDRESS: usize = 0x0800_0000 + SettingsFlash::PAGE as usize * SettingsFlash::PAGE_SIZE;
+    const KEY1: u32 = 0x4567_0123;
+    const KEY2: u32 = 0xcdef_89ab;
+    // OPERR, PROGERR, WRPERR, PGAERR, SIZERR, PGSERR, MISERR, FASTERR
+    const ERRORS: u32 = 0x0000_03fa;
+
+    pub fn new(flash: FLASH) -> Self {
+        SettingsFlash { flash }
+    }
+
+    pub fn read(&self) -> &'static [u8] {
+        unsafe {
+            slice::from_raw_parts(
+                SettingsFlash::ADDRESS as *const u8,
+                SettingsFlash::PAGE_SIZE,
+            )
+        }
+    }
+
+    // Erases the page and programs the data from its start, the data is padded by the
+    // erased value to whole double words
+    pub fn write(&mut self, data: &[u8]) -> Result<(), FlashError> {
+        self.unlock();
+        let result = self.erase().and_then(|_| self.program(data));
+        self.flash.cr.modify(|_, w| w.lock().set_bit());
+        result
+    }
+
+    fn unlock(&mut self) {
+        if self.flash.cr.read().lock().bit_is_set() {
+            self.flash
+                .keyr
+                .write(|w| unsafe { w.keyr().bits(SettingsFlash::KEY1) });
+            self.flash
+                .keyr
+                .write(|w| unsafe { w.keyr().bits(SettingsFlash::KEY2) });
+        }
+    }
+
+    fn erase(&mut self) -> Result<(), FlashError> {
+        self.wait_ready();
+        self.flash.cr.modify(|_, w| unsafe {
+            w.per().set_bit();
+            w.pnb().bits(SettingsFlash::PAGE)
+        });
+        self.flash.cr.modify(|_, w| w.strt().set_bit());
+        let result = self.finish();
+        self.flash.cr.modify(|_, w| w.per().clear_bit());
+        result
+    }
+
+    fn program(&mut self, data: &[u8]) -> Result<(), FlashError> {
+        self.wait_ready();
+        self.flash.cr.modify(|_, w| w.pg().set_bit());
+        let mut result = Ok(());
+        for (index, chunk) in data.chunks(8).enumerate() {
+            let mut double_word = [0xff; 8];
+            double_word[..chunk.len()].copy_from_slice(chunk);
+            let address = (SettingsFlash::ADDRESS + index * 8) as *mut u32;
+            // Both words in a row, the second one starts the programming
+            unsafe {
+                let [a, b, c, d, e, f, g, h] = double_word;
+                ptr::write_volatile(address, u32::from_le_bytes([a, b, c, d]));
+                ptr::write_volatile(address.add(1), u32::from_le_bytes([e, f, g, h]));
+            }
+            result = self.finish();
+            if result.is_err() {
+                break;
+            }
+        }
+        self.flash.cr.modify(|_, w| w.pg().clear_bit());
+        result
+    }
+
+    fn wait_ready(&self) {
+        while self.flash.sr.read().bsy().bit_is_set() {}
+        // Errors of a previous operation would block the next one
+        self.flash
+            .sr
+            .write(|w| unsafe { w.bits(SettingsFlash::ERRORS) });
+    }
+
+    fn finish(&mut self) -> Result<(), FlashError> {
+        while self.flash.sr.read().bsy().bit_is_set() {}
+        let errors = self.flash.sr.read().bits() & SettingsFlash::ERRORS;
+        // End of operation is only flagged with the interrupt enabled, cleared anyway
+        self.flash.sr.write(|w| unsafe { w.bits(errors | 1) });
+        if errors != 0 {
+            Err(FlashError(errors))
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -1,17 +1,17 @@
 use cortex_m::peripheral::SYST;
 use display_interface_parallel_gpio::PGPIO8BitInterface;
+use ecg_core::display::Layout;
 use stm32g0xx_hal::delay::Delay;
 use stm32g0xx_hal::dma::C1;
-use stm32g0xx_hal::gpio::gpioa::{PA0, PA4, PA5, PA6};
+use stm32g0xx_hal::gpio::gpioa::{PA0, PA11, PA12, PA4, PA5, PA6, PA7, PA8};
 use stm32g0xx_hal::gpio::gpiob::{PB0, PB1, PB2, PB3, PB4, PB5, PB6, PB7, PB8, PB9};
-use stm32g0xx_hal::gpio::{Analog, DefaultMode, Output, PushPull};
-use stm32g0xx_hal::prelude::OutputPin;
+use stm32g0xx_hal::gpio::{Analog, Floating, Input, Output, PullUp, PushPull};
+use stm32g0xx_hal::prelude::{InputPin, OutputPin};
 use stm32g0xx_hal::rcc::{Config, PllConfig, Rcc, RccExt};
 use stm32g0xx_hal::stm32g0::stm32g070::RCC;
 
 use crate::hw::adc::{Adc as HwAdc, Calibration};
 use crate::hw::lcd::{IliError, IliLcd};
-use crate::hw::timers::BeatCounterTimer;
 
 pub fn init_clock(pac_rcc: RCC) -> Rcc {
     // ((16 MHz / 4) * 32) / 2 = 64 MHz
@@ -43,8 +43,6 @@ type LcdWR = PB9<Output<PushPull>>;
 type DmaChannel = C1;
 // PA0 - ADC ECG input channel
 type InputChannel = PA0<Analog>;
-// PA6 - ECG beat counter input
-type CounterInput = PA6<DefaultMode>;
 
 // RESERVED for future use
 // PA1 - Comparator threshold - ADC input
@@ -55,9 +53,18 @@ type CounterInput = PA6<DefaultMode>;
 pub type LcdRst = PA4<Output<PushPull>>;
 // PA5 - LCD_RD (Read signal)
 pub type LcdRD = PA5<Output<PushPull>>;
+// PA6 - LO+ (DC lead-off detection of the front-end, optional)
+pub type LeadOffPlus = PA6<Input<Floating>>;
+// PA7 - LO- (DC lead-off detection of the front-end, optional)
+pub type LeadOffMinus = PA7<Input<Floating>>;
+// PA8 - Next button to GND
+pub type NextPin = PA8<Input<PullUp>>;
+// PA11 - Select button to GND
+pub type SelectPin = PA11<Input<PullUp>>;
+// PA12 - Back button to GND (optional)
+pub type BackPin = PA12<Input<PullUp>>;
 
 pub type Adc = HwAdc<InputChannel, DmaChannel>;
-pub type BeatCounter = BeatCounterTimer<CounterInput>;
 pub type LcdInterface =
     PGPIO8BitInterface<LcdD0, LcdD1, LcdD2, LcdD3, LcdD4, LcdD5, LcdD6, LcdD7, LcdDC, LcdWR>;
 pub type HwLcd = IliLcd<LcdInterface, LcdRst>;
@@ -66,12 +73,54 @@ pub fn init_lcd(
     interface: LcdInterface,
     lcd_rst: LcdRst,
     lcd_rd: LcdRD,
-    scroller_offset: (u16, u16),
+    layout: &Layout,
     delay: &mut Delay<SYST>,
 ) -> Result<HwLcd, IliError> {
     let mut lcd_rd = lcd_rd;
     lcd_rd.set_high().unwrap();
-    IliLcd::new(interface, lcd_rst, scroller_offset, delay)
+    IliLcd::new(interface, lcd_rst, layout, delay)
+}
+
+// Lead-off comparator outputs of the AD8232, high when an electrode is off
+pub struct LeadOffInput {
+    pins: Option<(LeadOffPlus, LeadOffMinus)>,
+}
+
+impl LeadOffInput {
+    pub fn new(pins: Option<(LeadOffPlus, LeadOffMinus)>) -> Self {
+        LeadOffInput { pins }
+    }
+
+    pub fn is_off(&self) -> bool {
+        match &self.pins {
+            Some((plus, minus)) => plus.is_high().unwrap() || minus.is_high().unwrap(),
+            None => false,
+        }
+    }
+}
+
+pub struct ButtonPins {
+    next: NextPin,
+    select: SelectPin,
+    back: Option<BackPin>,
+}
+
+impl ButtonPins {
+    pub fn new(next: NextPin, select: SelectPin, back: Option<BackPin>) -> Self {
+        ButtonPins { next, select, back }
+    }
+
+    // Next, select and back, true if pressed
+    pub fn read(&self) -> [bool; 3] {
+        [
+            self.next.is_low().unwrap(),
+            self.select.is_low().unwrap(),
+            match &self.back {
+                Some(back) => back.is_low().unwrap(),
+                None => false,
+            },
+        ]
+    }
 }
 
 pub fn get_calibration() -> u16 {
@@ -1,14 +1,16 @@
 use core::convert::Infallible;
 use display_interface_parallel_gpio::WriteOnlyDataCommand;
+use ecg_core::display::{Layout, Orientation, Panel};
+use ecg_core::lcd::Lcd;
 use embedded_graphics::drawable::Drawable;
 use embedded_graphics::pixelcolor::Rgb565;
 use embedded_graphics::DrawTarget;
-use ili9341::{DisplaySize320x480, Error, Ili9341, Orientation, Scroller};
+use ili9341::{
+    DisplaySize240x320, DisplaySize320x480, Error, Ili9341, Orientation as IliOrientation, Scroller,
+};
 use stm32g0xx_hal::hal::blocking::delay::DelayMs;
 use stm32g0xx_hal::hal::digital::v2::OutputPin;
 
-use crate::hw::Lcd;
-
 #[derive(Debug)]
 pub struct IliError(pub Error<Infallible>);
 
@@ -22,25 +24,26 @@ where
     I: WriteOnlyDataCommand,
     R: OutputPin<Error = Infallible>,
 {
-    pub fn new<D>(
-        interface: I,
-        reset: R,
-        scoller_offset: (u16, u16),
-        delay: &mut D,
-    ) -> Result<Self, IliError>
+    pub fn new<D>(interface: I, reset: R, layout: &Layout, delay: &mut D) -> Result<Self, IliError>
     where
         D: DelayMs<u16>,
     {
-        let mut ili = Ili9341::new(
-            interface,
-            reset,
-            delay,
-            Orientation::Landscape,
-            DisplaySize320x480,
-        )
+        let orientation = match layout.orientation() {
+            Orientation::Landscape => IliOrientation::Landscape,
+            Orientation::Portrait => IliOrientation::Portrait,
+        };
+        let mut ili = match layout.panel() {
+            Panel::Size320x240 => {
+                Ili9341::new(interface, reset, delay, orientation, DisplaySize240x320)
+            }
+            Panel::Size480x320 => {
+                Ili9341::new(interface, reset, delay, orientation, DisplaySize320x480)
+            }
+        }
         .map_err(IliError)?;
+        let (top, bottom) = layout.scroll_offsets();
         let scroller = ili
-            .configure_vertical_scroll(scoller_offset.0, scoller_offset.1)
+            .configure_vertical_scroll(top, bottom)
             .map_err(IliError)?;
 
         Ok(IliLcd { ili, scroller })
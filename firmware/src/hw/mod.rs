@@ -0,0 +1,11 @@
+mod adc;
+mod flash;
+mod helper;
+mod lcd;
+mod timers;
+
+pub use adc::AdcConfig;
+pub use flash::{FlashError, SettingsFlash};
+pub use helper::*;
+pub use lcd::IliError;
+pub use timers::FrameTimer;
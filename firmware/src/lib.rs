@@ -1,21 +1,19 @@
 #![no_std]
 
+#[cfg(feature = "hw")]
 use core::sync::atomic::{AtomicUsize, Ordering};
 
+#[cfg(feature = "hw")]
 use defmt_rtt as _; // global logger
+#[cfg(feature = "hw")]
 use panic_probe as _;
 
-pub mod display;
-pub mod error;
+#[cfg(feature = "hw")]
 pub mod hw;
-pub mod sampler;
-
-pub const TOP_SCROLL_OFFSET: u16 = display::Offset::LEFT as u16;
-pub const BOTTOM_SCROLL_OFFSET: u16 = display::Offset::RIGHT as u16;
-
-pub type Buffer = [u16; 4];
 
+#[cfg(feature = "hw")]
 static COUNT: AtomicUsize = AtomicUsize::new(0);
+#[cfg(feature = "hw")]
 defmt::timestamp!("{=usize}", {
     let n = COUNT.load(Ordering::Relaxed);
     COUNT.store(n + 1, Ordering::Relaxed);
@@ -23,6 +21,7 @@ defmt::timestamp!("{=usize}", {
 });
 
 /// Terminates the application and makes `probe-run` exit with exit-code = 0
+#[cfg(feature = "hw")]
 pub fn exit() -> ! {
     loop {
         cortex_m::asm::bkpt();
@@ -0,0 +1,515 @@
+#![no_main]
+#![no_std]
+
+use ecg as _;
+
+use cortex_m::singleton;
+use ecg::hw::{
+    get_calibration, init_clock, init_lcd, Adc, AdcConfig, ButtonPins, FrameTimer, HwLcd, IliError,
+    LcdInterface, LeadOffInput, SettingsFlash,
+};
+use ecg_core::analysis::contact::{Contact, ContactMonitor, LeadOff};
+use ecg_core::analysis::event_log::EventLog;
+use ecg_core::analysis::heart_rate::{Averaging, HeartRate};
+use ecg_core::analysis::hrv::Hrv;
+use ecg_core::analysis::pvc::{BeatClass, BeatClassifier, EctopyCounter};
+use ecg_core::analysis::qrs::QrsDetector;
+use ecg_core::analysis::quality::{QualityIndex, SignalQuality};
+use ecg_core::analysis::rhythm::{RhythmAnalyzer, RhythmEvent};
+use ecg_core::analysis::session::Session;
+use ecg_core::analysis::spectrum::{FrequencyMetrics, HrvSpectrum};
+use ecg_core::analysis::trend::HrTrend;
+use ecg_core::display::{Annotation, Display, Layout, Orientation, Panel};
+use ecg_core::ecgsyn::{EcgSyn, EcgSynConfig};
+use ecg_core::filter::EcgFilter;
+use ecg_core::sampler::Sampler;
+use ecg_core::settings::{Restored, Settings};
+use ecg_core::ui::{Action, Buttons, Navigator};
+use heapless::consts::{U64, U8};
+use heapless::spsc::{Consumer, Producer, Queue, SingleCore};
+use rtic::app;
+use stm32g0xx_hal::delay::DelayExt;
+use stm32g0xx_hal::dma::DmaExt;
+use stm32g0xx_hal::dmamux::DmaMuxIndex;
+use stm32g0xx_hal::gpio::{GpioExt, Speed};
+use stm32g0xx_hal::time::U32Ext;
+
+const SAMPLE_RATE: u32 = 500;
+const FRAME_RATE: u32 = 30;
+// Mains interference in millivolts at the ADC input that suggests poor electrode contact
+const INTERFERENCE_LIMIT: u16 = 50;
+// The warning clears only this far below the limit so it does not flicker
+const INTERFERENCE_HYSTERESIS: u16 = 10;
+// The spectrum takes a few milliseconds, LF/HF is refreshed only every few beats
+const SPECTRUM_BEATS: u8 = 16;
+// The front-end lead-off outputs are wired to PA6 and PA7
+const LEAD_OFF_PINS: bool = false;
+// Shows the synthetic ECG while the lead-off pins report no electrodes attached, or
+// all the time without the pins
+const DEMO: bool = false;
+// A third button on PA12 works as back, otherwise a long press of select
+const BACK_BUTTON: bool = true;
+const ADC_FULL_SCALE: u16 = 4095;
+const PANEL: Panel = Panel::Size480x320;
+// Portrait draws the trace in sweep mode, the controller scrolls only along the long side
+const ORIENTATION: Orientation = Orientation::Landscape;
+
+#[app(device = stm32g0xx_hal::stm32, peripherals = true)]
+const APP: () = {
+    struct Resources {
+        display: Display<'static, U64, HwLcd, IliError>,
+        sampler: Sampler<'static, U64, EcgFilter>,
+        frame_timer: FrameTimer,
+        adc: Adc,
+        contact: ContactMonitor,
+        quality: QualityIndex,
+        lead_off: LeadOffInput,
+        detector: QrsDetector,
+        classifier: BeatClassifier,
+        ectopy: EctopyCounter,
+        heart_rate: HeartRate,
+        hrv: Hrv,
+        spectrum: HrvSpectrum,
+        rhythm: RhythmAnalyzer,
+        trend: HrTrend,
+        events: EventLog,
+        session: Session,
+        navigator: Navigator,
+        buttons: Buttons,
+        button_pins: ButtonPins,
+        settings: Settings,
+        settings_flash: SettingsFlash,
+        // Settings to be written by idle
+        #[init(false)]
+        save_settings: bool,
+        // Sample index at which the sampling stalled for the flash write
+        #[init(None)]
+        flash_gap: Option<u32>,
+        beat_producer: Producer<'static, (u32, BeatClass), U8, u8, SingleCore>,
+        beat_consumer: Consumer<'static, (u32, BeatClass), U8, u8, SingleCore>,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> init::LateResources {
+        let core: rtic::export::Peripherals = cx.core;
+        let device: stm32g0xx_hal::stm32::Peripherals = cx.device;
+
+        // Buffers
+        let queue: &'static mut Queue<_, _, _, _> =
+            singleton!(: Queue<u16, U64, u8, SingleCore> = unsafe {Queue::u8_sc()}).unwrap();
+        let dma_buffer: &'static mut [u16; 4] = singleton!(: [u16; 4] = [0; 4]).unwrap();
+        let (producer, consumer) = queue.split();
+        let beat_queue: &'static mut Queue<_, _, _, _> =
+            singleton!(: Queue<(u32, BeatClass), U8, u8, SingleCore> = unsafe {Queue::u8_sc()})
+                .unwrap();
+        let (beat_producer, beat_consumer) = beat_queue.split();
+
+        // Clock
+        let mut rcc = init_clock(device.RCC);
+        let mut delay = core.SYST.delay(&mut rcc);
+
+        // Settings
+        let mut settings_flash = SettingsFlash::new(device.FLASH);
+        let (settings, restored) = Settings::decode(settings_flash.read());
+        match restored {
+            Restored::Current => {}
+            Restored::Migrated => {
+                defmt::info!("settings migrated");
+                if let Err(error) = settings_flash.write(&settings.encode()) {
+                    defmt::error!("settings not saved: {=u32}", error.0);
+                }
+            }
+            Restored::Defaults => defmt::warn!("settings missing or corrupted, using defaults"),
+        }
+
+        // GPIO
+        let gpioa = device.GPIOA.split(&mut rcc);
+        let gpiob = device.GPIOB.split(&mut rcc);
+
+        // LCD
+        let layout = Layout::new(PANEL, ORIENTATION);
+        let interface = LcdInterface::new(
+            gpiob.pb0.into_push_pull_output().set_speed(Speed::VeryHigh),
+            gpiob.pb1.into_push_pull_output().set_speed(Speed::VeryHigh),
+            gpiob.pb2.into_push_pull_output().set_speed(Speed::VeryHigh),
+            gpiob.pb3.into_push_pull_output().set_speed(Speed::VeryHigh),
+            gpiob.pb4.into_push_pull_output().set_speed(Speed::VeryHigh),
+            gpiob.pb5.into_push_pull_output().set_speed(Speed::VeryHigh),
+            gpiob.pb6.into_push_pull_output().set_speed(Speed::VeryHigh),
+            gpiob.pb7.into_push_pull_output().set_speed(Speed::VeryHigh),
+            gpiob.pb8.into_push_pull_output().set_speed(Speed::VeryHigh),
+            gpiob.pb9.into_push_pull_output().set_speed(Speed::VeryHigh),
+        );
+        let lcd = init_lcd(
+            interface,
+            gpioa.pa4.into_push_pull_output(),
+            gpioa.pa5.into_push_pull_output(),
+            &layout,
+            &mut delay,
+        )
+        .unwrap();
+        let display = Display::new(lcd, consumer, layout, SAMPLE_RATE, &settings).unwrap();
+        let frame_timer = FrameTimer::new(device.TIM6, FRAME_RATE.hz(), &mut rcc);
+
+        // Buttons
+        let back = if BACK_BUTTON {
+            Some(gpioa.pa12.into_pull_up_input())
+        } else {
+            None
+        };
+        let button_pins = ButtonPins::new(
+            gpioa.pa8.into_pull_up_input(),
+            gpioa.pa11.into_pull_up_input(),
+            back,
+        );
+        let buttons = Buttons::new(FRAME_RATE);
+        let navigator = Navigator::new();
+
+        // ADC
+        let dma = device.DMA.split(&mut rcc, device.DMAMUX);
+        let mut ch1 = dma.ch1;
+        ch1.mux().select_peripheral(DmaMuxIndex::ADC);
+        let adc = Adc::new(
+            device.ADC,
+            device.TIM1,
+            dma_buffer,
+            AdcConfig::new(gpioa.pa0, ch1, SAMPLE_RATE.hz()),
+            &mut rcc,
+            &mut delay,
+        );
+        let filter = EcgFilter::new(
+            SAMPLE_RATE,
+            settings.filter,
+            settings.mains,
+            settings.rejection,
+        );
+        let sampler = Sampler::new(
+            dma_buffer,
+            producer,
+            filter,
+            get_calibration(),
+            ADC_FULL_SCALE,
+        );
+
+        // Electrode contact
+        let contact = ContactMonitor::new(SAMPLE_RATE, ADC_FULL_SCALE);
+        let quality = QualityIndex::new(SAMPLE_RATE, ADC_FULL_SCALE);
+        let lead_off = if LEAD_OFF_PINS {
+            LeadOffInput::new(Some((
+                gpioa.pa6.into_floating_input(),
+                gpioa.pa7.into_floating_input(),
+            )))
+        } else {
+            LeadOffInput::new(None)
+        };
+
+        // Beat detection
+        let detector = QrsDetector::new(SAMPLE_RATE);
+        let classifier = BeatClassifier::new(SAMPLE_RATE);
+        let ectopy = EctopyCounter::new(SAMPLE_RATE);
+        let heart_rate = HeartRate::new(SAMPLE_RATE, Averaging::Beats4);
+        let hrv = Hrv::new(SAMPLE_RATE);
+        let spectrum = HrvSpectrum::new();
+        let rhythm = RhythmAnalyzer::new(SAMPLE_RATE, settings.rhythm_config());
+        let trend = HrTrend::new(SAMPLE_RATE);
+        let events = EventLog::new();
+        let session = Session::new(SAMPLE_RATE);
+
+        init::LateResources {
+            display,
+            sampler,
+            frame_timer,
+            adc,
+            contact,
+            quality,
+            lead_off,
+            detector,
+            classifier,
+            ectopy,
+            heart_rate,
+            hrv,
+            spectrum,
+            rhythm,
+            trend,
+            events,
+            session,
+            navigator,
+            buttons,
+            button_pins,
+            settings,
+            settings_flash,
+            beat_producer,
+            beat_consumer,
+        }
+    }
+
+    #[idle(resources = [frame_timer, adc, settings, settings_flash, save_settings, flash_gap, detector])]
+    fn idle(mut cx: idle::Context) -> ! {
+        cx.resources.frame_timer.lock(|timer: &mut FrameTimer| {
+            timer.start();
+        });
+        cx.resources.adc.lock(|adc: &mut Adc| {
+            adc.start();
+        });
+        loop {
+            let save = cx
+                .resources
+                .save_settings
+                .lock(|save: &mut bool| core::mem::replace(save, false));
+            if save {
+                // Out of the frame task, the samples lost to the stall are marked as a gap
+                let settings = cx
+                    .resources
+                    .settings
+                    .lock(|settings: &mut Settings| *settings);
+                let index = cx
+                    .resources
+                    .detector
+                    .lock(|detector: &mut QrsDetector| detector.index());
+                if let Err(error) = cx.resources.settings_flash.write(&settings.encode()) {
+                    defmt::error!("settings not saved: {=u32}", error.0);
+                }
+                cx.resources
+                    .flash_gap
+                    .lock(|gap: &mut Option<u32>| *gap = Some(index));
+            }
+            cortex_m::asm::nop();
+        }
+    }
+
+    #[task(binds = DMA_CHANNEL1, priority = 2, resources = [adc, sampler, contact, quality, lead_off, detector, classifier, beat_producer])]
+    fn dma(cx: dma::Context) {
+        static mut DEMO_SOURCE: Option<EcgSyn> = None;
+        static mut DROPPED_BEATS: u32 = 0;
+
+        let adc: &mut Adc = cx.resources.adc;
+        let sampler: &mut Sampler<'_, _, _> = cx.resources.sampler;
+        let contact: &mut ContactMonitor = cx.resources.contact;
+        let quality: &mut QualityIndex = cx.resources.quality;
+        let lead_off: &mut LeadOffInput = cx.resources.lead_off;
+        let detector: &mut QrsDetector = cx.resources.detector;
+        let classifier: &mut BeatClassifier = cx.resources.classifier;
+        let beats: &mut Producer<'_, _, _, _, _> = cx.resources.beat_producer;
+
+        adc.unpend();
+        let off = lead_off.is_off();
+        let demo = DEMO && (off || !LEAD_OFF_PINS);
+        let sample = if demo {
+            let source = DEMO_SOURCE
+                .get_or_insert_with(|| EcgSyn::new(SAMPLE_RATE, EcgSynConfig::default()));
+            sampler.inject::<IliError>(source.next().unwrap()).unwrap()
+        } else {
+            sampler.sample::<IliError>().unwrap()
+        };
+        let contact = contact.process(sampler.input(), sample, off && !demo);
+        quality.process(sampler.input(), sample);
+        classifier.push(sample);
+        if let Some(beat) = detector.process(sample) {
+            let class = classifier.classify(beat);
+            quality.beat(beat);
+            // Beats detected in noise without electrode contact are not reported
+            // The beat is dropped if the display task fell behind
+            if contact == Contact::Good && beats.enqueue((beat, class)).is_err() {
+                *DROPPED_BEATS = DROPPED_BEATS.wrapping_add(1);
+                defmt::warn!("beat queue full, {=u32} beats dropped", *DROPPED_BEATS);
+            }
+        }
+    }
+
+    #[task(binds = TIM6, priority = 1, resources = [display, frame_timer, heart_rate, hrv, spectrum, rhythm, trend, events, session, navigator, buttons, button_pins, settings, save_settings, flash_gap, ectopy, beat_consumer, sampler, detector, contact, quality])]
+    fn tim6(mut cx: tim6::Context) {
+        static mut BEATS: u8 = 0;
+        static mut FREQUENCY: Option<FrequencyMetrics> = None;
+        static mut TICKS: u32 = 0;
+        // Settings changed since they were saved
+        static mut CHANGED: bool = false;
+        // Sample at which the electrodes went off
+        static mut LEAD_OFF: Option<u32> = None;
+        // Sample of the last beat that updated the rate
+        static mut RATE_BEAT: Option<u32> = None;
+        static mut HUM: bool = false;
+
+        let frame_timer: &mut FrameTimer = cx.resources.frame_timer;
+        let display: &mut Display<'_, _, _, _> = cx.resources.display;
+        let heart_rate: &mut HeartRate = cx.resources.heart_rate;
+        let hrv: &mut Hrv = cx.resources.hrv;
+        let spectrum: &mut HrvSpectrum = cx.resources.spectrum;
+        let rhythm: &mut RhythmAnalyzer = cx.resources.rhythm;
+        let trend: &mut HrTrend = cx.resources.trend;
+        let events: &mut EventLog = cx.resources.events;
+        let session: &mut Session = cx.resources.session;
+        let navigator: &mut Navigator = cx.resources.navigator;
+        let buttons: &mut Buttons = cx.resources.buttons;
+        let button_pins: &mut ButtonPins = cx.resources.button_pins;
+        let settings: &mut Settings = cx.resources.settings;
+        let save_settings: &mut bool = cx.resources.save_settings;
+        let flash_gap: &mut Option<u32> = cx.resources.flash_gap;
+        let ectopy: &mut EctopyCounter = cx.resources.ectopy;
+        let beats: &mut Consumer<'_, _, _, _, _> = cx.resources.beat_consumer;
+
+        frame_timer.unpend();
+        let quality = cx
+            .resources
+            .quality
+            .lock(|quality: &mut QualityIndex| quality.quality());
+        // Beats from an unusable signal only end a pause, the rate, HRV and irregularity
+        // are not judged from them
+        let unusable = quality == Some(SignalQuality::Unusable);
+        display.update_quality(quality).unwrap();
+        while let Some((beat, class)) = beats.dequeue() {
+            if unusable {
+                heart_rate.reset();
+                hrv.interrupt();
+                record_rhythm(rhythm.interrupt(beat), events, session);
+                continue;
+            }
+            display.annotate(Annotation::beat(beat, class));
+            let bpm = heart_rate.beat(beat);
+            if let Some(bpm) = bpm {
+                *RATE_BEAT = Some(beat);
+                display.update_bpm(Some(bpm)).unwrap();
+                display.add_beat(beat, bpm);
+                trend.beat(bpm);
+            }
+            session.beat(bpm, class == BeatClass::Pvc);
+            record_rhythm(rhythm.beat(beat), events, session);
+            ectopy.beat(beat, class);
+            display
+                .update_pvc(ectopy.per_minute(), ectopy.pattern())
+                .unwrap();
+            hrv.beat(beat);
+            display.update_hrv(hrv.short_term()).unwrap();
+            *BEATS += 1;
+            if *BEATS >= SPECTRUM_BEATS {
+                *BEATS = 0;
+                *FREQUENCY = spectrum.analyze(hrv);
+                display
+                    .update_lf_hf(FREQUENCY.map(|metrics| metrics.ratio))
+                    .unwrap();
+            }
+        }
+        let contact = cx
+            .resources
+            .contact
+            .lock(|contact: &mut ContactMonitor| contact.contact());
+        let now = cx
+            .resources
+            .detector
+            .lock(|detector: &mut QrsDetector| detector.index());
+        match contact {
+            Contact::Good => {}
+            // A flat line may be asystole, the pause alarm keeps running from the last beat
+            Contact::LeadOff(LeadOff::FlatLine) => heart_rate.reset(),
+            Contact::LeadOff(_) => {
+                // The rhythm cannot be judged across the gap
+                heart_rate.reset();
+                record_rhythm(rhythm.reset(now), events, session);
+            }
+        }
+        display.update_contact(contact).unwrap();
+        match (contact, *LEAD_OFF) {
+            (Contact::LeadOff(_), None) => *LEAD_OFF = Some(now),
+            (Contact::Good, Some(start)) => {
+                *LEAD_OFF = None;
+                display.annotate(Annotation::lead_off(start, now));
+            }
+            _ => {}
+        }
+        record_rhythm(rhythm.update(now), events, session);
+        // A stale rate is not shown once the beats stopped for longer than a pause
+        let timeout = settings.rhythm_config().pause_ms * SAMPLE_RATE / 1000;
+        if heart_rate.rate().is_none()
+            || RATE_BEAT.map_or(true, |beat| now.wrapping_sub(beat) > timeout)
+        {
+            *RATE_BEAT = None;
+            display.update_bpm(None).unwrap();
+        }
+
+        let press = buttons.update(button_pins.read());
+        let action = press.and_then(|press| navigator.handle(press));
+        match action {
+            Some(Action::Show(screen)) => display.set_screen(screen, navigator.focus()).unwrap(),
+            Some(Action::Focus(focus)) => display.set_focus(focus).unwrap(),
+            Some(Action::Change(setting)) => {
+                let last = *settings;
+                settings.change(setting);
+                display.apply(settings).unwrap();
+                rhythm.set_config(settings.rhythm_config());
+                if !settings.same_filter(&last) {
+                    let filter = EcgFilter::new(
+                        SAMPLE_RATE,
+                        settings.filter,
+                        settings.mains,
+                        settings.rejection,
+                    );
+                    cx.resources
+                        .sampler
+                        .lock(|sampler: &mut Sampler<'_, _, EcgFilter>| sampler.set_filter(filter));
+                }
+                *CHANGED = true;
+            }
+            Some(Action::StartStopRecording) if session.is_running() => session.stop(now),
+            Some(Action::StartStopRecording) => session.start(now),
+            Some(Action::Pan(quarters)) => display.pan(quarters).unwrap(),
+            Some(Action::Mark) => {
+                defmt::info!("event marker at {=u32}", now);
+                display.annotate(Annotation::user(now));
+            }
+            None => {}
+        }
+        // Saved once the menu is left, writing the flash stalls the sampling
+        let left = matches!(action, Some(Action::Focus(None)) | Some(Action::Show(_)));
+        if *CHANGED && left {
+            *CHANGED = false;
+            *save_settings = true;
+        }
+        if let Some(index) = flash_gap.take() {
+            // The R-R intervals across the lost samples are too short
+            hrv.interrupt();
+            display.annotate(Annotation::gap(index));
+        }
+        // Charts are redrawn as a whole, text screens are updated every second
+        let minute = trend.update(now);
+        if action.is_some() || minute {
+            display.show_trend(trend).unwrap();
+        }
+        if action.is_some() {
+            display.show_settings(settings).unwrap();
+        }
+        *TICKS += 1;
+        if action.is_some() || *TICKS >= FRAME_RATE {
+            *TICKS = 0;
+            display
+                .show_hrv(hrv.short_term(), hrv.long_term(), *FREQUENCY)
+                .unwrap();
+            display.show_events(events).unwrap();
+            display.show_recording(session, now).unwrap();
+        }
+        display.update_alarm(rhythm.alarm()).unwrap();
+        let interference = cx
+            .resources
+            .sampler
+            .lock(|sampler: &mut Sampler<'_, _, EcgFilter>| sampler.filter().interference());
+        let limit = if *HUM {
+            INTERFERENCE_LIMIT - INTERFERENCE_HYSTERESIS
+        } else {
+            INTERFERENCE_LIMIT
+        };
+        *HUM = interference.unwrap_or(0) > limit;
+        display.update_interference_warning(*HUM).unwrap();
+        display.frame().unwrap();
+    }
+};
+
+fn record_rhythm(changes: &[RhythmEvent], events: &mut EventLog, session: &mut Session) {
+    session.add_events(events.record(changes));
+    for event in changes {
+        defmt::warn!(
+            "{=str} {=str}: {=u32} - {}",
+            event.rhythm.name(),
+            event.severity.name(),
+            event.start,
+            event.end
+        );
+    }
+}
@@ -0,0 +1,407 @@
+// Scores the beat detection against annotated records.
+//
+// Usage: benchmark <record directory> [baseline] [--save]
+//
+// Every WFDB record with an `.atr` reference annotation in the directory, e.g. the
+// MIT-BIH arrhythmia database, is played at the device sample rate through the filter,
+// the QRS detector and the heart rate averaging with the default settings. Detections
+// are matched to the reference beats within 150 ms and scored per ANSI/AAMI EC57: the
+// first 5 minutes of every record are left for learning and ventricular flutter
+// episodes are excluded. The heart rate error is the mean difference between the rate
+// of the detected and of the reference beats, sampled every second.
+//
+// With a baseline file the scores are compared to it and the exit code is 1 if any of
+// them got worse, `--save` writes the scores as the new baseline instead. Invalid
+// arguments, unreadable records and baseline files exit with 2.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use ecg_core::analysis::heart_rate::{Averaging, HeartRate};
+use ecg_core::analysis::qrs::QrsDetector;
+use ecg_core::filter::EcgFilter;
+use ecg_core::settings::Settings;
+use ecg_core::wfdb::{Annotations, Header, Playback};
+use heapless::consts::U8;
+use heapless::spsc::{Queue, SingleCore};
+
+// Sample rate of the device
+const SAMPLE_RATE: u32 = 500;
+const MATCH_WINDOW_MS: u32 = 150;
+const LEARNING_S: u32 = 300;
+// Allowed drop of Se and +P in percentage points and rise of the HR error in BPM
+const MAX_DROP: f64 = 0.05;
+const MAX_HR_RISE: f64 = 0.05;
+
+#[derive(Copy, Clone, Default)]
+struct Score {
+    true_positive: u32,
+    false_negative: u32,
+    false_positive: u32,
+    hr_error: f64,
+    hr_seconds: u32,
+}
+
+impl Score {
+    fn add(&mut self, other: &Score) {
+        self.true_positive += other.true_positive;
+        self.false_negative += other.false_negative;
+        self.false_positive += other.false_positive;
+        self.hr_error += other.hr_error;
+        self.hr_seconds += other.hr_seconds;
+    }
+
+    fn sensitivity(&self) -> Option<f64> {
+        ratio(self.true_positive, self.true_positive + self.false_negative)
+    }
+
+    fn predictivity(&self) -> Option<f64> {
+        ratio(self.true_positive, self.true_positive + self.false_positive)
+    }
+
+    fn hr_error(&self) -> Option<f64> {
+        mean(self.hr_error, self.hr_seconds as usize)
+    }
+
+    // Se, +P and the HR error
+    fn metrics(&self) -> [Option<f64>; 3] {
+        [self.sensitivity(), self.predictivity(), self.hr_error()]
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let save = args.iter().any(|arg| arg == "--save");
+    let paths: Vec<&String> = args[1..].iter().filter(|arg| *arg != "--save").collect();
+    if paths.is_empty() || paths.len() > 2 || (save && paths.len() < 2) {
+        eprintln!("Usage: {} <record directory> [baseline] [--save]", args[0]);
+        process::exit(2);
+    }
+
+    let records = find_records(paths[0]);
+    if records.is_empty() {
+        fail(&format!("No annotated records in {}", paths[0]));
+    }
+    println!(
+        "{:<8} {:>7} {:>7} {:>7} {:>7} {:>7} {:>7} {:>7}",
+        "Record", "Beats", "TP", "FN", "FP", "Se %", "+P %", "HR err"
+    );
+    let mut scores = Vec::new();
+    let mut gross = Score::default();
+    for (name, path) in &records {
+        let score = score_record(path);
+        print_row(name, Some(&score), score.metrics());
+        gross.add(&score);
+        scores.push((name.clone(), score.metrics()));
+    }
+    println!();
+    print_row("Gross", Some(&gross), gross.metrics());
+    // Every record counts the same regardless of its number of beats
+    let mut average = [None; 3];
+    for (metric, value) in average.iter_mut().enumerate() {
+        let values: Vec<f64> = scores.iter().filter_map(|(_, m)| m[metric]).collect();
+        *value = mean(values.iter().sum(), values.len());
+    }
+    print_row("Average", None, average);
+    scores.push(("gross".into(), gross.metrics()));
+
+    match paths.get(1) {
+        Some(path) if save => {
+            write_baseline(path, &scores);
+            println!("Baseline saved to {}", path);
+        }
+        Some(path) => {
+            let regressions = compare(&read_baseline(path), &scores);
+            if regressions > 0 {
+                eprintln!("{} scores are worse than the baseline", regressions);
+                process::exit(1);
+            }
+            println!("No regression against {}", path);
+        }
+        None => {}
+    }
+}
+
+// Headers of the records with a reference annotation, sorted by name
+fn find_records(directory: &str) -> Vec<(String, PathBuf)> {
+    let entries = fs::read_dir(directory)
+        .unwrap_or_else(|err| fail(&format!("Cannot read {}: {}", directory, err)));
+    let mut records: Vec<(String, PathBuf)> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("hea"))
+        .filter(|path| path.with_extension("atr").exists())
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_string_lossy().into_owned();
+            Some((name, path))
+        })
+        .collect();
+    records.sort();
+    records
+}
+
+fn score_record(path: &Path) -> Score {
+    let text = String::from_utf8(read(path))
+        .unwrap_or_else(|_| fail(&format!("Invalid header {}", path.display())));
+    let header = Header::parse(&text)
+        .unwrap_or_else(|err| fail(&format!("Invalid header {}: {:?}", path.display(), err)));
+    let signal = header
+        .signals()
+        .first()
+        .unwrap_or_else(|| fail(&format!("No signal in {}", path.display())));
+    let data = read(&path.with_file_name(signal.file));
+    let atr = read(&path.with_extension("atr"));
+
+    // Reference beats and ventricular flutter episodes at the device sample rate
+    let rescale =
+        |index: u32| (index as u64 * SAMPLE_RATE as u64 / header.sample_rate as u64) as u32;
+    let mut reference = Vec::new();
+    let mut excluded = Vec::new();
+    let mut flutter = None;
+    for annotation in Annotations::new(&atr) {
+        let index = rescale(annotation.index);
+        match annotation.mnemonic() {
+            '[' => flutter = Some(index),
+            ']' => excluded.extend(flutter.take().map(|start| (start, index))),
+            _ if annotation.is_beat() => reference.push(index),
+            _ => {}
+        }
+    }
+    excluded.extend(flutter.map(|start| (start, u32::MAX)));
+
+    let (detected, end) = detect(&header, &data, path);
+    let learning = LEARNING_S * SAMPLE_RATE;
+    let scored = |index: &u32| {
+        *index >= learning
+            && !excluded
+                .iter()
+                .any(|(start, end)| (*start..=*end).contains(index))
+    };
+    let reference_scored: Vec<u32> = reference.iter().copied().filter(scored).collect();
+    let detected_scored: Vec<u32> = detected.iter().copied().filter(scored).collect();
+    let mut score = match_beats(&reference_scored, &detected_scored);
+
+    // Rates after every second, the heart rates see all of the beats
+    let mut reference_rate = Rate::new(&reference);
+    let mut detected_rate = Rate::new(&detected);
+    for second in (learning..end).step_by(SAMPLE_RATE as usize) {
+        let rates = (reference_rate.at(second), detected_rate.at(second));
+        if let (Some(reference), Some(detected)) = rates {
+            if scored(&second) {
+                score.hr_error += (reference as f64 - detected as f64).abs();
+                score.hr_seconds += 1;
+            }
+        }
+    }
+    score
+}
+
+// Detected beats and the number of played samples
+fn detect(header: &Header, data: &[u8], path: &Path) -> (Vec<u32>, u32) {
+    let settings = Settings::default();
+    let filter = EcgFilter::new(
+        SAMPLE_RATE,
+        settings.filter,
+        settings.mains,
+        settings.rejection,
+    );
+    let mut queue: Queue<u16, U8, u8, SingleCore> = unsafe { Queue::u8_sc() };
+    let (producer, mut consumer) = queue.split();
+    let mut playback = Playback::new(header, 0, data, producer, filter, SAMPLE_RATE)
+        .unwrap_or_else(|err| fail(&format!("Cannot play {}: {:?}", path.display(), err)));
+    let mut detector = QrsDetector::new(SAMPLE_RATE);
+    let mut beats = Vec::new();
+    let mut len = 0;
+    while let Some(sample) = playback
+        .sample::<()>()
+        .unwrap_or_else(|_| fail("Queue full"))
+    {
+        consumer.dequeue();
+        len += 1;
+        beats.extend(detector.process(sample));
+    }
+    (beats, len)
+}
+
+// Every reference beat is matched to the nearest unmatched detection within the
+// window, unmatched detections are false positives
+fn match_beats(reference: &[u32], detected: &[u32]) -> Score {
+    let window = MATCH_WINDOW_MS * SAMPLE_RATE / 1000;
+    let mut matched = vec![false; detected.len()];
+    let mut first = 0;
+    let mut score = Score::default();
+    for beat in reference {
+        while first < detected.len() && detected[first] + window < *beat {
+            first += 1;
+        }
+        let nearest = (first..detected.len())
+            .take_while(|position| detected[*position] <= beat + window)
+            .filter(|position| !matched[*position])
+            .min_by_key(|position| (detected[*position] as i64 - *beat as i64).abs());
+        match nearest {
+            Some(position) => {
+                matched[position] = true;
+                score.true_positive += 1;
+            }
+            None => score.false_negative += 1,
+        }
+    }
+    score.false_positive = matched.iter().filter(|matched| !**matched).count() as u32;
+    score
+}
+
+// Heart rate of the device from a sequence of beats
+struct Rate<'a> {
+    beats: &'a [u32],
+    heart_rate: HeartRate,
+    bpm: Option<u16>,
+}
+
+impl<'a> Rate<'a> {
+    fn new(beats: &'a [u32]) -> Self {
+        Rate {
+            beats,
+            heart_rate: HeartRate::new(SAMPLE_RATE, Averaging::Beats4),
+            bpm: None,
+        }
+    }
+
+    // Rate shown at the sample, `index` has to grow
+    fn at(&mut self, index: u32) -> Option<u16> {
+        while let Some((beat, rest)) = self.beats.split_first() {
+            if *beat > index {
+                break;
+            }
+            self.beats = rest;
+            if let Some(bpm) = self.heart_rate.beat(*beat) {
+                self.bpm = Some(bpm);
+            }
+        }
+        self.bpm
+    }
+}
+
+// The counts are left out for the average
+fn print_row(name: &str, score: Option<&Score>, metrics: [Option<f64>; 3]) {
+    let counts = score.map_or([None; 4], |score| {
+        [
+            Some(score.true_positive + score.false_negative),
+            Some(score.true_positive),
+            Some(score.false_negative),
+            Some(score.false_positive),
+        ]
+    });
+    let counts: Vec<String> = counts
+        .iter()
+        .map(|count| count.map_or(String::new(), |count| count.to_string()))
+        .collect();
+    println!(
+        "{:<8} {:>7} {:>7} {:>7} {:>7} {:>7} {:>7} {:>7}",
+        name,
+        counts[0],
+        counts[1],
+        counts[2],
+        counts[3],
+        format_metric(metrics[0]),
+        format_metric(metrics[1]),
+        format_metric(metrics[2])
+    );
+}
+
+// Counts scores that got worse than the baseline, records missing in the run count too
+fn compare(baseline: &[(String, [Option<f64>; 3])], scores: &[(String, [Option<f64>; 3])]) -> u32 {
+    let names = ["Se", "+P", "HR error"];
+    let mut regressions = 0;
+    for (name, expected) in baseline {
+        let actual = match scores.iter().find(|(other, _)| other == name) {
+            Some((_, actual)) => actual,
+            None => {
+                eprintln!("{}: missing", name);
+                regressions += 1;
+                continue;
+            }
+        };
+        for (metric, metric_name) in names.iter().enumerate() {
+            let worse = match (expected[metric], actual[metric]) {
+                (Some(expected), Some(actual)) if metric < 2 => actual < expected - MAX_DROP,
+                (Some(expected), Some(actual)) => actual > expected + MAX_HR_RISE,
+                (Some(_), None) => true,
+                (None, _) => false,
+            };
+            if worse {
+                eprintln!(
+                    "{}: {} {} in the baseline, now {}",
+                    name,
+                    metric_name,
+                    format_metric(expected[metric]),
+                    format_metric(actual[metric])
+                );
+                regressions += 1;
+            }
+        }
+    }
+    regressions
+}
+
+// Lines of `<record> <Se> <+P> <HR error>`, `-` for a score without beats
+fn write_baseline(path: &str, scores: &[(String, [Option<f64>; 3])]) {
+    let mut text = String::from("# record Se% +P% HR-error\n");
+    for (name, metrics) in scores {
+        let metrics: Vec<String> = metrics
+            .iter()
+            .map(|metric| format_metric(*metric))
+            .collect();
+        text.push_str(&format!("{} {}\n", name, metrics.join(" ")));
+    }
+    fs::write(path, text).unwrap_or_else(|err| fail(&format!("Cannot write {}: {}", path, err)));
+}
+
+fn read_baseline(path: &str) -> Vec<(String, [Option<f64>; 3])> {
+    String::from_utf8(read(Path::new(path)))
+        .unwrap_or_else(|_| fail(&format!("Invalid baseline {}", path)))
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let invalid = || fail(&format!("Invalid baseline line: {}", line));
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 4 {
+                invalid();
+            }
+            let mut metrics = [None; 3];
+            for (metric, field) in metrics.iter_mut().zip(&fields[1..]) {
+                if *field != "-" {
+                    *metric = Some(field.parse().unwrap_or_else(|_| invalid()));
+                }
+            }
+            (fields[0].to_string(), metrics)
+        })
+        .collect()
+}
+
+// Percentage
+fn ratio(part: u32, whole: u32) -> Option<f64> {
+    mean(part as f64 * 100.0, whole as usize)
+}
+
+fn mean(sum: f64, count: usize) -> Option<f64> {
+    match count {
+        0 => None,
+        count => Some(sum / count as f64),
+    }
+}
+
+fn format_metric(metric: Option<f64>) -> String {
+    metric.map_or("-".into(), |metric| format!("{:.2}", metric))
+}
+
+fn read(path: &Path) -> Vec<u8> {
+    fs::read(path).unwrap_or_else(|err| fail(&format!("Cannot read {}: {}", path.display(), err)))
+}
+
+// Exits with 2, CI tells errors apart from regressions by it
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(2);
+}
@@ -0,0 +1,73 @@
+// Offline HRV analysis with the same code as the device.
+//
+// Reads R-R intervals in milliseconds, one per line, from the given file or stdin
+// and prints time-domain metrics of the last 1 and 5 minutes and the LF/HF power.
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::process;
+
+use ecg_core::analysis::hrv::{Hrv, HrvMetrics};
+use ecg_core::analysis::spectrum::HrvSpectrum;
+
+fn main() {
+    let input: Box<dyn BufRead> = match env::args().nth(1) {
+        Some(path) => match File::open(&path) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(err) => {
+                eprintln!("Cannot open {}: {}", path, err);
+                process::exit(1);
+            }
+        },
+        None => Box::new(BufReader::new(io::stdin())),
+    };
+
+    // Beat indices in ms
+    let mut hrv = Hrv::new(1000);
+    let mut time = 0u32;
+    hrv.beat(time);
+    for (number, line) in input.lines().enumerate() {
+        let line = line.unwrap_or_else(|err| {
+            eprintln!("Read failed: {}", err);
+            process::exit(1);
+        });
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.parse::<f64>() {
+            Ok(interval) if interval > 0.0 => {
+                time = time.wrapping_add(interval.round() as u32);
+                hrv.beat(time);
+            }
+            _ => {
+                eprintln!("Invalid interval on line {}: {}", number + 1, line);
+                process::exit(1);
+            }
+        }
+    }
+
+    print_metrics("1 min", hrv.short_term());
+    print_metrics("5 min", hrv.long_term());
+    match HrvSpectrum::new().analyze(&hrv) {
+        Some(metrics) => println!(
+            "LF {} ms^2, HF {} ms^2, LF/HF {}.{:02}",
+            metrics.lf,
+            metrics.hf,
+            metrics.ratio / 100,
+            metrics.ratio % 100
+        ),
+        None => println!("LF/HF: not enough data, at least 256 s are needed"),
+    }
+}
+
+fn print_metrics(window: &str, metrics: Option<HrvMetrics>) {
+    match metrics {
+        Some(metrics) => println!(
+            "{}: {} NN, mean {} ms, SDNN {} ms, RMSSD {} ms, pNN50 {} %",
+            window, metrics.count, metrics.mean_nn, metrics.sdnn, metrics.rmssd, metrics.pnn50
+        ),
+        None => println!("{}: not enough data", window),
+    }
+}
@@ -0,0 +1,161 @@
+// Validation of the beat classifier against annotated recordings.
+//
+// Usage: pvc <samples> <annotations> [sample rate, default 500]
+//
+// Samples are the ADC input in millivolts, one per line. Annotations are lines of
+// `<sample index> <label>`, `N` is a normal beat, `V` a PVC and any other label a beat
+// of a different kind. The samples are filtered the same way as on the device, beats
+// are matched to the annotations within 150 ms.
+
+use std::env;
+use std::fs;
+use std::process;
+
+use ecg_core::analysis::pvc::{BeatClass, BeatClassifier, EctopyCounter};
+use ecg_core::analysis::qrs::QrsDetector;
+use ecg_core::filter::{EcgFilter, Filter, FilterMode, MainsFrequency, MainsRejection};
+
+#[derive(Copy, Clone, PartialEq)]
+enum Label {
+    Normal,
+    Pvc,
+    Other,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        eprintln!("Usage: {} <samples> <annotations> [sample rate]", args[0]);
+        process::exit(2);
+    }
+    let sample_rate = match args.get(3).map(|rate| rate.parse::<u32>()) {
+        Some(Ok(rate)) => rate,
+        Some(Err(_)) => fail("Invalid sample rate"),
+        None => 500,
+    };
+    let samples = read_samples(&args[1]);
+    let annotations = read_annotations(&args[2]);
+
+    let mut filter = EcgFilter::new(
+        sample_rate,
+        FilterMode::Monitor,
+        MainsFrequency::Hz50,
+        MainsRejection::Adaptive,
+    );
+    let mut detector = QrsDetector::new(sample_rate);
+    let mut classifier = BeatClassifier::new(sample_rate);
+    let mut ectopy = EctopyCounter::new(sample_rate);
+    let mut beats = Vec::new();
+    let mut max_per_minute = 0;
+    for sample in samples {
+        let sample = filter.process(sample).clamp(0, u16::MAX as i32) as u16;
+        classifier.push(sample);
+        if let Some(beat) = detector.process(sample) {
+            let class = classifier.classify(beat);
+            ectopy.beat(beat, class);
+            max_per_minute = max_per_minute.max(ectopy.per_minute());
+            if let Some(pattern) = ectopy.pattern() {
+                println!("{:>8} {}", beat, pattern.name());
+            }
+            beats.push((beat, class));
+        }
+    }
+
+    // Rows are the annotations, columns the classification
+    let mut matrix = [[0u32; 4]; 3];
+    let tolerance = 150 * sample_rate / 1000;
+    let mut matched = vec![false; beats.len()];
+    for (index, label) in annotations.iter() {
+        let beat = beats
+            .iter()
+            .enumerate()
+            .filter(|(position, (beat, _))| {
+                !matched[*position] && beat.max(index) - beat.min(index) <= tolerance
+            })
+            .min_by_key(|(_, (beat, _))| beat.max(index) - beat.min(index));
+        let column = match beat {
+            Some((position, (_, class))) => {
+                matched[position] = true;
+                match class {
+                    BeatClass::Normal => 0,
+                    BeatClass::Pvc => 1,
+                    BeatClass::Unknown => 2,
+                }
+            }
+            // Missed beat
+            None => 3,
+        };
+        matrix[*label as usize][column] += 1;
+    }
+    let false_beats = matched.iter().filter(|matched| !**matched).count();
+
+    println!();
+    println!("          Normal     PVC Unknown  Missed");
+    for (label, row) in ["N", "V", "Other"].iter().zip(matrix.iter()) {
+        println!(
+            "{:<7} {:>8} {:>7} {:>7} {:>7}",
+            label, row[0], row[1], row[2], row[3]
+        );
+    }
+    println!("False detections: {}", false_beats);
+
+    let true_pvc = matrix[Label::Pvc as usize][1];
+    let annotated_pvc: u32 = matrix[Label::Pvc as usize].iter().sum();
+    let detected_pvc: u32 = matrix.iter().map(|row| row[1]).sum();
+    println!(
+        "PVC Se {}, +P {}, at most {} per minute",
+        percent(true_pvc, annotated_pvc),
+        percent(true_pvc, detected_pvc),
+        max_per_minute
+    );
+}
+
+fn percent(part: u32, whole: u32) -> String {
+    if whole == 0 {
+        "-".into()
+    } else {
+        format!("{:.1} %", part as f64 * 100.0 / whole as f64)
+    }
+}
+
+fn read_samples(path: &str) -> Vec<i32> {
+    read(path)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            line.parse()
+                .unwrap_or_else(|_| fail(&format!("Invalid sample: {}", line)))
+        })
+        .collect()
+}
+
+fn read_annotations(path: &str) -> Vec<(u32, Label)> {
+    read(path)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let mut fields = line.split_whitespace();
+            let index = fields.next().and_then(|index| index.parse().ok());
+            let label = match fields.next() {
+                Some("N") => Label::Normal,
+                Some("V") => Label::Pvc,
+                _ => Label::Other,
+            };
+            match index {
+                Some(index) => (index, label),
+                None => fail(&format!("Invalid annotation: {}", line)),
+            }
+        })
+        .collect()
+}
+
+fn read(path: &str) -> String {
+    fs::read_to_string(path).unwrap_or_else(|err| fail(&format!("Cannot read {}: {}", path, err)))
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
@@ -0,0 +1,201 @@
+// Renders the display of a recording to images with the same code as the device.
+//
+// Usage: simulator <samples | record.hea> <output prefix> [sample rate, default 500]
+//                  [snapshot interval in seconds, default 10]
+//
+// Samples are the ADC input in millivolts, one per line, or the first signal of a WFDB
+// record resampled to the sample rate. They are filtered and analyzed like on the
+// device and drawn frame by frame into an in-memory 480x320 panel with the default
+// settings. A snapshot of the screen is written as `<prefix>-<seconds>.ppm` every
+// interval and after the last sample.
+
+use std::env;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::process;
+use std::vec;
+
+use ecg_core::analysis::heart_rate::{Averaging, HeartRate};
+use ecg_core::analysis::pvc::BeatClassifier;
+use ecg_core::analysis::qrs::QrsDetector;
+use ecg_core::display::{Annotation, Display, Layout, Orientation, Panel};
+use ecg_core::filter::{EcgFilter, Filter};
+use ecg_core::lcd::FrameBuffer;
+use ecg_core::settings::Settings;
+use ecg_core::wfdb::{Header, Playback};
+use embedded_graphics::pixelcolor::RgbColor;
+use heapless::consts::U128;
+use heapless::spsc::{Producer, Queue, SingleCore};
+
+const FRAME_RATE: u32 = 30;
+
+enum Source<'a, 'b> {
+    Samples {
+        samples: vec::IntoIter<i32>,
+        filter: EcgFilter,
+        producer: Producer<'a, u16, U128, u8, SingleCore>,
+    },
+    Record(Playback<'a, 'b, U128, EcgFilter>),
+}
+
+impl<'a, 'b> Source<'a, 'b> {
+    // Filtered sample pushed to the display, `None` after the last one
+    fn next(&mut self) -> Option<u16> {
+        match self {
+            Source::Samples {
+                samples,
+                filter,
+                producer,
+            } => {
+                let sample = filter.process(samples.next()?).clamp(0, u16::MAX as i32) as u16;
+                producer
+                    .enqueue(sample)
+                    .unwrap_or_else(|_| fail("Queue full"));
+                Some(sample)
+            }
+            Source::Record(playback) => playback
+                .sample::<()>()
+                .unwrap_or_else(|_| fail("Queue full")),
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        eprintln!(
+            "Usage: {} <samples | record.hea> <output prefix> [sample rate] [interval]",
+            args[0]
+        );
+        process::exit(2);
+    }
+    let sample_rate = match args.get(3).map(|rate| rate.parse::<u32>()) {
+        Some(Ok(rate)) if rate >= FRAME_RATE => rate,
+        Some(_) => fail("Invalid sample rate"),
+        None => 500,
+    };
+    let interval = match args.get(4).map(|interval| interval.parse::<u32>()) {
+        Some(Ok(interval)) if interval > 0 => interval,
+        Some(_) => fail("Invalid interval"),
+        None => 10,
+    };
+    let prefix = &args[2];
+
+    let settings = Settings::default();
+    let mut queue: Queue<u16, U128, u8, SingleCore> = unsafe { Queue::u8_sc() };
+    let (producer, consumer) = queue.split();
+    let layout = Layout::new(Panel::Size480x320, Orientation::Landscape);
+    let lcd = FrameBuffer::new(&layout);
+    let mut display = Display::new(lcd, consumer, layout, sample_rate, &settings)
+        .unwrap_or_else(|_| fail("Display failed"));
+
+    let filter = EcgFilter::new(
+        sample_rate,
+        settings.filter,
+        settings.mains,
+        settings.rejection,
+    );
+    let data;
+    let mut source = if args[1].ends_with(".hea") {
+        let text = String::from_utf8(read(&args[1])).unwrap_or_else(|_| fail("Invalid header"));
+        let header =
+            Header::parse(&text).unwrap_or_else(|err| fail(&format!("Invalid header: {:?}", err)));
+        let file = header
+            .signals()
+            .first()
+            .unwrap_or_else(|| fail("No signal in the record"))
+            .file;
+        let path = Path::new(&args[1]).with_file_name(file);
+        data = read(&path.to_string_lossy());
+        let playback = Playback::new(&header, 0, &data, producer, filter, sample_rate)
+            .unwrap_or_else(|err| fail(&format!("Cannot play the record: {:?}", err)));
+        Source::Record(playback)
+    } else {
+        Source::Samples {
+            samples: read_samples(&args[1]).into_iter(),
+            filter,
+            producer,
+        }
+    };
+    let mut detector = QrsDetector::new(sample_rate);
+    let mut classifier = BeatClassifier::new(sample_rate);
+    let mut heart_rate = HeartRate::new(sample_rate, Averaging::Beats4);
+
+    let frame_len = (sample_rate / FRAME_RATE) as usize;
+    let snapshot_len = (sample_rate * interval) as usize;
+    let mut end = 0;
+    let mut last = false;
+    while !last {
+        let start = end;
+        for _ in 0..frame_len {
+            let sample = match source.next() {
+                Some(sample) => sample,
+                None => {
+                    last = true;
+                    break;
+                }
+            };
+            end += 1;
+            classifier.push(sample);
+            if let Some(beat) = detector.process(sample) {
+                let class = classifier.classify(beat);
+                display.annotate(Annotation::beat(beat, class));
+                if let Some(bpm) = heart_rate.beat(beat) {
+                    display
+                        .update_bpm(Some(bpm))
+                        .unwrap_or_else(|_| fail("Display failed"));
+                    display.add_beat(beat, bpm);
+                }
+            }
+        }
+        display.frame().unwrap_or_else(|_| fail("Display failed"));
+
+        if end / snapshot_len != start / snapshot_len || (last && end > 0) {
+            let path = format!("{}-{}.ppm", prefix, end as u32 / sample_rate);
+            write_ppm(&path, display.lcd());
+        }
+    }
+}
+
+// Binary PPM with 8 bits per channel
+fn write_ppm(path: &str, lcd: &FrameBuffer) {
+    let file = fs::File::create(path)
+        .unwrap_or_else(|err| fail(&format!("Cannot create {}: {}", path, err)));
+    let mut writer = BufWriter::new(file);
+    let mut data = format!("P6\n{} {}\n255\n", lcd.width(), lcd.height()).into_bytes();
+    for y in 0..lcd.height() {
+        for x in 0..lcd.width() {
+            let color = lcd.pixel(x, y);
+            data.push(color.r() << 3 | color.r() >> 2);
+            data.push(color.g() << 2 | color.g() >> 4);
+            data.push(color.b() << 3 | color.b() >> 2);
+        }
+    }
+    writer
+        .write_all(&data)
+        .and_then(|_| writer.flush())
+        .unwrap_or_else(|err| fail(&format!("Cannot write {}: {}", path, err)));
+}
+
+fn read(path: &str) -> Vec<u8> {
+    fs::read(path).unwrap_or_else(|err| fail(&format!("Cannot read {}: {}", path, err)))
+}
+
+fn read_samples(path: &str) -> Vec<i32> {
+    String::from_utf8(read(path))
+        .unwrap_or_else(|_| fail(&format!("Invalid text in {}", path)))
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            line.parse()
+                .unwrap_or_else(|_| fail(&format!("Invalid sample: {}", line)))
+        })
+        .collect()
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}